@@ -0,0 +1,133 @@
+//! Hand-written alt text for individual frames, loaded from a CSV file.
+//!
+//! Each row pairs a frame number with its description:
+//!
+//! ```csv
+//! frame,alt_text
+//! 1,"A dark hallway, lit by a single flickering bulb."
+//! 2,The same hallway from the other end.
+//! ```
+//!
+//! The header row is optional. Descriptions containing commas, quotes, or line
+//! breaks must be quoted, with quotes inside doubled as `""`. Frames without a
+//! row fall back to the alt text template.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use log::*;
+
+/// Alt text for each frame that has one, keyed by frame number.
+#[derive(Debug, Clone)]
+pub struct AltTextMap {
+    by_frame: HashMap<u32, String>,
+}
+
+impl AltTextMap {
+    /// Load and validate an alt text CSV file.
+    ///
+    /// Every row must have a frame number and a non-empty description, and
+    /// each frame may only appear once.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read alt text file {}", path.display()))?;
+        let rows = parse_csv(&content)
+            .with_context(|| format!("Failed to parse alt text file {}", path.display()))?;
+
+        let mut by_frame = HashMap::new();
+        for (index, row) in rows.into_iter().enumerate() {
+            let row_number = index + 1;
+            let [frame, alt_text] = <[String; 2]>::try_from(row).map_err(|row| {
+                anyhow::anyhow!(
+                    "Row {} of {} has {} fields, expected a frame and its alt text",
+                    row_number,
+                    path.display(),
+                    row.len()
+                )
+            })?;
+
+            let Ok(frame) = frame.trim().parse::<u32>() else {
+                // Allow a header row naming the columns
+                if index == 0 {
+                    continue;
+                }
+                bail!(
+                    "Invalid frame number '{}' in row {} of {}",
+                    frame,
+                    row_number,
+                    path.display()
+                );
+            };
+
+            let alt_text = alt_text.trim();
+            if alt_text.is_empty() {
+                bail!("Frame {} has empty alt text in {}", frame, path.display());
+            }
+            if by_frame.insert(frame, alt_text.to_string()).is_some() {
+                bail!("Frame {} appears twice in {}", frame, path.display());
+            }
+        }
+
+        info!(
+            "Loaded alt text for {} frames from {}",
+            by_frame.len(),
+            path.display()
+        );
+        Ok(Self { by_frame })
+    }
+
+    /// The alt text written for `frame`, if any.
+    pub fn get(&self, frame: u32) -> Option<&str> {
+        self.by_frame.get(&frame).map(String::as_str)
+    }
+}
+
+/// Split CSV content into rows of fields, skipping blank lines.
+fn parse_csv(content: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                let field = std::mem::take(&mut field);
+                end_row(&mut rows, std::mem::take(&mut row), field);
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        bail!("Unterminated quoted field");
+    }
+    end_row(&mut rows, row, field);
+    Ok(rows)
+}
+
+/// Finish a row with its last field, dropping it if the line was blank.
+fn end_row(rows: &mut Vec<Vec<String>>, mut row: Vec<String>, field: String) {
+    if row.is_empty() && field.trim().is_empty() {
+        return;
+    }
+    row.push(field);
+    rows.push(row);
+}
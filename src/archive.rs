@@ -0,0 +1,84 @@
+//! Local archive of posted frames.
+//!
+//! Keeps an exact copy of every JPEG that was published, after any recompression,
+//! so there is a record of what actually went out. Archived files are named with
+//! the post timestamp first so that a plain name sort is also chronological.
+
+use std::path::Path;
+
+use anyhow::Context;
+use log::*;
+
+/// Write a posted frame's images to the archive directory.
+///
+/// Creates the directory if needed, then saves the image as
+/// `<timestamp>_frame_<number>.<extension>`, or as
+/// `<timestamp>_frame_<number>_tile_<n>.<extension>` when the frame was posted
+/// as several tiles. When `max_files` is set, the oldest archived files beyond
+/// that limit are removed afterwards.
+pub async fn archive_frame(
+    dir: &Path,
+    frame: u32,
+    jpeg_images: &[Vec<u8>],
+    extension: &str,
+    max_files: Option<usize>,
+) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create archive directory {}", dir.display()))?;
+
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    for (index, jpeg_data) in jpeg_images.iter().enumerate() {
+        let path = match jpeg_images.len() {
+            1 => dir.join(format!("{timestamp}_frame_{frame}.{extension}")),
+            _ => dir.join(format!(
+                "{timestamp}_frame_{frame}_tile_{}.{extension}",
+                index + 1
+            )),
+        };
+
+        tokio::fs::write(&path, jpeg_data)
+            .await
+            .with_context(|| format!("Failed to write archived frame to {}", path.display()))?;
+        debug!("Archived frame {} to {}", frame, path.display());
+    }
+
+    if let Some(max_files) = max_files {
+        prune_archive(dir, max_files).await?;
+    }
+
+    Ok(())
+}
+
+/// Remove the oldest archived frames so at most `max_files` remain.
+async fn prune_archive(dir: &Path, max_files: usize) -> anyhow::Result<()> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to read archive directory {}", dir.display()))?;
+    let mut archived = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let extension = path.extension().and_then(|s| s.to_str());
+        if path.is_file() && matches!(extension, Some("jpg" | "avif")) {
+            archived.push(path);
+        }
+    }
+
+    if archived.len() <= max_files {
+        return Ok(());
+    }
+
+    // Names start with the timestamp, so sorting puts the oldest first
+    archived.sort();
+    let excess = archived.len() - max_files;
+
+    for path in archived.into_iter().take(excess) {
+        tokio::fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to prune archived frame {}", path.display()))?;
+        debug!("Pruned archived frame {}", path.display());
+    }
+
+    Ok(())
+}
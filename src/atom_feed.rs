@@ -0,0 +1,320 @@
+//! Atom feed of recently posted frames, for following the bot by RSS reader.
+//!
+//! Enabled by setting `ATOM_FEED_FILE`. After each posted frame an entry is
+//! added to the front of the feed, linking to the post and embedding its images
+//! with their alt text, and the oldest entries are dropped beyond
+//! `ATOM_FEED_MAX_ENTRIES`. The entries are kept in [`ATOM_FEED_ENTRIES_FILE`]
+//! and the whole feed is rewritten from them each time.
+
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+
+use anyhow::Context;
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    audit_log::blob_cid,
+    config::{
+        Config,
+        ATOM_FEED_ENTRIES_FILE,
+    },
+    pending_post::UploadedImage,
+};
+
+/// A posted frame as it appears in the feed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct FeedEntry {
+    frame: u32,
+    total_frames: u32,
+    /// AT URI of the post
+    uri: String,
+    /// When the post was created, in RFC 3339 format
+    published: String,
+    images: Vec<FeedImage>,
+}
+
+/// An image attached to a feed entry.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct FeedImage {
+    /// CID of the uploaded image blob
+    cid: String,
+    alt_text: String,
+    /// Whether the blob is a video rather than an image
+    #[serde(default)]
+    video: bool,
+}
+
+/// Add a frame that was just posted to the feed, if one is configured.
+///
+/// Failures are only logged since the post already exists.
+pub fn record_post(
+    config: &Config,
+    frame: u32,
+    total_frames: u32,
+    images: &[UploadedImage],
+    uri: &str,
+) {
+    let Some(feed_file) = &config.atom_feed_file else {
+        return;
+    };
+
+    let entry = FeedEntry {
+        frame,
+        total_frames,
+        uri: uri.to_string(),
+        published: chrono::Utc::now().to_rfc3339(),
+        images: images
+            .iter()
+            .map(|image| FeedImage {
+                cid: blob_cid(&image.blob),
+                alt_text: image.alt_text.clone(),
+                video: image.video,
+            })
+            .collect(),
+    };
+
+    if let Err(e) = add_entry(config, feed_file, entry) {
+        warn!("Failed to update Atom feed: {:#}", e);
+    }
+}
+
+fn add_entry(config: &Config, feed_file: &Path, entry: FeedEntry) -> anyhow::Result<()> {
+    let entries_path = Path::new(ATOM_FEED_ENTRIES_FILE);
+    let mut entries = load_entries(entries_path);
+    entries.insert(0, entry);
+    entries.truncate(config.atom_feed_max_entries);
+
+    let json =
+        serde_json::to_string_pretty(&entries).context("Failed to serialize feed entries")?;
+    write_file(entries_path, json.as_bytes())?;
+    write_file(
+        feed_file,
+        render_feed(&config.movie_name, &entries).as_bytes(),
+    )?;
+
+    debug!("Updated Atom feed {}", feed_file.display());
+    Ok(())
+}
+
+/// Load the saved entries, newest first, starting over if they can't be read.
+fn load_entries(path: &Path) -> Vec<FeedEntry> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            warn!("Failed to read feed entries {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Failed to parse feed entries {}: {}", path.display(), e);
+        Vec::new()
+    })
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create parent directories for {}", path.display())
+        })?;
+    }
+
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Render the Atom document for `entries`, newest first.
+fn render_feed(movie_name: &str, entries: &[FeedEntry]) -> String {
+    // Every entry comes from the same account, so any of them identifies it
+    let profile = entries
+        .first()
+        .and_then(|entry| post_location(&entry.uri))
+        .map(|(did, _)| format!("https://bsky.app/profile/{}", did));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!(
+        "  <title>{}</title>\n",
+        escape(&format!("Every frame of {}", movie_name))
+    ));
+    // Atom requires an id, which falls back to one made up from the movie name
+    let id = profile
+        .clone()
+        .unwrap_or_else(|| format!("tag:every-frame,2024:{}", tag_name(movie_name)));
+    xml.push_str(&format!("  <id>{}</id>\n", escape(&id)));
+    if let Some(profile) = &profile {
+        xml.push_str(&format!("  <link href=\"{}\"/>\n", escape(profile)));
+    }
+    if let Some(latest) = entries.first() {
+        xml.push_str(&format!("  <updated>{}</updated>\n", latest.published));
+    }
+
+    for entry in entries {
+        xml.push_str(&render_entry(entry));
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn render_entry(entry: &FeedEntry) -> String {
+    let location = post_location(&entry.uri);
+
+    let content = entry
+        .images
+        .iter()
+        .map(|image| match &location {
+            Some((did, _)) if image.video => format!(
+                "<p><video src=\"{}\" poster=\"{}\" title=\"{}\" controls loop muted></video></p>",
+                escape(&video_url(did, &image.cid)),
+                escape(&video_thumbnail_url(did, &image.cid)),
+                escape(&image.alt_text)
+            ),
+            Some((did, _)) => format!(
+                "<p><img src=\"{}\" alt=\"{}\"/></p>",
+                escape(&image_url(did, &image.cid)),
+                escape(&image.alt_text)
+            ),
+            None => format!("<p>{}</p>", escape(&image.alt_text)),
+        })
+        .collect::<String>();
+
+    let mut xml = String::from("  <entry>\n");
+    xml.push_str(&format!(
+        "    <title>Frame {} of {}</title>\n",
+        entry.frame, entry.total_frames
+    ));
+    // An AT URI identifies the post for good, otherwise the frame and the time
+    // it was posted tell entries apart
+    let id = match location {
+        Some(_) => entry.uri.clone(),
+        None => format!(
+            "tag:every-frame,{}:frame-{}",
+            entry.published.get(..10).unwrap_or("2024"),
+            entry.frame
+        ),
+    };
+    xml.push_str(&format!("    <id>{}</id>\n", escape(&id)));
+    if let Some((did, rkey)) = &location {
+        xml.push_str(&format!(
+            "    <link href=\"{}\"/>\n",
+            escape(&format!("https://bsky.app/profile/{}/post/{}", did, rkey))
+        ));
+    }
+    xml.push_str(&format!("    <published>{}</published>\n", entry.published));
+    xml.push_str(&format!("    <updated>{}</updated>\n", entry.published));
+    // The HTML is escaped again to embed it as text
+    xml.push_str(&format!(
+        "    <content type=\"html\">{}</content>\n",
+        escape(&content)
+    ));
+    xml.push_str("  </entry>\n");
+    xml
+}
+
+/// The DID and record key from a post's AT URI, like
+/// `at://did:plc:abc/app.bsky.feed.post/3k2a`.
+fn post_location(uri: &str) -> Option<(&str, &str)> {
+    let mut parts = uri.strip_prefix("at://")?.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(did), Some(_collection), Some(rkey)) => Some((did, rkey)),
+        _ => None,
+    }
+}
+
+/// Full size URL of an uploaded image on the Bluesky CDN.
+fn image_url(did: &str, cid: &str) -> String {
+    format!(
+        "https://cdn.bsky.app/img/feed_fullsize/plain/{}/{}@jpeg",
+        did, cid
+    )
+}
+
+/// Playlist URL of an uploaded video on the Bluesky video CDN.
+fn video_url(did: &str, cid: &str) -> String {
+    format!("https://video.bsky.app/watch/{}/{}/playlist.m3u8", did, cid)
+}
+
+/// Thumbnail URL of an uploaded video on the Bluesky video CDN.
+fn video_thumbnail_url(did: &str, cid: &str) -> String {
+    format!("https://video.bsky.app/watch/{}/{}/thumbnail.jpg", did, cid)
+}
+
+/// Lowercase `name` with runs of anything but letters and digits turned into
+/// single dashes, for use in a `tag:` URI.
+fn tag_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(uri: &str, video: bool) -> FeedEntry {
+        FeedEntry {
+            frame: 7,
+            total_frames: 10,
+            uri: uri.to_string(),
+            published: "2024-05-01T12:00:00+00:00".to_string(),
+            images: vec![FeedImage {
+                cid: "bafkrei".to_string(),
+                alt_text: "A frame".to_string(),
+                video,
+            }],
+        }
+    }
+
+    #[test]
+    fn feed_is_identified_by_the_profile() {
+        let xml = render_feed(
+            "Spirited Away",
+            &[entry("at://did:plc:abc/app.bsky.feed.post/3k2a", false)],
+        );
+        assert!(xml.contains("<id>https://bsky.app/profile/did:plc:abc</id>"));
+        assert!(xml.contains("<id>at://did:plc:abc/app.bsky.feed.post/3k2a</id>"));
+    }
+
+    #[test]
+    fn feed_and_entries_always_have_an_id() {
+        let xml = render_feed("Howl's Moving Castle", &[entry("not-a-uri", false)]);
+        assert!(xml.contains("<id>tag:every-frame,2024:howl-s-moving-castle</id>"));
+        assert!(xml.contains("<id>tag:every-frame,2024-05-01:frame-7</id>"));
+
+        let xml = render_feed("Howl's Moving Castle", &[]);
+        assert!(xml.contains("<id>tag:every-frame,2024:howl-s-moving-castle</id>"));
+    }
+
+    #[test]
+    fn videos_link_to_the_video_cdn() {
+        let xml = render_entry(&entry("at://did:plc:abc/app.bsky.feed.post/3k2a", true));
+        assert!(xml.contains("https://video.bsky.app/watch/did:plc:abc/bafkrei/playlist.m3u8"));
+        assert!(!xml.contains("cdn.bsky.app/img"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Append-only JSON Lines log of every post made.
+//!
+//! Each line records exactly what was published, so the file doubles as a
+//! durable, machine-readable post history separate from the regular logs.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Context;
+use bsky_sdk::api::types::{
+    BlobRef,
+    TypedBlobRef,
+};
+use serde::Serialize;
+
+use crate::pending_post::UploadedImage;
+
+/// A single post as recorded in the audit log.
+#[derive(Serialize, Debug)]
+pub struct AuditEntry<'a> {
+    /// When the post was created, in RFC 3339 format
+    pub timestamp: String,
+    /// Frame number, or `None` for posts outside the frame sequence
+    pub frame: Option<u32>,
+    /// AT URI of the created post
+    pub uri: &'a str,
+    /// Images attached to the post, in order
+    pub images: Vec<AuditImage<'a>>,
+}
+
+/// An image attached to an audited post.
+#[derive(Serialize, Debug)]
+pub struct AuditImage<'a> {
+    pub alt_text: &'a str,
+    pub width: u32,
+    pub height: u32,
+    /// CID of the uploaded image blob
+    pub blob_cid: String,
+}
+
+impl<'a> AuditEntry<'a> {
+    /// Describe a post that was just created.
+    pub fn new(frame: Option<u32>, images: &'a [UploadedImage], uri: &'a str) -> Self {
+        let images = images
+            .iter()
+            .map(|image| AuditImage {
+                alt_text: &image.alt_text,
+                width: image.dimensions.width,
+                height: image.dimensions.height,
+                blob_cid: blob_cid(&image.blob),
+            })
+            .collect();
+
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            frame,
+            uri,
+            images,
+        }
+    }
+}
+
+/// Append an entry to the audit log as a single JSON line.
+///
+/// The line is written with one append-mode write, so readers never see a
+/// partially written entry interleaved with another.
+pub fn append(path: &Path, entry: &AuditEntry) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(entry).context("Failed to serialize audit log entry")?;
+    line.push(b'\n');
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create parent directories for {}", path.display())
+        })?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("Failed to append to audit log {}", path.display()))
+}
+
+/// The content identifier of an uploaded blob.
+pub fn blob_cid(blob: &BlobRef) -> String {
+    match blob {
+        BlobRef::Typed(TypedBlobRef::Blob(blob)) => blob.r#ref.0.to_string(),
+        BlobRef::Untyped(blob) => blob.cid.clone(),
+    }
+}
@@ -0,0 +1,66 @@
+//! Blobs already uploaded this run, so identical images aren't uploaded twice.
+//!
+//! Posting the same frame again, e.g. when bouncing back and forth through the
+//! movie, produces the same JPEG bytes. Entries are keyed by the account's DID
+//! along with the SHA-256 of the bytes, since a blob only exists in the repo it
+//! was uploaded to. The least recently used entry is evicted once the cache is
+//! full.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex,
+        PoisonError,
+    },
+};
+
+use bsky_sdk::api::types::BlobRef;
+
+/// Maximum number of blobs remembered.
+const CAPACITY: usize = 64;
+
+/// Cached blobs, most recently used last.
+static CACHE: Mutex<VecDeque<CachedBlob>> = Mutex::new(VecDeque::new());
+
+struct CachedBlob {
+    did: String,
+    digest: [u8; 32],
+    blob: BlobRef,
+}
+
+/// SHA-256 digest of image data, to look it up by.
+pub fn digest(data: &[u8]) -> [u8; 32] {
+    openssl::sha::sha256(data)
+}
+
+/// The blob uploaded to `did` for data with `digest`, if any.
+pub fn get(did: &str, digest: &[u8; 32]) -> Option<BlobRef> {
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let index = cache
+        .iter()
+        .position(|cached| cached.did == did && &cached.digest == digest)?;
+
+    let cached = cache.remove(index)?;
+    let blob = cached.blob.clone();
+    cache.push_back(cached);
+    Some(blob)
+}
+
+/// Remember the blob uploaded to `did` for data with `digest`.
+pub fn insert(did: &str, digest: [u8; 32], blob: BlobRef) {
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    cache.retain(|cached| !(cached.did == did && cached.digest == digest));
+    if cache.len() >= CAPACITY {
+        cache.pop_front();
+    }
+    cache.push_back(CachedBlob {
+        did: did.to_string(),
+        digest,
+        blob,
+    });
+}
+
+/// Forget every cached blob, in case one was garbage collected by the server.
+pub fn clear() {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
@@ -1,6 +1,13 @@
 //! Bluesky authentication and posting operations.
 
-use std::num::NonZeroU64;
+use std::{
+    num::NonZeroU64,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Instant,
+};
 
 use anyhow::Context;
 use bsky_sdk::{
@@ -15,49 +22,119 @@ use bsky_sdk::{
                     AspectRatio,
                     AspectRatioData,
                 },
+                external::{
+                    self,
+                    ExternalData,
+                },
                 images::{
                     self,
                     ImageData,
                 },
+                record,
+                record_with_media,
+                video,
+            },
+            feed::{
+                self,
+                get_author_feed,
+                post,
+                threadgate,
             },
-            feed::post,
         },
-        com::atproto::repo::upload_blob,
+        com::atproto::repo::{
+            get_record,
+            strong_ref,
+            upload_blob,
+        },
         types::{
-            string::Datetime,
+            string::{
+                AtIdentifier,
+                Cid,
+                Datetime,
+                RecordKey,
+            },
+            BlobRef,
+            Collection,
             Union,
         },
+        xrpc::{
+            self,
+            error::XrpcErrorKind,
+        },
     },
+    record::Record,
     BskyAgent,
 };
 use ipld_core::ipld::Ipld;
 use log::*;
 
+#[cfg(feature = "ocr")]
+use crate::ocr;
 use crate::{
+    atom_feed,
+    audit_log::{
+        self,
+        AuditEntry,
+    },
+    blob_cache,
     config::{
         Config,
-        FRAME_DATA_FILE,
+        PostRef,
+        ReplyGate,
+        ReplyRule,
+        INTRO_PROGRESS_FILE,
         MAX_RETRIES,
+        PENDING_POST_FILE,
+        REPLY_SEED_FILE,
         RETRY_DELAY,
         SESSION_FILE,
+        UPLOAD_RETRY_DELAY,
+        VERIFY_ATTEMPTS,
+        VERIFY_DELAY,
+        VERIFY_FEED_LIMIT,
     },
-    frame_info::FrameInfo,
+    credentials,
+    error::FrameError,
+    failures,
     frame_processing::{
         get_frame_as_jpeg,
-        get_total_frame_count,
+        get_frame_images,
+        get_image_as_jpeg,
+        load_total_frame_count,
         FrameDimensions,
+        ProcessedFrame,
+    },
+    hooks,
+    intro::{
+        self,
+        IntroProgress,
+    },
+    pending_post::{
+        CreatedRecord,
+        PendingPost,
+        UploadedImage,
     },
+    post_index,
+    posting,
+    run_stats,
+    stage_timings::StageTimings,
+    template,
+    timecode,
 };
 
 /// Create and authenticate a Bluesky agent.
 ///
-/// Sets up the agent with the provided credentials, performs initial
-/// authentication, and saves the session for future use.
+/// Sets up the agent with the configured credentials, performs initial
+/// authentication, and saves the session for future use. Falls back on any
+/// other configured app passwords if the primary one can't log in.
 pub async fn initialize_agent(config: &Config) -> anyhow::Result<BskyAgent> {
+    credentials::login(config).await
+}
+
+/// Log in with the given credentials and save the session.
+pub async fn login(identifier: &str, app_password: &str) -> anyhow::Result<BskyAgent> {
     let agent = BskyAgent::builder().build().await?;
-    agent
-        .login(&config.identifier, &config.app_password)
-        .await?;
+    agent.login(identifier, app_password).await?;
 
     agent
         .to_config()
@@ -69,18 +146,66 @@ pub async fn initialize_agent(config: &Config) -> anyhow::Result<BskyAgent> {
     Ok(agent)
 }
 
+/// Result of a single posting attempt that didn't fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostOutcome {
+    /// The frame was posted
+    Posted,
+    /// The frame was unusable, so the counter moved past it without posting
+    Skipped,
+}
+
+/// What a post embeds besides any quoted post.
+enum Media {
+    Images(Box<images::Main>),
+    Video(Box<video::Main>),
+    External(Box<external::Main>),
+}
+
+/// Processed images of a frame, kept after uploading them.
+struct FrameImages {
+    /// JPEG data of each image, for archiving
+    jpeg_images: Vec<Vec<u8>>,
+    /// Quality each image was recompressed at, or `None` for originals
+    qualities: Vec<Option<u8>>,
+}
+
 /// Post a frame with retry logic.
 ///
 /// Attempts to post a frame up to MAX_RETRIES times, with a delay
 /// between attempts. This handles temporary network issues and
 /// transient failures gracefully.
-pub async fn post_frame_task(movie_name: &str) {
+pub async fn post_frame_task(config: &Config) {
+    retry_post(config, || post_frame(config, None)).await;
+}
+
+/// Post a frame with retry logic using an already authenticated agent,
+/// instead of resuming the saved session.
+pub async fn post_frame_task_with_agent(config: &Config, agent: &BskyAgent) {
+    retry_post(config, || post_frame(config, Some(agent))).await;
+}
+
+/// Run a posting attempt up to MAX_RETRIES times until it doesn't fail,
+/// recording the outcome.
+pub async fn retry_post<F, Fut>(config: &Config, mut post: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<PostOutcome>>,
+{
+    let mut last_error = None;
     for attempt in 1..=MAX_RETRIES {
-        match post_frame(movie_name).await {
-            Ok(_) => {
+        match post().await {
+            Ok(PostOutcome::Posted) => {
+                failures::record_success();
+                run_stats::record_post();
                 info!("Frame posted successfully!");
                 return;
             }
+            Ok(PostOutcome::Skipped) => {
+                failures::record_success();
+                warn!("Frame was skipped without posting");
+                return;
+            }
             Err(e) => {
                 error!(
                     "Attempt {}/{} failed to post frame: {}",
@@ -90,10 +215,15 @@ pub async fn post_frame_task(movie_name: &str) {
                     warn!("Retrying in {} seconds...", RETRY_DELAY.as_secs());
                     tokio::time::sleep(RETRY_DELAY).await;
                 }
+                last_error = Some(e);
             }
         }
     }
     error!("Failed to post frame after {} attempts", MAX_RETRIES);
+
+    if let Some(e) = last_error {
+        failures::record_failure(config, &e);
+    }
 }
 
 /// Post a single frame to Bluesky.
@@ -101,114 +231,1293 @@ pub async fn post_frame_task(movie_name: &str) {
 /// Orchestrates the entire posting process: loads the current frame info,
 /// converts the frame to JPEG, uploads it to Bluesky, creates a post with
 /// the image, and updates the frame counter for next time. Also saves the
-/// session after successful posting to maintain authentication.
-pub async fn post_frame(movie_name: &str) -> anyhow::Result<()> {
+/// session after successful posting to maintain authentication, and archives
+/// the posted JPEG when an archive directory is configured.
+///
+/// Frames that can never be posted, such as ones failing checksum
+/// verification, are skipped by advancing the counter rather than retried, as
+/// are frames already posted `MAX_FRAME_REPEATS` times in a row.
+///
+/// Posts with `agent` when given, otherwise resumes the saved session.
+pub async fn post_frame(config: &Config, agent: Option<&BskyAgent>) -> anyhow::Result<PostOutcome> {
     info!("Preparing to post a frame...");
 
-    let agent = load_agent().await?;
-    let total_frames = get_total_frame_count().await?;
-    let mut frame_info = FrameInfo::load_or_create(FRAME_DATA_FILE, total_frames, 1)?;
+    let mut timings = StageTimings::new();
+    let agent = match agent {
+        Some(agent) => agent.clone(),
+        None => load_agent(config).await?,
+    };
+
+    if let Some((progress, card, card_count)) = next_intro_card(config)? {
+        return post_intro_card(&agent, config, progress, &card, card_count).await;
+    }
+
+    let total_frames = load_total_frame_count(config).await?;
+    let mut frame_info = posting::load_frame_info(config, total_frames)?;
+
+    if posting::skip_repeated_frame(&mut frame_info, config)? {
+        return Ok(PostOutcome::Skipped);
+    }
+    let frame = frame_info.current_frame;
+    let (mut pending, frame_images) = match PendingPost::load_for_frame(PENDING_POST_FILE, frame) {
+        Some(pending) => {
+            info!("Reusing blobs already uploaded for frame {}", frame);
+            (pending, None)
+        }
+        None => {
+            let upload = upload_frame(&agent, config, frame, total_frames, &mut timings).await;
+            let Some((pending, frame_images)) =
+                posting::skip_unpostable_frame(upload, &mut frame_info, config)?
+            else {
+                return Ok(PostOutcome::Skipped);
+            };
+
+            // Not fatal, the upload just won't be reused if this attempt is interrupted
+            if let Err(e) = pending.save(PENDING_POST_FILE) {
+                warn!("{:#}", e);
+            }
+            (pending, Some(frame_images))
+        }
+    };
+
+    // Only the first post after configuring a reply root goes under it
+    let reply_to = config
+        .reply_root
+        .as_ref()
+        .filter(|root| !reply_seeded(root));
+    if let Some(root) = reply_to {
+        info!("Posting frame {} as a reply to {}", frame, root.uri);
+    }
 
-    let processed_frame = get_frame_as_jpeg(frame_info.current_frame).await?;
-    let blob = upload_frame_blob(&agent, processed_frame.jpeg_data).await?;
+    let reused_record = pending.record.is_some();
+    let record = match pending.record.clone() {
+        // Created by an attempt that failed or was interrupted afterwards, so
+        // don't post again
+        Some(record) => {
+            info!("Frame {} was already posted as {}", frame, record.uri);
+            record
+        }
+        None => {
+            let post_data = create_post_data(
+                config,
+                "",
+                Some((frame, total_frames)),
+                frame_info.posts_left(config.frame_step, config.scenes.as_ref()),
+                &pending.images,
+                reply_to,
+            )?;
+
+            let started = Instant::now();
+            let record = match agent.create_record(post_data).await {
+                Ok(record) => record,
+                Err(e) => {
+                    // A reused blob may have expired server-side, so upload afresh next attempt
+                    blob_cache::clear();
+                    if frame_images.is_none() {
+                        PendingPost::clear(PENDING_POST_FILE)?;
+                    }
+                    return Err(e).context("Failed to create post record");
+                }
+            };
+            timings.record("create record", started);
+
+            CreatedRecord {
+                uri: record.uri.clone(),
+                cid: record.cid.clone(),
+            }
+        }
+    };
+
+    // Keep the record with the pending upload until the counter has advanced, so
+    // a retry or restart carries on with this post rather than creating another
+    if pending.record.is_none() {
+        pending.record = Some(record.clone());
+        if let Err(e) = pending.save(PENDING_POST_FILE) {
+            warn!("{:#}", e);
+        }
+    }
+
+    if config.verify_posted {
+        let started = Instant::now();
+        if let Err(e) = verify_posted(&agent, &record.uri).await {
+            error!("Post verification failed for frame {}: {:#}", frame, e);
+            if !reused_record {
+                return Err(e).context("Post verification failed");
+            }
+
+            // Verifying the same post again would fail the same way every attempt
+            if !record_exists(&agent, &record.uri).await? {
+                warn!(
+                    "Post {} for frame {} no longer exists, posting the frame again next attempt",
+                    record.uri, frame
+                );
+                pending.record = None;
+                if let Err(e) = pending.save(PENDING_POST_FILE) {
+                    warn!("{:#}", e);
+                }
+                return Err(e).context("Post verification failed and the post is gone");
+            }
+
+            // The post is live, so posting again would only duplicate it. Report it
+            // through the failure hook and carry on as posted.
+            let error = anyhow::anyhow!(
+                "Post {} for frame {} exists but never appeared in the feed",
+                record.uri,
+                frame
+            );
+            error!("{:#}, moving on without verifying it", error);
+            hooks::post_failed(config, Some(frame), &error, false);
+        } else {
+            timings.record("verify post", started);
+        }
+    }
+
+    // The post is live from here on, so only advancing the counter may fail the
+    // attempt. Anything after it is logged instead to avoid re-posting the frame.
+    frame_info.record_posted();
+    posting::advance_frame(&mut frame_info, config)?;
+    if let Err(e) = PendingPost::clear(PENDING_POST_FILE) {
+        warn!("{:#}", e);
+    }
+
+    // Save session after successful post
+    if let Err(e) = agent
+        .to_config()
+        .await
+        .save(&FileStore::new(SESSION_FILE))
+        .await
+    {
+        warn!("Failed to save session after posting: {}", e);
+    }
+
+    if let Some(root) = reply_to
+        && let Err(e) = mark_reply_seeded(root)
+    {
+        warn!("{:#}", e);
+    }
+
+    gate_replies(&agent, config, &record.uri).await;
+    audit_post(config, Some(frame), &pending.images, &record.uri);
+    index_post(config, frame, &record.uri, &record.cid);
+    atom_feed::record_post(config, frame, total_frames, &pending.images, &record.uri);
+    hooks::post_succeeded(config, frame, Some(&record.uri));
+
+    // Blobs reused from before a restart were processed by an earlier run
+    if let Some(frame_images) = &frame_images {
+        frame_images
+            .qualities
+            .iter()
+            .for_each(|quality| run_stats::record_quality(*quality));
+    }
+
+    match &frame_images {
+        Some(frame_images) => {
+            posting::archive_posted_frame(config, frame, &frame_images.jpeg_images).await
+        }
+        None if config.archive_dir.is_some() => warn!(
+            "Frame {} was uploaded before a restart and can't be archived",
+            frame
+        ),
+        None => {}
+    }
+
+    timings.log(&format!("frame {}", frame));
+    info!("Successfully posted frame {}/{}", frame, total_frames);
+    Ok(PostOutcome::Posted)
+}
+
+/// Post a specific frame without touching the frame counter.
+///
+/// Used for manually posting a chosen frame. Skips the pending-post and archive
+/// bookkeeping and the finale countdown of the scheduled path since the frame
+/// isn't part of the sequence.
+pub async fn post_specific_frame(
+    agent: &BskyAgent,
+    config: &Config,
+    frame: u32,
+) -> anyhow::Result<()> {
+    let mut timings = StageTimings::new();
+    let total_frames = load_total_frame_count(config).await?;
+    let (upload, _) = upload_frame(agent, config, frame, total_frames, &mut timings).await?;
 
     let post_data = create_post_data(
-        movie_name,
-        &frame_info,
-        total_frames,
-        blob,
-        &processed_frame.dimensions,
+        config,
+        "",
+        Some((frame, total_frames)),
+        None,
+        &upload.images,
+        None,
     )?;
+    let started = Instant::now();
+    let record = agent
+        .create_record(post_data)
+        .await
+        .context("Failed to create post record")?;
+    timings.record("create record", started);
 
-    agent
+    gate_replies(agent, config, &record.uri).await;
+    audit_post(config, Some(frame), &upload.images, &record.uri);
+    index_post(config, frame, &record.uri, &record.cid);
+    timings.log(&format!("frame {}", frame));
+
+    info!("Successfully posted frame {}/{}", frame, total_frames);
+    Ok(())
+}
+
+/// Post a frame and delete it straight away to check posting works end to end.
+///
+/// Runs the same processing, upload, and record creation as a scheduled post,
+/// then deletes the record. The frame counter, audit log, and archive are
+/// left untouched.
+pub async fn self_test(agent: &BskyAgent, config: &Config, frame: u32) -> anyhow::Result<()> {
+    let mut timings = StageTimings::new();
+    let total_frames = load_total_frame_count(config).await?;
+    let (upload, _) = upload_frame(agent, config, frame, total_frames, &mut timings).await?;
+    info!("Uploaded frame {}", frame);
+
+    let post_data = create_post_data(
+        config,
+        "",
+        Some((frame, total_frames)),
+        None,
+        &upload.images,
+        None,
+    )?;
+    let record = agent
         .create_record(post_data)
         .await
         .context("Failed to create post record")?;
+    info!("Created test post {}", record.uri);
 
-    // Save session after successful post
-    agent
-        .to_config()
+    agent.delete_record(&record.uri).await.with_context(|| {
+        format!(
+            "Failed to delete test post {}, remove it by hand",
+            record.uri
+        )
+    })?;
+    info!("Deleted test post {}", record.uri);
+
+    timings.log(&format!("frame {}", frame));
+    Ok(())
+}
+
+/// Post an arbitrary image with some text, outside the frame sequence.
+///
+/// Used for announcements like maintenance notices. The image goes through the
+/// same compression as frames, and the text doubles as its alt text.
+pub async fn post_announcement(
+    agent: &BskyAgent,
+    config: &Config,
+    image_path: &Path,
+    text: &str,
+) -> anyhow::Result<()> {
+    post_image(agent, config, image_path, text, text).await?;
+
+    info!("Successfully posted announcement {}", image_path.display());
+    Ok(())
+}
+
+/// Post a hand-picked set of frames together as one multi-image post.
+///
+/// Each frame is processed whole, even when a tile layout is configured, and
+/// gets its usual alt text. The frame counter is left untouched.
+pub async fn post_frame_set(
+    agent: &BskyAgent,
+    config: &Config,
+    frames: &[u32],
+    text: &str,
+) -> anyhow::Result<()> {
+    let mut timings = StageTimings::new();
+    let total_frames = load_total_frame_count(config).await?;
+
+    let mut images = Vec::with_capacity(frames.len());
+    for (index, &frame) in frames.iter().enumerate() {
+        let processed_image = get_frame_as_jpeg(frame, config, &mut timings).await?;
+        let average_color = processed_image.average_color.as_deref().unwrap_or_default();
+        let alt_text = frame_alt_text(config, frame, total_frames, average_color);
+
+        space_out_upload(config, index).await;
+        let started = Instant::now();
+        images.push(UploadedImage {
+            blob: upload_frame_blob(agent, config, processed_image.jpeg_data).await?,
+            dimensions: processed_image.dimensions,
+            alt_text,
+            video: false,
+        });
+        timings.record("upload", started);
+    }
+
+    let post_data = create_post_data(config, text, None, None, &images, None)?;
+    let started = Instant::now();
+    let record = agent
+        .create_record(post_data)
         .await
-        .save(&FileStore::new(SESSION_FILE))
+        .context("Failed to create post record")?;
+    timings.record("create record", started);
+
+    gate_replies(agent, config, &record.uri).await;
+    audit_post(config, None, &images, &record.uri);
+
+    let frame_list = frames
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    timings.log(&format!("frames {}", frame_list));
+    info!("Successfully posted frames {}", frame_list);
+    Ok(())
+}
+
+/// Post an arbitrary image outside the frame sequence.
+///
+/// The image goes through the same compression as frames before being
+/// uploaded and posted with `body` as the post text.
+async fn post_image(
+    agent: &BskyAgent,
+    config: &Config,
+    image_path: &Path,
+    body: &str,
+    alt_text: &str,
+) -> anyhow::Result<()> {
+    let mut timings = StageTimings::new();
+    let processed_image = get_image_as_jpeg(image_path, config, &mut timings).await?;
+
+    let started = Instant::now();
+    let images = [UploadedImage {
+        blob: upload_frame_blob(agent, config, processed_image.jpeg_data).await?,
+        dimensions: processed_image.dimensions,
+        alt_text: alt_text.to_string(),
+        video: false,
+    }];
+    let started = timings.record("upload", started);
+
+    let post_data = create_post_data(config, body, None, None, &images, None)?;
+    let record = agent
+        .create_record(post_data)
         .await
-        .context("Failed to save session after posting")?;
+        .context("Failed to create post record")?;
+    timings.record("create record", started);
 
-    frame_info.increment(FRAME_DATA_FILE)?;
+    gate_replies(agent, config, &record.uri).await;
+    audit_post(config, None, &images, &record.uri);
+    timings.log(&image_path.display().to_string());
+    Ok(())
+}
 
-    info!(
-        "Successfully posted frame {}/{}",
-        frame_info.current_frame, total_frames
+/// Find the intro card to post next, if an intro is underway.
+///
+/// Returns the progress, the card's path, and how many cards there are. An
+/// intro whose cards have run out, e.g. because some were removed, is ended.
+fn next_intro_card(config: &Config) -> anyhow::Result<Option<(IntroProgress, PathBuf, usize)>> {
+    let Some(intro_dir) = &config.intro_dir else {
+        return Ok(None);
+    };
+    let progress_path = Path::new(INTRO_PROGRESS_FILE);
+    let Some(progress) = IntroProgress::load(progress_path)? else {
+        return Ok(None);
+    };
+
+    let cards = intro::list_cards(intro_dir)?;
+    match cards.get(progress.next_card) {
+        Some(card) => Ok(Some((progress, card.clone(), cards.len()))),
+        None => {
+            warn!(
+                "No intro card {} in {}, ending the intro",
+                progress.next_card + 1,
+                intro_dir.display()
+            );
+            IntroProgress::finish(progress_path)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Post an intro card and move on to the next one.
+///
+/// Cards that can never be posted are skipped like unusable frames.
+async fn post_intro_card(
+    agent: &BskyAgent,
+    config: &Config,
+    progress: IntroProgress,
+    card: &Path,
+    card_count: usize,
+) -> anyhow::Result<PostOutcome> {
+    let number = progress.next_card + 1;
+    let alt_text = format!(
+        "{} intro card {} of {}",
+        config.movie_name, number, card_count
     );
-    Ok(())
+
+    let outcome = match post_image(agent, config, card, "", &alt_text).await {
+        Ok(()) => PostOutcome::Posted,
+        Err(e) if FrameError::is_skippable(&e) => {
+            error!("Skipping intro card {}: {:#}", card.display(), e);
+            PostOutcome::Skipped
+        }
+        Err(e) => return Err(e),
+    };
+
+    progress.advance(Path::new(INTRO_PROGRESS_FILE), card_count)?;
+
+    if outcome == PostOutcome::Posted {
+        info!("Successfully posted intro card {}/{}", number, card_count);
+    }
+    Ok(outcome)
+}
+
+/// Whether the first post has already been made under this reply root.
+fn reply_seeded(root: &PostRef) -> bool {
+    std::fs::read_to_string(REPLY_SEED_FILE).is_ok_and(|uri| uri.trim() == root.uri)
+}
+
+/// Record that the first post was made under this reply root.
+///
+/// Stores the root's URI, so configuring a different root starts over.
+fn mark_reply_seeded(root: &PostRef) -> anyhow::Result<()> {
+    std::fs::write(REPLY_SEED_FILE, &root.uri)
+        .with_context(|| format!("Failed to record reply root in {}", REPLY_SEED_FILE))
+}
+
+/// Build a reply reference using `root` as both the thread root and parent.
+fn reply_ref(root: &PostRef) -> anyhow::Result<post::ReplyRef> {
+    let strong_ref = post_strong_ref(root)?;
+    Ok(post::ReplyRefData {
+        parent: strong_ref.clone(),
+        root: strong_ref,
+    }
+    .into())
+}
+
+/// Build a strong reference to an existing post.
+fn post_strong_ref(post: &PostRef) -> anyhow::Result<strong_ref::Main> {
+    Ok(strong_ref::MainData {
+        cid: post
+            .cid
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid CID '{}' of {}: {}", post.cid, post.uri, e))?,
+        uri: post.uri.clone(),
+    }
+    .into())
+}
+
+/// Append a post that was just created to the audit log, if one is configured.
+///
+/// Failures are only logged since the post already exists.
+fn audit_post(config: &Config, frame: Option<u32>, images: &[UploadedImage], uri: &str) {
+    let Some(audit_log_file) = &config.audit_log_file else {
+        return;
+    };
+
+    let entry = AuditEntry::new(frame, images, uri);
+    if let Err(e) = audit_log::append(audit_log_file, &entry) {
+        warn!("{:#}", e);
+    }
+}
+
+/// Save the post just made for `frame` to the post index, if one is
+/// configured.
+///
+/// Failures are only logged since the post already exists.
+fn index_post(config: &Config, frame: u32, uri: &str, cid: &Cid) {
+    let Some(post_index_file) = &config.post_index_file else {
+        return;
+    };
+
+    let cid = cid.as_ref().to_string();
+    if let Err(e) = post_index::record_post(post_index_file, frame, uri, &cid) {
+        warn!("Failed to update post index: {:#}", e);
+    }
 }
 
-/// Load authenticated agent from saved session.
-async fn load_agent() -> anyhow::Result<BskyAgent> {
-    BskyAgent::builder()
-        .config(BskyConfig::load(&FileStore::new(SESSION_FILE)).await?)
-        .build()
+/// Confirm a post that was just created shows up in the account's own feed.
+///
+/// The feed is fetched up to [`VERIFY_ATTEMPTS`] times, since a new post can
+/// take a moment to be indexed.
+async fn verify_posted(agent: &BskyAgent, uri: &str) -> anyhow::Result<()> {
+    let did = agent
+        .did()
+        .await
+        .context("Not logged in, can't fetch the feed to verify the post")?;
+
+    for attempt in 1..=VERIFY_ATTEMPTS {
+        let feed = agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_author_feed(
+                get_author_feed::ParametersData {
+                    actor: AtIdentifier::Did(did.clone()),
+                    cursor: None,
+                    filter: None,
+                    include_pins: None,
+                    limit: VERIFY_FEED_LIMIT.try_into().ok(),
+                }
+                .into(),
+            )
+            .await;
+
+        match feed {
+            Ok(feed) if feed.feed.iter().any(|item| item.post.uri == uri) => {
+                debug!("Verified {} appears in the feed", uri);
+                return Ok(());
+            }
+            Ok(_) => warn!(
+                "Attempt {}/{}: {} is not in the feed yet",
+                attempt, VERIFY_ATTEMPTS, uri
+            ),
+            Err(e) => warn!(
+                "Attempt {}/{}: Failed to fetch the feed to verify {}: {}",
+                attempt, VERIFY_ATTEMPTS, uri, e
+            ),
+        }
+
+        if attempt < VERIFY_ATTEMPTS {
+            tokio::time::sleep(VERIFY_DELAY).await;
+        }
+    }
+
+    anyhow::bail!(
+        "{} did not appear in the feed after {} attempts",
+        uri,
+        VERIFY_ATTEMPTS
+    )
+}
+
+/// Restrict who can reply to a post that was just created, if configured.
+///
+/// Failures are only logged since the post already exists.
+async fn gate_replies(agent: &BskyAgent, config: &Config, uri: &str) {
+    let Some(reply_gate) = &config.reply_gate else {
+        return;
+    };
+
+    if let Err(e) = create_threadgate(agent, reply_gate, uri).await {
+        warn!("Failed to restrict replies to {}: {:#}", uri, e);
+    }
+}
+
+/// The record key at the end of a post's AT URI.
+fn record_key(uri: &str) -> anyhow::Result<RecordKey> {
+    uri.rsplit('/')
+        .next()
+        .map(|rkey| RecordKey::new(rkey.to_string()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid record key in post URI '{}': {}", uri, e))?
+        .with_context(|| format!("Post URI '{}' has no record key", uri))
+}
+
+/// Whether the post record at `uri` still exists in the account's repo.
+///
+/// Tells a post that was deleted apart from one that exists but never showed
+/// up in the feed.
+async fn record_exists(agent: &BskyAgent, uri: &str) -> anyhow::Result<bool> {
+    let did = agent
+        .did()
         .await
-        .context("Failed to load agent from session")
+        .context("Not logged in, can't look up the post record")?;
+    let result = agent
+        .api
+        .com
+        .atproto
+        .repo
+        .get_record(
+            get_record::ParametersData {
+                cid: None,
+                collection: feed::Post::nsid(),
+                repo: AtIdentifier::Did(did),
+                rkey: record_key(uri)?,
+            }
+            .into(),
+        )
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(xrpc::Error::XrpcResponse(response))
+            if matches!(
+                response.error,
+                Some(XrpcErrorKind::Custom(get_record::Error::RecordNotFound(_)))
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to look up post record {}", uri)),
+    }
+}
+
+/// Create the threadgate record limiting replies to the post at `uri`.
+async fn create_threadgate(
+    agent: &BskyAgent,
+    reply_gate: &ReplyGate,
+    uri: &str,
+) -> anyhow::Result<()> {
+    // A threadgate only applies when it shares its post's record key
+    let rkey = record_key(uri)?;
+
+    let allow = reply_gate
+        .allow
+        .iter()
+        .map(|rule| {
+            Union::Refs(match rule {
+                ReplyRule::Mentioned => threadgate::RecordAllowItem::MentionRule(Box::new(
+                    threadgate::MentionRuleData {}.into(),
+                )),
+                ReplyRule::Following => threadgate::RecordAllowItem::FollowingRule(Box::new(
+                    threadgate::FollowingRuleData {}.into(),
+                )),
+                ReplyRule::Followers => threadgate::RecordAllowItem::FollowerRule(Box::new(
+                    threadgate::FollowerRuleData {}.into(),
+                )),
+            })
+        })
+        .collect();
+
+    threadgate::RecordData {
+        allow: Some(allow),
+        created_at: Datetime::now(),
+        hidden_replies: None,
+        post: uri.to_string(),
+    }
+    .put(agent, rkey)
+    .await
+    .context("Failed to create threadgate record")?;
+
+    debug!("Restricted replies to {}", uri);
+    Ok(())
+}
+
+/// Process a frame and upload it to Bluesky.
+///
+/// Returns everything needed to create the post, along with the processed
+/// JPEG data and quality of each image. Tiles get their position in the frame added to
+/// their alt text. With `KEN_BURNS`, a video made from the frame is uploaded
+/// instead, falling back on the still if the video can't be made.
+async fn upload_frame(
+    agent: &BskyAgent,
+    config: &Config,
+    frame: u32,
+    total_frames: u32,
+    timings: &mut StageTimings,
+) -> anyhow::Result<(PendingPost, FrameImages)> {
+    let processed_images = get_frame_images(frame, config, timings).await?;
+    let image_count = processed_images.len();
+    let mut images = Vec::with_capacity(image_count);
+    let mut jpeg_images = Vec::with_capacity(image_count);
+    let mut qualities = Vec::with_capacity(image_count);
+
+    for (index, processed_image) in processed_images.into_iter().enumerate() {
+        #[cfg(feature = "ken-burns")]
+        let video = match config.ken_burns {
+            Some(ken_burns) => {
+                let started = Instant::now();
+                let name = format!("frame {}", frame);
+                let video = ken_burns.render(
+                    processed_image.jpeg_data.clone(),
+                    name,
+                    config.compression_timeout,
+                );
+                match video.await {
+                    Ok(video) => {
+                        timings.record("render video", started);
+                        Some(video)
+                    }
+                    Err(e) => {
+                        warn!("Posting frame {} as a still: {:#}", frame, e);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "ken-burns"))]
+        let video: Option<(Vec<u8>, u32, u32)> = None;
+
+        space_out_upload(config, index).await;
+        let started = Instant::now();
+        let (blob, dimensions) = match &video {
+            Some((data, width, height)) => {
+                let blob = upload_blob(agent, config, data.clone())
+                    .await
+                    .context("Failed to upload frame video")?;
+                let dimensions = FrameDimensions {
+                    width: *width,
+                    height: *height,
+                };
+                (blob, dimensions)
+            }
+            None => {
+                let data = processed_image.jpeg_data.clone();
+                let blob = upload_frame_blob(agent, config, data).await?;
+                (blob, processed_image.dimensions.clone())
+            }
+        };
+        timings.record("upload", started);
+
+        let alt_text = image_alt_text(
+            config,
+            frame,
+            total_frames,
+            &processed_image,
+            index,
+            image_count,
+        )
+        .await;
+
+        images.push(UploadedImage {
+            blob,
+            dimensions,
+            alt_text,
+            video: video.is_some(),
+        });
+        jpeg_images.push(processed_image.jpeg_data);
+        qualities.push(processed_image.quality_used);
+    }
+
+    let frame_images = FrameImages {
+        jpeg_images,
+        qualities,
+    };
+    Ok((
+        PendingPost {
+            frame,
+            images,
+            record: None,
+        },
+        frame_images,
+    ))
+}
+
+/// Load authenticated agent from saved session, logging in again if it
+/// can't be resumed.
+///
+/// A missing session file, e.g. after it was deleted or the bot was started
+/// from another directory, is treated the same as an expired session.
+async fn load_agent(config: &Config) -> anyhow::Result<BskyAgent> {
+    if !Path::new(SESSION_FILE).exists() {
+        info!("No saved session at {}, logging in", SESSION_FILE);
+        return initialize_agent(config).await;
+    }
+
+    let resumed = async {
+        BskyAgent::builder()
+            .config(BskyConfig::load(&FileStore::new(SESSION_FILE)).await?)
+            .build()
+            .await
+            .context("Failed to load agent from session")
+    }
+    .await;
+
+    match resumed {
+        Ok(agent) => Ok(agent),
+        Err(e) => {
+            warn!("{:#}, logging in again", e);
+            initialize_agent(config).await
+        }
+    }
 }
 
 /// Upload JPEG data to Bluesky.
+///
+/// Data identical to something already uploaded to the account this run
+/// reuses that blob instead of uploading it again.
 async fn upload_frame_blob(
     agent: &BskyAgent,
+    config: &Config,
     jpeg_data: Vec<u8>,
-) -> anyhow::Result<upload_blob::OutputData> {
+) -> anyhow::Result<BlobRef> {
+    let did = agent.did().await.map(|did| did.as_str().to_string());
+    let digest = blob_cache::digest(&jpeg_data);
+    if let Some(did) = &did
+        && let Some(blob) = blob_cache::get(did, &digest)
+    {
+        debug!("Reusing blob already uploaded this run");
+        return Ok(blob);
+    }
+
+    let blob = upload_blob(agent, config, jpeg_data)
+        .await
+        .context("Failed to upload frame blob")?;
+
+    if let Some(did) = &did {
+        blob_cache::insert(did, digest, blob.clone());
+    }
+    Ok(blob)
+}
+
+/// Upload a blob, retrying up to `UPLOAD_RETRIES` times with backoff when the
+/// transfer fails or the server errors.
+///
+/// Large uploads are the most likely part of a post to be cut off, so they
+/// get their own retries instead of using up a whole posting attempt.
+async fn upload_blob(
+    agent: &BskyAgent,
+    config: &Config,
+    data: Vec<u8>,
+) -> Result<BlobRef, xrpc::Error<upload_blob::Error>> {
+    let mut delay = UPLOAD_RETRY_DELAY;
+    for retry in 1..=config.upload_retries {
+        match agent.api.com.atproto.repo.upload_blob(data.clone()).await {
+            Ok(response) => return Ok(response.data.blob),
+            Err(e) if is_transient(&e) => {
+                warn!(
+                    "Blob upload failed, retry {}/{} in {} seconds: {}",
+                    retry,
+                    config.upload_retries,
+                    delay.as_secs(),
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
     agent
         .api
         .com
         .atproto
         .repo
-        .upload_blob(jpeg_data)
+        .upload_blob(data)
         .await
-        .map(|response| response.data)
-        .context("Failed to upload frame blob")
+        .map(|response| response.data.blob)
+}
+
+/// Wait `INTER_UPLOAD_DELAY_MS` before uploading image `index` of a post, so
+/// successive uploads are spaced out. The first image is uploaded right away.
+async fn space_out_upload(config: &Config, index: usize) {
+    if index > 0 && !config.inter_upload_delay.is_zero() {
+        debug!(
+            "Waiting {}ms before the next upload",
+            config.inter_upload_delay.as_millis()
+        );
+        tokio::time::sleep(config.inter_upload_delay).await;
+    }
+}
+
+/// Whether a request failed in transit or on the server, so trying it again
+/// may work.
+fn is_transient<E: std::fmt::Debug>(error: &xrpc::Error<E>) -> bool {
+    match error {
+        xrpc::Error::HttpClient(_) => true,
+        xrpc::Error::XrpcResponse(response) => response.status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// Describe the current frame for use as image alt text.
+///
+/// Uses the frame's hand-written alt text when the alt text file has one.
+/// Otherwise renders the configured alt text template, followed by the
+/// secondary language template when one is set. When stepping through the
+/// movie, notes that only a sample of the frames is being posted so the frame
+/// numbers don't look like they skip at random. `color` fills the `{color}`
+/// placeholder.
+fn frame_alt_text(config: &Config, frame: u32, total_frames: u32, color: &str) -> String {
+    if let Some(alt_text) = config
+        .alt_texts
+        .as_ref()
+        .and_then(|alt_texts| alt_texts.get(frame))
+    {
+        return alt_text.to_string();
+    }
+
+    let values = [
+        ("movie", config.movie_name.clone()),
+        ("frame", frame.to_string()),
+        ("total", total_frames.to_string()),
+        ("color", color.to_string()),
+    ];
+
+    let mut alt_text = template::render(&config.alt_text_template, &values);
+    if config.frame_step > 1 {
+        alt_text = format!(
+            "{alt_text} (posting one of every {} frames)",
+            config.frame_step
+        );
+    }
+
+    match &config.alt_text_template_secondary {
+        Some(secondary) => format!(
+            "{alt_text}{}{}",
+            config.alt_text_separator,
+            template::render(secondary, &values)
+        ),
+        None => alt_text,
+    }
+}
+
+/// Alt text for image `index` of the `image_count` a frame was processed into.
+///
+/// Tiles get their position in the frame added, and any on-screen text when
+/// OCR is enabled.
+pub async fn image_alt_text(
+    config: &Config,
+    frame: u32,
+    total_frames: u32,
+    processed_image: &ProcessedFrame,
+    index: usize,
+    image_count: usize,
+) -> String {
+    let average_color = processed_image.average_color.as_deref().unwrap_or_default();
+    let alt_text = frame_alt_text(config, frame, total_frames, average_color);
+    let alt_text = match &processed_image.tile_position {
+        Some(position) => format!(
+            "{alt_text} (tile {} of {}, {})",
+            index + 1,
+            image_count,
+            position
+        ),
+        None => alt_text,
+    };
+    #[cfg(feature = "ocr")]
+    let alt_text = ocr::append_on_screen_text(alt_text, &processed_image.jpeg_data).await;
+    alt_text
+}
+
+/// Countdown to the end of the movie, like `only 3 frames left!`, once the
+/// `posts_left` after this one are within the last `FINALE_COUNTDOWN`.
+///
+/// Only frames that will actually be posted are counted, so with `FRAME_STEP`
+/// or `SCENES_FILE` it counts down the posts to come rather than the frames
+/// of film. Nothing is added to the final post.
+fn finale_countdown(config: &Config, posts_left: Option<u32>) -> Option<String> {
+    let countdown = config.finale_countdown?;
+    match posts_left? {
+        0 => None,
+        1 => Some("only 1 frame left!".to_string()),
+        posts_left if posts_left <= countdown => Some(format!("only {posts_left} frames left!")),
+        _ => None,
+    }
+}
+
+/// Tag for finding a frame's post by number, like `frame00123`, when enabled.
+fn frame_tag(config: &Config, frame: u32) -> Option<String> {
+    let width = config.frame_tag_padding?;
+    Some(format!("frame{:0width$}", frame))
+}
+
+/// Width and height to declare as an image's aspect ratio.
+///
+/// When either side is larger than `max_dimension`, the ratio is reduced to
+/// its lowest terms, and only if that's still too large are both sides scaled
+/// down by the same factor to fit. Scaled sides are rounded to whole pixels and
+/// kept at one or more, so extremely thin images end up only approximately in
+/// ratio.
+fn declared_aspect_ratio(dimensions: &FrameDimensions, max_dimension: Option<u32>) -> (u64, u64) {
+    let (width, height) = (dimensions.width as u64, dimensions.height as u64);
+    let Some(max_dimension) = max_dimension.map(u64::from) else {
+        return (width, height);
+    };
+
+    if width.max(height) <= max_dimension {
+        return (width, height);
+    }
+
+    let divisor = gcd(width, height).max(1);
+    let (width, height) = (width / divisor, height / divisor);
+    let largest = width.max(height);
+    if largest <= max_dimension {
+        return (width, height);
+    }
+
+    let scale = |side: u64| (side as f64 * max_dimension as f64 / largest as f64).round() as u64;
+    (scale(width).max(1), scale(height).max(1))
+}
+
+/// Greatest common divisor of `a` and `b`, or 0 if both are 0.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// When a frame appears in the film, given when the film starts.
+///
+/// Fails if the result is outside the range a post's `createdAt` can hold.
+fn film_datetime(
+    film_start: chrono::DateTime<chrono::FixedOffset>,
+    frame: u32,
+    fps: f64,
+) -> anyhow::Result<Datetime> {
+    let offset = chrono::Duration::from_std(timecode::frame_position(frame, fps))
+        .context("Frame position is too far into the film")?;
+    let datetime = film_start
+        .checked_add_signed(offset)
+        .with_context(|| format!("Frame {} is too far past FILM_START", frame))?;
+
+    datetime
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Frame {} can't be dated {}: {}", frame, datetime, e))
+}
+
+/// Text of a post: the configured prefix, then `body`, then the countdown
+/// once the `posts_left` in the cycle after this one are few enough.
+pub fn post_text(config: &Config, body: &str, posts_left: Option<u32>) -> String {
+    let countdown = finale_countdown(config, posts_left)
+        .map(|countdown| {
+            // Separate it from any prefix or body text before it
+            if config.post_prefix.is_empty() && body.is_empty() {
+                countdown
+            } else {
+                format!(" {countdown}")
+            }
+        })
+        .unwrap_or_default();
+
+    template::compose_post_text(&[&config.post_prefix, body, &countdown])
 }
 
 /// Create post data with image and metadata.
 ///
-/// Builds the complete post structure including the image embed,
-/// alt text description, and aspect ratio information.
+/// Builds the complete post structure including the image or video embed,
+/// alt text description, aspect ratio information, and post languages.
+/// Frame posts embed a link card with the frame as its thumbnail instead of
+/// the image when `LINK_CARD_URL` is set.
+/// The post text is the configured prefix followed by `body`. When `reply_to`
+/// is given, the post is made as a reply with it as both root and parent.
+/// Posts of a `frame`, given with the total number of frames, are tagged with
+/// its number when enabled and quote the quote root when one is configured.
+/// Posts made from the frame counter, given with the `posts_left` in its cycle,
+/// end with a countdown near the end of the movie.
 fn create_post_data(
-    movie_name: &str,
-    frame_info: &FrameInfo,
-    total_frames: u32,
-    blob: upload_blob::OutputData,
-    dimensions: &FrameDimensions,
+    config: &Config,
+    body: &str,
+    frame: Option<(u32, u32)>,
+    posts_left: Option<u32>,
+    uploaded_images: &[UploadedImage],
+    reply_to: Option<&PostRef>,
 ) -> anyhow::Result<post::RecordData> {
-    let images =
-        vec![ImageData {
-        alt: format!(
-            "A frame from the movie '{movie_name}', specifically frame {} of {total_frames}",
-            frame_info.current_frame
-        ),
-        image: blob.blob,
-        aspect_ratio: Some(AspectRatio {
+    let aspect_ratio = |uploaded: &UploadedImage| -> anyhow::Result<AspectRatio> {
+        let (width, height) =
+            declared_aspect_ratio(&uploaded.dimensions, config.max_aspect_dimension);
+        Ok(AspectRatio {
             data: AspectRatioData {
-                width: NonZeroU64::new(dimensions.width as u64)
-                    .context("Invalid width dimension")?,
-                height: NonZeroU64::new(dimensions.height as u64)
-                    .context("Invalid height dimension")?,
+                width: NonZeroU64::new(width).context("Invalid width dimension")?,
+                height: NonZeroU64::new(height).context("Invalid height dimension")?,
             },
             extra_data: Ipld::Null,
-        }),
-    }.into()];
+        })
+    };
 
-    let embed = Some(Union::Refs(post::RecordEmbedRefs::AppBskyEmbedImagesMain(
-        Box::new(images::MainData { images }.into()),
-    )));
+    // A video can only be posted on its own
+    let media = match (uploaded_images, frame, &config.link_card) {
+        ([uploaded], Some((frame, total_frames)), Some(link_card)) if !uploaded.video => {
+            let values = [
+                ("movie", config.movie_name.clone()),
+                ("frame", frame.to_string()),
+                ("total", total_frames.to_string()),
+            ];
+            Media::External(Box::new(
+                external::MainData {
+                    external: ExternalData {
+                        description: template::render(&link_card.description, &values),
+                        thumb: Some(uploaded.blob.clone()),
+                        title: link_card
+                            .title
+                            .clone()
+                            .unwrap_or_else(|| config.movie_name.clone()),
+                        uri: link_card.url.clone(),
+                    }
+                    .into(),
+                }
+                .into(),
+            ))
+        }
+        ([uploaded], _, _) if uploaded.video => Media::Video(Box::new(
+            video::MainData {
+                alt: Some(uploaded.alt_text.clone()),
+                aspect_ratio: Some(aspect_ratio(uploaded)?),
+                captions: None,
+                video: uploaded.blob.clone(),
+            }
+            .into(),
+        )),
+        _ => {
+            let images = uploaded_images
+                .iter()
+                .map(|uploaded| {
+                    Ok(ImageData {
+                        alt: uploaded.alt_text.clone(),
+                        image: uploaded.blob.clone(),
+                        aspect_ratio: Some(aspect_ratio(uploaded)?),
+                    }
+                    .into())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Media::Images(Box::new(images::MainData { images }.into()))
+        }
+    };
+
+    // Frame posts quote the quote root alongside their media when one is set
+    let embed = match (frame, &config.quote_root) {
+        (Some(_), Some(quote_root)) => {
+            let media = match media {
+                Media::Images(images) => {
+                    record_with_media::MainMediaRefs::AppBskyEmbedImagesMain(images)
+                }
+                Media::Video(video) => {
+                    record_with_media::MainMediaRefs::AppBskyEmbedVideoMain(video)
+                }
+                Media::External(external) => {
+                    record_with_media::MainMediaRefs::AppBskyEmbedExternalMain(external)
+                }
+            };
+            post::RecordEmbedRefs::AppBskyEmbedRecordWithMediaMain(Box::new(
+                record_with_media::MainData {
+                    media: Union::Refs(media),
+                    record: record::MainData {
+                        record: post_strong_ref(quote_root)?,
+                    }
+                    .into(),
+                }
+                .into(),
+            ))
+        }
+        _ => match media {
+            Media::Images(images) => post::RecordEmbedRefs::AppBskyEmbedImagesMain(images),
+            Media::Video(video) => post::RecordEmbedRefs::AppBskyEmbedVideoMain(video),
+            Media::External(external) => post::RecordEmbedRefs::AppBskyEmbedExternalMain(external),
+        },
+    };
+
+    let created_at = match (frame, config.film_start, config.fps) {
+        (Some((frame, _)), Some(film_start), Some(fps)) => film_datetime(film_start, frame, fps)?,
+        _ => Datetime::now(),
+    };
 
     Ok(post::RecordData {
-        created_at: Datetime::now(),
-        embed,
+        created_at,
+        embed: Some(Union::Refs(embed)),
         entities: None,
         facets: None,
         labels: None,
-        langs: None,
-        reply: None,
-        tags: None,
-        text: String::new(),
+        langs: (!config.post_langs.is_empty()).then(|| config.post_langs.clone()),
+        reply: reply_to.map(reply_ref).transpose()?,
+        tags: frame
+            .and_then(|(frame, _)| frame_tag(config, frame))
+            .map(|tag| vec![tag]),
+        text: post_text(config, body, posts_left),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::*;
+    use crate::{
+        config::MAX_POST_GRAPHEMES,
+        frame_info::FrameInfo,
+    };
+
+    fn dimensions(width: u32, height: u32) -> FrameDimensions {
+        FrameDimensions { width, height }
+    }
+
+    #[test]
+    fn aspect_ratio_without_cap_is_the_dimensions() {
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(1920, 1080), None),
+            (1920, 1080)
+        );
+        assert_eq!(declared_aspect_ratio(&dimensions(4000, 2), None), (4000, 2));
+    }
+
+    #[test]
+    fn aspect_ratio_within_cap_is_the_dimensions() {
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(1920, 1080), Some(2000)),
+            (1920, 1080)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_over_cap_is_reduced_to_lowest_terms() {
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(1920, 1080), Some(100)),
+            (16, 9)
+        );
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(1080, 1920), Some(16)),
+            (9, 16)
+        );
+    }
+
+    #[test]
+    fn aspect_ratio_still_over_cap_is_scaled_down() {
+        // 4000:2 reduces to 2000:1, which still has to be scaled to fit
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(4000, 2), Some(100)),
+            (100, 1)
+        );
+        // 1921:1080 has no common divisor to reduce by
+        assert_eq!(
+            declared_aspect_ratio(&dimensions(1921, 1080), Some(100)),
+            (100, 56)
+        );
+    }
+
+    #[test]
+    fn alt_text_names_the_frame_and_total() {
+        let config = Config::from_values(&[]).unwrap();
+        assert_eq!(
+            frame_alt_text(&config, 21, 500, ""),
+            "A frame from the movie 'Spirited Away', specifically frame 21 of 500"
+        );
+    }
+
+    #[test]
+    fn alt_text_with_frame_step_names_the_sampled_frame() {
+        let config = Config::from_values(&[("FRAME_STEP", "10")]).unwrap();
+        // The third post with a step of 10 is of frame 21, not frame 3
+        assert_eq!(
+            frame_alt_text(&config, 21, 500, ""),
+            "A frame from the movie 'Spirited Away', specifically frame 21 of 500 \
+             (posting one of every 10 frames)"
+        );
+    }
+
+    #[test]
+    fn truncated_post_text_has_no_facets() {
+        let config = Config::from_values(&[("POST_PREFIX", "千と千尋の神隠し 🐉 ")]).unwrap();
+        // Links and hashtags are posted as plain text, so there are no facet
+        // byte offsets for truncation to leave pointing into the wrong place
+        let body = "👨‍👩‍👧 https://example.com #frame ".repeat(100);
+        let record =
+            create_post_data(&config, &body, Some((21, 500)), Some(479), &[], None).unwrap();
+
+        assert_eq!(record.text.graphemes(true).count(), MAX_POST_GRAPHEMES);
+        assert!(record.text.starts_with("千と千尋の神隠し 🐉 👨‍👩‍👧"));
+        assert!(record.facets.is_none());
+    }
+
+    #[test]
+    fn finale_countdown_counts_the_posts_left() {
+        let config = Config::from_values(&[("FINALE_COUNTDOWN", "5")]).unwrap();
+        assert_eq!(finale_countdown(&config, Some(6)), None);
+        assert_eq!(
+            finale_countdown(&config, Some(5)).as_deref(),
+            Some("only 5 frames left!")
+        );
+        assert_eq!(
+            finale_countdown(&config, Some(1)).as_deref(),
+            Some("only 1 frame left!")
+        );
+        assert_eq!(finale_countdown(&config, Some(0)), None);
+        assert_eq!(finale_countdown(&config, None), None);
+    }
+
+    #[test]
+    fn finale_countdown_with_frame_step_counts_posts_not_frames() {
+        let config =
+            Config::from_values(&[("FINALE_COUNTDOWN", "5"), ("FRAME_STEP", "10")]).unwrap();
+        // 40 frames of film left, but only 4 more of them will be posted
+        let frame_info = FrameInfo::new(500, 460).unwrap();
+        let posts_left = frame_info.posts_left(config.frame_step, None);
+        assert_eq!(post_text(&config, "", posts_left), "only 4 frames left!");
+    }
+}
@@ -0,0 +1,130 @@
+//! SHA-256 verification of frame files against a manifest.
+//!
+//! The manifest uses the same format as `sha256sum`, one `<hex digest>  <file name>`
+//! line per frame, so it can be generated or checked with standard tools as well
+//! as with the `generate-manifest` command.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::OnceLock,
+};
+
+use anyhow::Context;
+use log::*;
+
+use crate::{
+    config::MANIFEST_FILE,
+    frame_processing::{
+        frame_number,
+        scan_frame_files,
+    },
+};
+
+/// Cached manifest contents, keyed by file name.
+static MANIFEST: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Hex-encoded SHA-256 digest of some data.
+pub fn sha256_hex(data: &[u8]) -> String {
+    openssl::sha::sha256(data)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Check a frame file's contents against its manifest entry.
+///
+/// Returns `Ok(true)` when the digest matches, `Ok(false)` on a mismatch. Frames
+/// missing from the manifest are logged and treated as matching, so adding new
+/// frames doesn't require regenerating the manifest first.
+pub async fn verify_frame(file_name: &str, data: &[u8]) -> anyhow::Result<bool> {
+    let manifest = load_manifest().await?;
+
+    let Some(expected) = manifest.get(file_name) else {
+        warn!(
+            "No checksum for {} in {}, skipping verification",
+            file_name, MANIFEST_FILE
+        );
+        return Ok(true);
+    };
+
+    let actual = sha256_hex(data);
+    if &actual != expected {
+        error!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            file_name, expected, actual
+        );
+        return Ok(false);
+    }
+
+    trace!("Checksum verified for {}", file_name);
+    Ok(true)
+}
+
+/// Hash every frame file and write the manifest, returning the number of frames.
+pub async fn generate_manifest() -> anyhow::Result<usize> {
+    let mut paths = scan_frame_files().await?;
+    paths.sort_by_key(|path| frame_number(path));
+
+    let mut manifest = String::new();
+    for path in &paths {
+        let data = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read frame file: {}", path.display()))?;
+        let digest = tokio::task::spawn_blocking(move || sha256_hex(&data))
+            .await
+            .context("Task panicked while hashing frame")?;
+
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid frame file name: {}", path.display()))?;
+        let _ = writeln!(manifest, "{digest}  {file_name}");
+    }
+
+    tokio::fs::write(MANIFEST_FILE, manifest)
+        .await
+        .with_context(|| format!("Failed to write manifest to {}", MANIFEST_FILE))?;
+
+    Ok(paths.len())
+}
+
+/// Load the manifest, using the cached copy if available.
+async fn load_manifest() -> anyhow::Result<&'static HashMap<String, String>> {
+    if let Some(manifest) = MANIFEST.get() {
+        return Ok(manifest);
+    }
+
+    let content = tokio::fs::read_to_string(MANIFEST_FILE)
+        .await
+        .with_context(|| format!("Failed to read checksum manifest {}", MANIFEST_FILE))?;
+    cache_manifest(&content, MANIFEST_FILE)
+}
+
+/// Parse a manifest read from `source` and cache it for verifying frames.
+///
+/// Used directly for frames loaded over HTTP, whose manifest is fetched along
+/// with them rather than read from [`MANIFEST_FILE`].
+pub fn cache_manifest(
+    content: &str,
+    source: &str,
+) -> anyhow::Result<&'static HashMap<String, String>> {
+    let mut manifest = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (digest, file_name) = line
+            .split_once(char::is_whitespace)
+            .with_context(|| format!("Malformed line {} in {}", index + 1, source))?;
+        // sha256sum marks binary-mode entries with a leading '*'
+        let file_name = file_name.trim_start().trim_start_matches('*');
+        manifest.insert(file_name.to_string(), digest.to_ascii_lowercase());
+    }
+
+    debug!("Loaded {} checksums from {}", manifest.len(), source);
+    Ok(MANIFEST.get_or_init(|| manifest))
+}
@@ -0,0 +1,516 @@
+//! One-off diagnostic and maintenance commands.
+//!
+//! These run instead of the posting bot when a command name is passed on the
+//! command line, and exit once finished.
+
+#[cfg(feature = "bluesky")]
+use std::path::Path;
+use std::{
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use log::*;
+use tokio::task::{
+    JoinError,
+    JoinSet,
+};
+
+#[cfg(feature = "bluesky")]
+use crate::bluesky;
+#[cfg(feature = "bluesky")]
+use crate::tiles::MAX_IMAGES_PER_POST;
+use crate::{
+    checksum,
+    config::{
+        Config,
+        FrameNameFormat,
+        DEFAULT_FILMSTRIP_FRAMES,
+        FILMSTRIP_FILE,
+        FILMSTRIP_THUMBNAIL_HEIGHT,
+        FRAMES_DIR,
+        FRAME_DATA_FILE,
+        MANIFEST_FILE,
+        MAX_FILMSTRIP_FRAMES,
+        PRECOMPRESSED_DIR,
+    },
+    filmstrip::Filmstrip,
+    frame_info::FrameInfo,
+    frame_processing::{
+        decode_frame,
+        frame_number,
+        frame_original_use,
+        load_total_frame_count,
+        process_frame,
+        scan_frame_files,
+        timecode_ordered_frames,
+        verify_frame,
+        OriginalUse,
+    },
+    known_settings::KNOWN_SETTINGS,
+    precompressed::{
+        self,
+        PrecompressedFrame,
+        PrecompressedIndex,
+    },
+    stage_timings::StageTimings,
+    timecode,
+};
+
+/// Print every detected frame with its size and whether it needs recompression.
+///
+/// Frames are listed in posting order, followed by a summary of the whole set
+/// so the oversized frames that will drop in quality are easy to spot.
+pub async fn list_frames() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let paths = match config.frame_name_format {
+        FrameNameFormat::Number => numbered_frame_files().await?,
+        FrameNameFormat::Timecode => (1..).zip(timecode_ordered_frames().await?).collect(),
+    };
+
+    let size_limit = config.jpeg_size_limit() as u64;
+    let mut frames = Vec::new();
+    for (frame, path) in paths {
+        let data = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let size = data.len() as u64;
+        let recompress = frame_original_use(frame, data, &config)? != OriginalUse::AsIs;
+        frames.push((frame, size, recompress));
+    }
+    frames.sort_unstable();
+
+    if frames.is_empty() {
+        println!("No frames found in '{}'", FRAMES_DIR);
+        return Ok(());
+    }
+
+    println!("{:>8}  {:>12}  Recompress", "Frame", "Size (bytes)");
+    for &(frame, size, recompress) in &frames {
+        let recompress = if recompress { "yes" } else { "no" };
+        println!("{frame:>8}  {size:>12}  {recompress}");
+    }
+
+    let sizes = frames.iter().map(|&(_, size, _)| size);
+    let smallest = sizes.clone().min().unwrap_or_default();
+    let largest = sizes.clone().max().unwrap_or_default();
+    let oversized = sizes.filter(|&size| size > size_limit).count();
+    let recompressed = frames
+        .iter()
+        .filter(|&&(_, _, recompress)| recompress)
+        .count();
+
+    println!();
+    println!(
+        "{} frames, smallest {} bytes, largest {} bytes",
+        frames.len(),
+        smallest,
+        largest
+    );
+    println!(
+        "{} frames will be recompressed, {} of them exceeding the {} byte limit",
+        recompressed, oversized, size_limit
+    );
+
+    Ok(())
+}
+
+/// List the frame files named by frame number, along with their numbers.
+async fn numbered_frame_files() -> anyhow::Result<Vec<(u32, PathBuf)>> {
+    let mut frames = Vec::new();
+
+    for path in scan_frame_files().await? {
+        let Some(frame) = frame_number(&path) else {
+            warn!(
+                "Skipping {} as it isn't named after a frame number",
+                path.display()
+            );
+            continue;
+        };
+        frames.push((frame, path));
+    }
+
+    Ok(frames)
+}
+
+/// Write a SHA-256 manifest covering every frame currently in the frames directory.
+pub async fn generate_manifest() -> anyhow::Result<()> {
+    let count = checksum::generate_manifest().await?;
+    println!("Wrote checksums for {} frames to {}", count, MANIFEST_FILE);
+    Ok(())
+}
+
+/// Process every frame ahead of time for `USE_PRECOMPRESSED`.
+///
+/// Frames are processed exactly as they would be for posting, using the
+/// current settings, and written to the precompressed directory along with an
+/// index of their dimensions and qualities. Frames that fail are reported and
+/// skipped, leaving them to be processed when posted.
+pub async fn precompress() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    if config.tile_mode.is_some() {
+        bail!("Frames split into tiles can't be precompressed, unset TILE_MODE first");
+    }
+
+    let total_frames = load_total_frame_count(&config).await?;
+    tokio::fs::create_dir_all(PRECOMPRESSED_DIR)
+        .await
+        .with_context(|| format!("Failed to create {}", PRECOMPRESSED_DIR))?;
+
+    let mut index = PrecompressedIndex::new();
+    let mut failed = 0;
+    for frame in 1..=total_frames {
+        let mut timings = StageTimings::new();
+        let processed = match process_frame(frame, &config, &mut timings).await {
+            Ok(processed) => processed,
+            Err(e) => {
+                println!("Frame {}/{}: failed, {:#}", frame, total_frames, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let path = precompressed::frame_path(frame, config.output_format);
+        tokio::fs::write(&path, &processed.jpeg_data)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let quality = match processed.quality_used {
+            Some(quality) => format!("quality {}", quality),
+            None => "original".to_string(),
+        };
+        println!(
+            "Frame {}/{}: {} bytes, {}",
+            frame,
+            total_frames,
+            processed.jpeg_data.len(),
+            quality
+        );
+
+        index.insert(
+            frame,
+            PrecompressedFrame {
+                dimensions: processed.dimensions,
+                quality: processed.quality_used,
+                average_color: processed.average_color,
+            },
+        );
+    }
+
+    precompressed::save_index(&index).await?;
+    println!(
+        "Precompressed {} of {} frames into {}",
+        index.len(),
+        total_frames,
+        PRECOMPRESSED_DIR
+    );
+    if failed > 0 {
+        println!("{} frames failed and will be processed when posted", failed);
+    }
+
+    Ok(())
+}
+
+/// Decode every frame to find ones that would fail when posted.
+///
+/// Frames are checked as described in `verify_frame`, several at a time, and
+/// each failure is printed along with a final comma-separated list of the
+/// failed frame numbers. Fails when any frame did.
+pub async fn verify_frames() -> anyhow::Result<()> {
+    let config = Arc::new(Config::load()?);
+    let total_frames = load_total_frame_count(&config).await?;
+    let parallelism = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+    let mut tasks = JoinSet::new();
+    let mut failed = Vec::new();
+    let mut record = |result: Result<(u32, anyhow::Result<(u32, u32)>), JoinError>| match result {
+        Ok((_, Ok(_))) => {}
+        Ok((frame, Err(e))) => {
+            println!("Frame {}/{}: {:#}", frame, total_frames, e);
+            failed.push(frame);
+        }
+        Err(e) => error!("Frame verification task failed: {}", e),
+    };
+
+    for frame in 1..=total_frames {
+        if tasks.len() >= parallelism
+            && let Some(result) = tasks.join_next().await
+        {
+            record(result);
+        }
+
+        let config = config.clone();
+        tasks.spawn(async move { (frame, verify_frame(frame, &config).await) });
+    }
+    while let Some(result) = tasks.join_next().await {
+        record(result);
+    }
+
+    failed.sort_unstable();
+    println!("Verified {} frames, {} failed", total_frames, failed.len());
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    let list = failed
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    println!("Failed frames: {}", list);
+    bail!("{} frames failed verification", failed.len())
+}
+
+/// Manually post a single frame chosen by number, timecode, or percentage.
+///
+/// Takes one of the frame options described in `select_frame`. The frame
+/// counter used by the scheduled posts is left untouched.
+#[cfg(feature = "bluesky")]
+pub async fn post(args: &[String]) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let total_frames = load_total_frame_count(&config).await?;
+    let frame = select_frame(args, &config, total_frames)?;
+
+    let agent = bluesky::initialize_agent(&config).await?;
+    bluesky::post_specific_frame(&agent, &config, frame).await
+}
+
+/// Move the frame counter so scheduled posts continue from a chosen frame.
+///
+/// Takes one of the frame options described in `select_frame`, or starts over
+/// from frame 1 when none is given. Takes effect from the next scheduled post.
+pub async fn reset(args: &[String]) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let total_frames = load_total_frame_count(&config).await?;
+    let frame = if args.is_empty() {
+        1
+    } else {
+        select_frame(args, &config, total_frames)?
+    };
+
+    FrameInfo::new(total_frames, frame)?.save_to_file(FRAME_DATA_FILE)?;
+    println!("Next frame to post is {}/{}", frame, total_frames);
+    Ok(())
+}
+
+/// Post a custom image with text, e.g. to announce maintenance.
+///
+/// Takes `--image <path>` and `--text <text>`. The frame counter is left
+/// untouched.
+#[cfg(feature = "bluesky")]
+pub async fn announce(args: &[String]) -> anyhow::Result<()> {
+    let (Some(image), Some(text)) = (flag_value(args, "--image"), flag_value(args, "--text"))
+    else {
+        bail!("Expected --image <path> and --text <text>");
+    };
+
+    let config = Config::load()?;
+    let agent = bluesky::initialize_agent(&config).await?;
+    bluesky::post_announcement(&agent, &config, Path::new(image), text).await
+}
+
+/// Post a hand-picked set of frames together, e.g. for a "best of" post.
+///
+/// Takes `--frames <list>` with up to four comma-separated frame numbers, like
+/// `--frames 12,340,1001`, and optional `--text <text>` for the post. The
+/// frame counter is left untouched.
+#[cfg(feature = "bluesky")]
+pub async fn curate(args: &[String]) -> anyhow::Result<()> {
+    let list = flag_value(args, "--frames").context("Expected --frames <list>")?;
+    let frames = list
+        .split(',')
+        .map(|frame| {
+            frame
+                .trim()
+                .parse::<u32>()
+                .with_context(|| format!("Invalid frame number '{}'", frame))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if frames.len() > MAX_IMAGES_PER_POST as usize {
+        bail!(
+            "Posts can have at most {} images, got {} frames",
+            MAX_IMAGES_PER_POST,
+            frames.len()
+        );
+    }
+
+    let config = Config::load()?;
+    let total_frames = load_total_frame_count(&config).await?;
+    if let Some(frame) = frames
+        .iter()
+        .find(|frame| !(1..=total_frames).contains(frame))
+    {
+        bail!(
+            "Frame {} is out of range, the movie has frames 1 to {}",
+            frame,
+            total_frames
+        );
+    }
+
+    let text = flag_value(args, "--text").unwrap_or_default();
+    let agent = bluesky::initialize_agent(&config).await?;
+    bluesky::post_frame_set(&agent, &config, &frames, text).await
+}
+
+/// Save thumbnails of the next frames to post side by side in one image.
+///
+/// Takes an optional `--count <n>` of frames to show, following the frame
+/// counter the same way scheduled posts would, and `--output <path>` to save
+/// to, whose extension picks the image format. The frame counter is left
+/// untouched.
+pub async fn filmstrip(args: &[String]) -> anyhow::Result<()> {
+    let count = flag_value(args, "--count")
+        .map(|count| {
+            count
+                .parse::<usize>()
+                .with_context(|| format!("Invalid frame count '{}'", count))
+        })
+        .transpose()?
+        .unwrap_or(DEFAULT_FILMSTRIP_FRAMES);
+    if !(1..=MAX_FILMSTRIP_FRAMES).contains(&count) {
+        bail!("--count must be between 1 and {}", MAX_FILMSTRIP_FRAMES);
+    }
+    let output = flag_value(args, "--output").unwrap_or(FILMSTRIP_FILE);
+
+    let config = Config::load()?;
+    let total_frames = load_total_frame_count(&config).await?;
+    let mut frame_info = match FrameInfo::peek(FRAME_DATA_FILE)? {
+        Some(frame_info) => frame_info,
+        None => FrameInfo::new(total_frames, 1)?,
+    };
+
+    let mut filmstrip = Filmstrip::new(FILMSTRIP_THUMBNAIL_HEIGHT);
+    let mut frames = Vec::with_capacity(count);
+    for _ in 0..count {
+        let frame = frame_info.current_frame;
+        let image = decode_frame(frame, &config).await?;
+        filmstrip.push(frame, &image);
+        frames.push(frame.to_string());
+        frame_info.advance(config.frame_step, config.scenes.as_ref())?;
+    }
+
+    filmstrip
+        .render()
+        .save(output)
+        .with_context(|| format!("Failed to save filmstrip to {}", output))?;
+    println!("Saved frames {} to {}", frames.join(", "), output);
+    Ok(())
+}
+
+/// Print every setting the bot reads, with its default and what it does.
+///
+/// Doesn't load the config, so it works before anything is set up.
+pub fn print_env() {
+    let width = KNOWN_SETTINGS
+        .iter()
+        .map(|setting| setting.name.len())
+        .max()
+        .unwrap_or_default();
+    let default_width = KNOWN_SETTINGS
+        .iter()
+        .filter_map(|setting| setting.default)
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+        .max("Default".len());
+
+    println!(
+        "{:width$}  {:default_width$}  Required  Description",
+        "Variable", "Default"
+    );
+    for setting in KNOWN_SETTINGS {
+        let required = if setting.required { "yes" } else { "no" };
+        let mut description = setting.description.to_string();
+        if let Some(feature) = setting.feature {
+            description.push_str(&format!(" (needs the {} feature)", feature));
+        }
+        println!(
+            "{:width$}  {:default_width$}  {:8}  {}",
+            setting.name,
+            setting.default.unwrap_or("-"),
+            required,
+            description
+        );
+    }
+}
+
+/// Check the full posting round-trip by posting the next frame and deleting it.
+///
+/// Logs in, uploads the frame, creates the post, then deletes it again, so a
+/// working setup leaves nothing behind. The frame counter is left untouched.
+#[cfg(feature = "bluesky")]
+pub async fn self_test() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let frame = FrameInfo::peek_current_frame(FRAME_DATA_FILE)?;
+
+    let result = async {
+        let agent = bluesky::initialize_agent(&config).await?;
+        bluesky::self_test(&agent, &config, frame).await
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            println!("Self-test passed: posted and deleted frame {}", frame);
+            Ok(())
+        }
+        Err(e) => Err(e.context("Self-test failed")),
+    }
+}
+
+/// Choose a frame from `--frame <number>`, `--at <timecode>`, or `--seek <percent>`.
+///
+/// Timecodes are converted to frame numbers using the configured FPS, and
+/// percentages such as `50%` are rounded to the nearest frame.
+fn select_frame(args: &[String], config: &Config, total_frames: u32) -> anyhow::Result<u32> {
+    let frame = match (
+        flag_value(args, "--frame"),
+        flag_value(args, "--at"),
+        flag_value(args, "--seek"),
+    ) {
+        (Some(frame), None, None) => frame
+            .parse()
+            .with_context(|| format!("Invalid frame number '{}'", frame))?,
+        (None, Some(at), None) => {
+            let fps = config
+                .fps
+                .context("FPS must be set to select a frame by timecode")?;
+            let frame = timecode::frame_at(timecode::parse_timecode(at)?, fps);
+            info!("Timecode {} is frame {} at {} fps", at, frame, fps);
+            frame
+        }
+        (None, None, Some(seek)) => {
+            let fraction = timecode::parse_percentage(seek)?;
+            let frame = timecode::frame_at_fraction(fraction, total_frames);
+            info!("Seeking to {} is frame {}", seek, frame);
+            frame
+        }
+        _ => {
+            bail!("Expected exactly one of --frame <number>, --at <timecode>, or --seek <percent>")
+        }
+    };
+
+    if !(1..=total_frames).contains(&frame) {
+        bail!(
+            "Frame {} is out of range, the movie has frames 1 to {}",
+            frame,
+            total_frames
+        );
+    }
+
+    Ok(frame)
+}
+
+/// Find the value following a `--flag` argument.
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
@@ -1,11 +1,62 @@
 //! Configuration constants and environment variable handling.
+//!
+//! Settings can also be kept in a TOML file named by `CONFIG_FILE`, using the
+//! environment variable names as keys in either case, e.g. `movie_name = "Heat"`.
+//! Environment variables override the file, which keeps secrets out of it.
 
-use std::env;
+use std::{
+    collections::HashMap,
+    env,
+    fmt,
+    fs,
+    net::SocketAddr,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+    time::Duration,
+};
 
-use anyhow::Context;
+use anyhow::{
+    bail,
+    Context,
+};
+#[cfg(feature = "bluesky")]
+use bsky_sdk::api::types::string::Language;
+use log::*;
 
-/// Maximum JPEG file size in bytes before compression quality is reduced.
-pub const MAX_JPEG_SIZE: usize = 1_000_000;
+#[cfg(feature = "ken-burns")]
+use crate::ken_burns::KenBurns;
+#[cfg(feature = "mozjpeg")]
+use crate::mozjpeg;
+#[cfg(feature = "bluesky")]
+use crate::{
+    alt_text::AltTextMap,
+    template::DEFAULT_LINK_CARD_DESCRIPTION,
+};
+use crate::{
+    crop::FrameCrop,
+    known_settings,
+    overlay::TimecodeOverlay,
+    post_days::PostDays,
+    scenes::SceneList,
+    schedule::CronSchedule,
+    schedule_windows::{
+        self,
+        ScheduleWindows,
+    },
+    shots::ShotList,
+    template::DEFAULT_ALT_TEXT_TEMPLATE,
+    tiles::TileLayout,
+};
+
+/// Largest image blob Bluesky accepts, in bytes.
+pub const PLATFORM_BLOB_LIMIT: usize = 1_000_000;
+
+/// Maximum JPEG file size in bytes before compression quality is reduced,
+/// when none is configured.
+pub const DEFAULT_MAX_JPEG_SIZE: usize = PLATFORM_BLOB_LIMIT;
 
 /// Minimum JPEG quality setting before giving up on compression.
 pub const MIN_JPEG_QUALITY: u8 = 10;
@@ -13,53 +64,1314 @@ pub const MIN_JPEG_QUALITY: u8 = 10;
 /// Quality reduction step size when file is too large.
 pub const JPEG_QUALITY_STEP: u8 = 5;
 
+/// Scale applied to a frame's dimensions each time it's downscaled to stay
+/// above the quality floor, when no step is configured.
+pub const DEFAULT_DOWNSCALE_STEP: f64 = 0.9;
+
 /// Directory containing JXL frame files.
 pub const FRAMES_DIR: &str = "frames";
 
+/// Manifest of frame SHA-256 checksums, in `sha256sum` format.
+pub const MANIFEST_FILE: &str = "frames/frames.manifest";
+
+/// Directory the `precompress` command writes processed frames to.
+pub const PRECOMPRESSED_DIR: &str = "frames_compressed";
+
+/// Index of the dimensions and quality of each precompressed frame.
+pub const PRECOMPRESSED_INDEX_FILE: &str = "frames_compressed/index.json";
+
 /// File storing the Bluesky session data.
+#[cfg(feature = "bluesky")]
 pub const SESSION_FILE: &str = "config/session.toml";
 
 /// File storing frame posting progress.
 pub const FRAME_DATA_FILE: &str = "config/frame_data.toml";
 
-/// Seconds between frame posts.
+/// File locked while a post reads and advances the frame counter, so instances
+/// sharing `FRAME_DATA_FILE` never post the same frame.
+pub const FRAME_DATA_LOCK_FILE: &str = "config/frame_data.toml.lock";
+
+/// File storing an uploaded frame whose post hasn't been created yet.
+#[cfg(feature = "bluesky")]
+pub const PENDING_POST_FILE: &str = "config/pending_post.json";
+
+/// File storing how many scheduled posts in a row have failed, present only
+/// while they're failing.
+pub const FAILURE_STREAK_FILE: &str = "config/failure_streak.toml";
+
+/// File storing progress through the intro cards, present only mid-intro.
+#[cfg(feature = "bluesky")]
+pub const INTRO_PROGRESS_FILE: &str = "config/intro_progress.toml";
+
+/// File storing the entries of the Atom feed, which is rendered from them.
+#[cfg(feature = "bluesky")]
+pub const ATOM_FEED_ENTRIES_FILE: &str = "config/atom_feed.json";
+
+/// Posts kept in the Atom feed when no maximum is configured.
+#[cfg(feature = "bluesky")]
+pub const DEFAULT_ATOM_FEED_MAX_ENTRIES: usize = 50;
+
+/// Directory processed frames are written to when built without Bluesky support.
+#[cfg(not(feature = "bluesky"))]
+pub const DUMP_DIR: &str = "dump";
+
+/// Seconds between frame posts when no interval is configured.
 pub const POST_INTERVAL_SECONDS: u32 = 1800;
 
+/// Maximum graphemes of OCR text appended to alt text.
+#[cfg(feature = "ocr")]
+pub const OCR_MAX_GRAPHEMES: usize = 300;
+
+/// Separator between primary and secondary alt text when none is configured.
+#[cfg(feature = "bluesky")]
+pub const DEFAULT_ALT_TEXT_SEPARATOR: &str = "\n\n";
+
+/// Maximum length of post text in graphemes, as enforced by Bluesky.
+#[cfg(feature = "bluesky")]
+pub const MAX_POST_GRAPHEMES: usize = 300;
+
+/// Digits the frame number tag is zero-padded to by default.
+#[cfg(feature = "bluesky")]
+pub const DEFAULT_FRAME_TAG_PADDING: usize = 5;
+
+/// Most digits the frame number tag may be padded to, keeping it well within
+/// Bluesky's tag length limit.
+#[cfg(feature = "bluesky")]
+pub const MAX_FRAME_TAG_PADDING: usize = 20;
+
+/// File recording which reply root the first post was already made under.
+#[cfg(feature = "bluesky")]
+pub const REPLY_SEED_FILE: &str = "config/reply_seed.txt";
+
+/// Real seconds a frame is held per second of shot duration by default.
+pub const DEFAULT_SHOT_TIME_SCALE: f64 = 60.0;
+
 /// Maximum retry attempts for failed posts.
+#[cfg(feature = "bluesky")]
 pub const MAX_RETRIES: u32 = 3;
 
 /// Delay between retry attempts.
+#[cfg(feature = "bluesky")]
 pub const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
 
+/// Times a failed blob upload is retried within a single post attempt, unless
+/// UPLOAD_RETRIES is set.
+#[cfg(feature = "bluesky")]
+pub const DEFAULT_UPLOAD_RETRIES: u32 = 2;
+
+/// Mean luminance, out of 255, below which `SKIP_BLACK_FRAMES` skips a frame
+/// when `BLACK_FRAME_THRESHOLD` isn't set.
+pub const DEFAULT_BLACK_FRAME_THRESHOLD: u8 = 10;
+
+/// How far `KEN_BURNS` zooms in when `KEN_BURNS_ZOOM` isn't set.
+#[cfg(feature = "ken-burns")]
+pub const DEFAULT_KEN_BURNS_ZOOM: f64 = 1.2;
+
+/// Length of `KEN_BURNS` videos in seconds when `KEN_BURNS_DURATION_SECONDS`
+/// isn't set.
+#[cfg(feature = "ken-burns")]
+pub const DEFAULT_KEN_BURNS_DURATION_SECONDS: u64 = 6;
+
+/// Longest `KEN_BURNS` video in seconds, well within Bluesky's limit.
+#[cfg(feature = "ken-burns")]
+pub const MAX_KEN_BURNS_DURATION_SECONDS: u64 = 60;
+
+/// Frames the `filmstrip` command shows when `--count` isn't given.
+pub const DEFAULT_FILMSTRIP_FRAMES: usize = 8;
+
+/// Most frames the `filmstrip` command shows, to keep the image a sane width.
+pub const MAX_FILMSTRIP_FRAMES: usize = 50;
+
+/// Height in pixels of each thumbnail in a filmstrip.
+pub const FILMSTRIP_THUMBNAIL_HEIGHT: u32 = 180;
+
+/// File the `filmstrip` command writes to when `--output` isn't given.
+pub const FILMSTRIP_FILE: &str = "filmstrip.jpg";
+
+/// Delay before retrying a failed blob upload, doubled after each retry.
+#[cfg(feature = "bluesky")]
+pub const UPLOAD_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Times to fetch the feed when verifying a post before giving up.
+#[cfg(feature = "bluesky")]
+pub const VERIFY_ATTEMPTS: u32 = 3;
+
+/// Delay between feed fetches when verifying a post.
+#[cfg(feature = "bluesky")]
+pub const VERIFY_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Recent posts to look through when verifying a post.
+#[cfg(feature = "bluesky")]
+pub const VERIFY_FEED_LIMIT: u8 = 10;
+
+/// Unit of the interval between scheduled posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntervalUnit {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl FromStr for IntervalUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(unit: &str) -> anyhow::Result<Self> {
+        match unit {
+            "seconds" => Ok(Self::Seconds),
+            "minutes" => Ok(Self::Minutes),
+            "hours" => Ok(Self::Hours),
+            _ => bail!("Expected seconds, minutes, or hours"),
+        }
+    }
+}
+
+impl fmt::Display for IntervalUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Seconds => "seconds",
+            Self::Minutes => "minutes",
+            Self::Hours => "hours",
+        })
+    }
+}
+
+/// How the frame files in the frames directory are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameNameFormat {
+    /// Named by frame number, like `123.jpg`
+    #[default]
+    Number,
+    /// Named by timecode, like `00_01_23_456.jpg`, and posted in timecode order
+    Timecode,
+}
+
+impl FromStr for FrameNameFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "number" => Ok(Self::Number),
+            "timecode" => Ok(Self::Timecode),
+            _ => bail!("Expected number or timecode"),
+        }
+    }
+}
+
+/// Where frame files are loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum FrameSource {
+    /// The frames directory
+    #[default]
+    Local,
+    /// Under a base URL, without a trailing slash
+    #[cfg(feature = "remote-frames")]
+    Http(String),
+}
+
+impl fmt::Display for FrameSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "directory '{}'", FRAMES_DIR),
+            #[cfg(feature = "remote-frames")]
+            Self::Http(base_url) => write!(f, "{}", base_url),
+        }
+    }
+}
+
+impl FromStr for FrameSource {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> anyhow::Result<Self> {
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+        match source {
+            "local" => Ok(Self::Local),
+            #[cfg(feature = "remote-frames")]
+            _ if is_url => Ok(Self::Http(source.trim_end_matches('/').to_string())),
+            #[cfg(not(feature = "remote-frames"))]
+            _ if is_url => {
+                bail!("Loading frames over HTTP needs the bot built with the remote-frames feature")
+            }
+            _ => bail!("Expected local or an http(s) URL"),
+        }
+    }
+}
+
+/// Where frames are posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Bluesky, or dumping to disk when built without the `bluesky` feature
+    #[default]
+    Bluesky,
+    /// A Discord channel, through a webhook
+    #[cfg(feature = "discord")]
+    Discord,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(backend: &str) -> anyhow::Result<Self> {
+        match backend {
+            "bluesky" => Ok(Self::Bluesky),
+            #[cfg(feature = "discord")]
+            "discord" => Ok(Self::Discord),
+            #[cfg(not(feature = "discord"))]
+            "discord" => bail!("Posting to Discord needs the bot built with the discord feature"),
+            _ => bail!("Expected bluesky or discord"),
+        }
+    }
+}
+
+/// Encoder JPEG output is made with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JpegEncoder {
+    /// The `image` crate's built-in encoder
+    #[default]
+    Image,
+    /// mozjpeg's `cjpeg` CLI, slower but with smaller output
+    #[cfg(feature = "mozjpeg")]
+    Mozjpeg {
+        /// Write progressive rather than baseline JPEGs
+        progressive: bool,
+    },
+}
+
+impl FromStr for JpegEncoder {
+    type Err = anyhow::Error;
+
+    fn from_str(encoder: &str) -> anyhow::Result<Self> {
+        match encoder {
+            "image" => Ok(Self::Image),
+            #[cfg(feature = "mozjpeg")]
+            "mozjpeg" => Ok(Self::Mozjpeg { progressive: false }),
+            #[cfg(not(feature = "mozjpeg"))]
+            "mozjpeg" => bail!("The mozjpeg encoder needs the bot built with the mozjpeg feature"),
+            _ => bail!("Expected image or mozjpeg"),
+        }
+    }
+}
+
+/// Image format frames are posted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Jpeg,
+    /// Smaller than JPEG at the same quality, but much slower to encode
+    #[cfg(feature = "avif")]
+    Avif,
+}
+
+impl OutputFormat {
+    /// File extension for images in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            #[cfg(feature = "avif")]
+            Self::Avif => "avif",
+        }
+    }
+
+    /// MIME type of images in this format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            #[cfg(feature = "avif")]
+            Self::Avif => "image/avif",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jpeg => write!(f, "JPEG"),
+            #[cfg(feature = "avif")]
+            Self::Avif => write!(f, "AVIF"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "jpeg" => Ok(Self::Jpeg),
+            #[cfg(feature = "avif")]
+            "avif" => Ok(Self::Avif),
+            #[cfg(not(feature = "avif"))]
+            "avif" => bail!("AVIF output needs the bot built with the avif feature"),
+            _ => bail!("Expected jpeg or avif"),
+        }
+    }
+}
+
+/// What to do when the saved frame is past the last frame, e.g. after frames
+/// were removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRangePolicy {
+    /// Refuse to continue
+    #[default]
+    Error,
+    /// Continue from the last frame
+    Clamp,
+    /// Start over from frame 1
+    Reset,
+}
+
+impl FromStr for OutOfRangePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(policy: &str) -> anyhow::Result<Self> {
+        match policy {
+            "error" => Ok(Self::Error),
+            "clamp" => Ok(Self::Clamp),
+            "reset" => Ok(Self::Reset),
+            _ => bail!("Expected error, clamp, or reset"),
+        }
+    }
+}
+
+/// Who may reply to the bot's posts, parsed from `nobody` or a comma-separated
+/// list of rules like `mentioned,following`.
+#[cfg(feature = "bluesky")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyGate {
+    /// Groups allowed to reply, empty when nobody can
+    pub allow: Vec<ReplyRule>,
+}
+
+/// A group of accounts allowed to reply to a gated post.
+#[cfg(feature = "bluesky")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyRule {
+    /// Accounts mentioned in the post
+    Mentioned,
+    /// Accounts the bot follows
+    Following,
+    /// Accounts following the bot
+    Followers,
+}
+
+#[cfg(feature = "bluesky")]
+impl FromStr for ReplyGate {
+    type Err = anyhow::Error;
+
+    fn from_str(gate: &str) -> anyhow::Result<Self> {
+        if gate == "nobody" {
+            return Ok(Self { allow: Vec::new() });
+        }
+
+        let allow = gate
+            .split(',')
+            .map(|rule| match rule.trim() {
+                "mentioned" => Ok(ReplyRule::Mentioned),
+                "following" => Ok(ReplyRule::Following),
+                "followers" => Ok(ReplyRule::Followers),
+                rule => bail!(
+                    "Unknown reply rule '{}', expected nobody or a list of mentioned, following, and followers",
+                    rule
+                ),
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { allow })
+    }
+}
+
+/// An existing post referred to by frame posts, like a root to reply under.
+#[cfg(feature = "bluesky")]
+#[derive(Debug, Clone)]
+pub struct PostRef {
+    /// AT URI of the post, e.g. `at://did:plc:abc/app.bsky.feed.post/xyz`
+    pub uri: String,
+    /// CID of the post
+    pub cid: String,
+}
+
+/// A link card posted in place of the image embed, with the frame as its
+/// thumbnail.
+#[cfg(feature = "bluesky")]
+#[derive(Debug, Clone)]
+pub struct LinkCard {
+    /// Page the card links to, e.g. the movie's page on a film database
+    pub url: String,
+    /// Title shown on the card, the movie name if not set
+    pub title: Option<String>,
+    /// Template for the description shown on the card
+    pub description: String,
+}
+
+#[cfg(feature = "bluesky")]
+impl PostRef {
+    /// Read a post from the `<name>_URI` and `<name>_CID` settings.
+    ///
+    /// Both must be set together. The URI must point at a post record.
+    fn from_settings(settings: &Settings, name: &str) -> anyhow::Result<Option<Self>> {
+        let (uri, cid) = match (
+            settings.var(&format!("{name}_URI")),
+            settings.var(&format!("{name}_CID")),
+        ) {
+            (Some(uri), Some(cid)) => (uri, cid),
+            (None, None) => return Ok(None),
+            _ => bail!("{name}_URI and {name}_CID must be set together"),
+        };
+
+        let is_post_uri = uri.strip_prefix("at://").is_some_and(|path| {
+            let segments: Vec<&str> = path.split('/').collect();
+            matches!(segments[..], [authority, "app.bsky.feed.post", record_key]
+                if !authority.is_empty() && !record_key.is_empty())
+        });
+        if !is_post_uri {
+            bail!(
+                "{}_URI '{}' is not a post URI like at://<did>/app.bsky.feed.post/<id>",
+                name,
+                uri
+            );
+        }
+
+        #[cfg(feature = "bluesky")]
+        cid.parse::<bsky_sdk::api::types::string::Cid>()
+            .with_context(|| format!("Invalid {}_CID: {}", name, cid))?;
+
+        Ok(Some(Self { uri, cid }))
+    }
+}
+
 /// Bot configuration loaded from environment variables.
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Bluesky account identifier
+    #[cfg(feature = "bluesky")]
     pub identifier: String,
     /// Bluesky app password
+    #[cfg(feature = "bluesky")]
     pub app_password: String,
+    /// App passwords for the same account to fall back on, in order
+    #[cfg(feature = "bluesky")]
+    pub fallback_app_passwords: Vec<String>,
+    /// Where frames are posted
+    pub backend: Backend,
+    /// Discord webhook to post frames to when the backend is Discord
+    #[cfg(feature = "discord")]
+    pub discord_webhook_url: Option<String>,
     /// Movie name for generating alt text
     pub movie_name: String,
     /// Whether to post frames immediately on startup
     pub post_immediately: bool,
+    /// Time to wait on startup before the first post or scheduled tick
+    pub startup_delay: Duration,
+    /// Number of `post_interval_unit`s between scheduled posts
+    pub post_interval: u32,
+    /// Unit of `post_interval`
+    pub post_interval_unit: IntervalUnit,
+    /// Cron schedule to post on instead of a fixed interval, if any
+    pub post_cron: Option<CronSchedule>,
+    /// Shot lengths to pace posts by instead of a fixed interval, if any
+    pub shots: Option<ShotList>,
+    /// Times of day to post at their own interval instead of `post_interval`,
+    /// if any
+    pub schedule_windows: Option<ScheduleWindows>,
+    /// Days of the week to post on, every day if not set
+    pub post_days: Option<PostDays>,
+    /// Scenes to post in shuffled order, if any
+    pub scenes: Option<SceneList>,
+    /// Whether to back up and recreate a corrupt frame data file
+    pub recover_corrupt_state: bool,
+    /// Whether to post frames made by the `precompress` command when available
+    pub use_precompressed: bool,
+    /// Whether to only advance the frame counter on each tick, without
+    /// processing or posting anything
+    pub dry_advance: bool,
+    /// Whether to process the next frame in the background after each post
+    pub prewarm_next_frame: bool,
+    /// Whether to log luminance statistics of each frame decoded for
+    /// recompression
+    pub analyze_frames: bool,
+    /// Whether to confirm each post appears in the account's feed before
+    /// advancing the frame counter
+    #[cfg(feature = "bluesky")]
+    pub verify_posted: bool,
+    /// Times a blob upload that failed in transit is retried before the post
+    /// attempt fails
+    #[cfg(feature = "bluesky")]
+    pub upload_retries: u32,
+    /// Pause between successive blob uploads for the same post
+    #[cfg(feature = "bluesky")]
+    pub inter_upload_delay: Duration,
+    /// How to handle a saved frame past the last frame
+    pub on_out_of_range: OutOfRangePolicy,
+    /// How frame files are named
+    pub frame_name_format: FrameNameFormat,
+    /// Where frame files are loaded from
+    pub frame_source: FrameSource,
+    /// Frame rate of the movie, used to convert timecodes to frame numbers
+    pub fps: Option<f64>,
+    /// How to burn the timecode into posted frames, if enabled
+    pub timecode_overlay: Option<TimecodeOverlay>,
+    /// When the film starts, to date each post by its frame's position in
+    /// the film instead of when it was posted, if set
+    #[cfg(feature = "bluesky")]
+    pub film_start: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// Number of frames to advance after each post
+    pub frame_step: u32,
+    /// Most times the same frame may be posted in a row before it's skipped,
+    /// if limited
+    pub max_frame_repeats: Option<u32>,
+    /// Whether to verify frames against the checksum manifest before posting
+    pub verify_checksums: bool,
+    /// Whether to strip EXIF, XMP, ICC profiles and comments from posted JPEGs
+    pub strip_metadata: bool,
+    /// Whether to embed the movie name and frame number in posted JPEGs as a
+    /// comment
+    pub embed_metadata: bool,
+    /// Minimum SSIM a recompressed frame must keep versus its source, if any
+    pub ssim_target: Option<f64>,
+    /// Lowest JPEG quality to accept before downscaling the frame instead, if any
+    pub quality_floor: Option<u8>,
+    /// Image format to post frames in
+    pub output_format: OutputFormat,
+    /// Encoder to make JPEG output with
+    pub jpeg_encoder: JpegEncoder,
+    /// Configured maximum JPEG size in bytes, see `jpeg_size_limit`
+    pub max_jpeg_size: usize,
+    /// Size in bytes above which originals are recompressed even when they
+    /// are within the maximum
+    pub recompress_above: usize,
+    /// Scale applied to the frame's dimensions on each downscale
+    pub downscale_step: f64,
+    /// Maximum decoded pixel count a frame may have before it's rejected, if any
+    pub max_pixels: Option<u64>,
+    /// Mean luminance below which frames are skipped as black, if they are
+    pub black_frame_threshold: Option<u8>,
+    /// Megapixel count to downscale frames to before compressing, if any
+    pub target_megapixels: Option<f64>,
+    /// Width to scale every frame to before compressing, if any
+    pub post_width: Option<u32>,
+    /// Largest width or height to declare in a post's aspect ratio, if any
+    #[cfg(feature = "bluesky")]
+    pub max_aspect_dimension: Option<u32>,
+    /// Grid to split each frame into for a multi-image post, if any
+    pub tile_mode: Option<TileLayout>,
+    /// Aspect ratio and focal point to crop frames to before posting, if any
+    pub crop: Option<FrameCrop>,
+    /// How to animate frames posted to Bluesky as video, if at all
+    #[cfg(feature = "ken-burns")]
+    pub ken_burns: Option<KenBurns>,
+    /// Time limit for decoding and recompressing a frame, if any
+    pub compression_timeout: Option<Duration>,
+    /// Template for image alt text
+    pub alt_text_template: String,
+    /// Hand-written alt text for individual frames, used instead of the
+    /// templates, if any
+    #[cfg(feature = "bluesky")]
+    pub alt_texts: Option<AltTextMap>,
+    /// Template for a second-language alt text appended to the first, if any
+    pub alt_text_template_secondary: Option<String>,
+    /// Separator placed between the primary and secondary alt text
+    #[cfg(feature = "bluesky")]
+    pub alt_text_separator: String,
+    /// Existing post the first scheduled post replies under, if any
+    #[cfg(feature = "bluesky")]
+    pub reply_root: Option<PostRef>,
+    /// Existing post every frame post quotes alongside its images, if any
+    #[cfg(feature = "bluesky")]
+    pub quote_root: Option<PostRef>,
+    /// Link card to embed instead of the frame image, if any
+    #[cfg(feature = "bluesky")]
+    pub link_card: Option<LinkCard>,
+    /// Who may reply to posts, or `None` to allow everyone
+    #[cfg(feature = "bluesky")]
+    pub reply_gate: Option<ReplyGate>,
+    /// Text prepended to every post, e.g. an emoji
+    #[cfg(feature = "bluesky")]
+    pub post_prefix: String,
+    /// Posts from the end of the cycle to start counting down how many are
+    /// left in the post text, if enabled
+    #[cfg(feature = "bluesky")]
+    pub finale_countdown: Option<u32>,
+    /// Digits to zero-pad the frame number tag to, if frames are tagged
+    #[cfg(feature = "bluesky")]
+    pub frame_tag_padding: Option<usize>,
+    /// Language tags declared on each post
+    #[cfg(feature = "bluesky")]
+    pub post_langs: Vec<Language>,
+    /// File to append a JSON line to for every post made, if any
+    #[cfg(feature = "bluesky")]
+    pub audit_log_file: Option<PathBuf>,
+    /// File to keep an Atom feed of posted frames in, if any
+    pub atom_feed_file: Option<PathBuf>,
+    /// File to keep the URI and CID of each frame's post in, if any
+    #[cfg(feature = "bluesky")]
+    pub post_index_file: Option<PathBuf>,
+    /// Posts to keep in the Atom feed
+    #[cfg(feature = "bluesky")]
+    pub atom_feed_max_entries: usize,
+    /// Address to serve the HTTP preview server on, if any
+    pub http_addr: Option<SocketAddr>,
+    /// Free space in MB below which optional writes like archiving are
+    /// skipped, if any
+    pub min_free_disk_mb: Option<u64>,
+    /// Directory to keep a copy of every posted JPEG in, if any
+    pub archive_dir: Option<PathBuf>,
+    /// Maximum number of archived JPEGs to keep before pruning the oldest
+    pub archive_max_files: Option<usize>,
+    /// Directory of title cards to post before frame 1 of each new cycle, if any
+    #[cfg(feature = "bluesky")]
+    pub intro_dir: Option<PathBuf>,
+    /// Shell command to run after each successful post, if any
+    pub on_post_success: Option<String>,
+    /// Shell command to run when a post fails after all retries, if any
+    pub on_post_failure: Option<String>,
+    /// Shell command to run after the last frame of each cycle, if any
+    pub on_cycle_complete: Option<String>,
+    /// Consecutive failed posts before failures are escalated, if any
+    pub max_consecutive_failures: Option<u32>,
+    /// Whether to exit once failures are escalated
+    pub exit_on_max_failures: bool,
+    /// File to also write the quality summary to on shutdown, if any
+    pub quality_summary_file: Option<PathBuf>,
 }
 
 impl Config {
-    /// Load configuration from environment variables.
+    /// Load configuration from environment variables and the config file.
     ///
     /// Expects BLUESKY_IDENTIFIER, BLUESKY_APP_PASSWORD, and MOVIE_NAME
-    /// to be set in the environment.
-    pub fn from_env() -> anyhow::Result<Self> {
+    /// to be set. The Bluesky credentials are only required when built with
+    /// the `bluesky` feature and posting to Bluesky. When `CONFIG_FILE` points
+    /// to a TOML file, its keys are read as settings of the same name, and
+    /// environment variables take precedence over them. Every setting read
+    /// must be listed in [`KNOWN_SETTINGS`](known_settings::KNOWN_SETTINGS).
+    pub fn load() -> anyhow::Result<Self> {
+        Self::from_settings(Settings::load()?)
+    }
+
+    /// Load the configuration from the given settings, as if read from a
+    /// config file, with placeholders for the required ones not given.
+    #[cfg(test)]
+    pub fn from_values(values: &[(&str, &str)]) -> anyhow::Result<Self> {
+        let required = [
+            ("BLUESKY_IDENTIFIER", "bot.example.com"),
+            ("BLUESKY_APP_PASSWORD", "app-password"),
+            ("MOVIE_NAME", "Spirited Away"),
+        ];
+        // Later values replace earlier ones of the same name
+        let file = required
+            .iter()
+            .chain(values)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Self::from_settings(Settings { file })
+    }
+
+    fn from_settings(settings: Settings) -> anyhow::Result<Self> {
+        let post_interval_unit = settings.parse("POST_INTERVAL_UNIT")?;
+        let post_interval = match (settings.parse("POST_INTERVAL")?, post_interval_unit) {
+            (Some(interval), _) => interval,
+            (None, None) => POST_INTERVAL_SECONDS,
+            (None, Some(_)) => bail!("POST_INTERVAL_UNIT requires POST_INTERVAL to be set"),
+        };
+        if post_interval == 0 {
+            bail!("POST_INTERVAL must be at least 1");
+        }
+
+        let post_cron = settings.parse("POST_CRON")?;
+        let shots = match settings.var("SHOTS_FILE") {
+            Some(path) => {
+                let time_scale = settings
+                    .parse::<f64>("SHOT_TIME_SCALE")?
+                    .unwrap_or(DEFAULT_SHOT_TIME_SCALE);
+                if !(time_scale.is_finite() && time_scale > 0.0) {
+                    bail!("SHOT_TIME_SCALE must be a positive number");
+                }
+                Some(ShotList::load(Path::new(&path), time_scale)?)
+            }
+            None => None,
+        };
+        if post_cron.is_some() && shots.is_some() {
+            bail!("POST_CRON and SHOTS_FILE can't be used together");
+        }
+
+        let schedule_windows = settings.parse("SCHEDULE_WINDOWS")?;
+        if schedule_windows.is_some() && (post_cron.is_some() || shots.is_some()) {
+            bail!("SCHEDULE_WINDOWS can't be used with POST_CRON or SHOTS_FILE");
+        }
+
+        let frame_step = settings.parse("FRAME_STEP")?.unwrap_or(1);
+        if frame_step == 0 {
+            bail!("FRAME_STEP must be at least 1");
+        }
+
+        let max_frame_repeats = settings.parse("MAX_FRAME_REPEATS")?;
+        if max_frame_repeats == Some(0) {
+            bail!("MAX_FRAME_REPEATS must be at least 1");
+        }
+
+        let ssim_target = settings.parse::<f64>("SSIM_TARGET")?;
+        if ssim_target.is_some_and(|target| !(target > 0.0 && target <= 1.0)) {
+            bail!("SSIM_TARGET must be greater than 0 and at most 1");
+        }
+
+        let output_format = settings
+            .parse::<OutputFormat>("OUTPUT_FORMAT")?
+            .unwrap_or_default();
+        // Judging SSIM means decoding the output, and only JPEG can be decoded
+        if ssim_target.is_some() && output_format != OutputFormat::Jpeg {
+            bail!("SSIM_TARGET only works with JPEG output");
+        }
+
+        let progressive_jpeg = settings
+            .var("PROGRESSIVE_JPEG")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        if progressive_jpeg && output_format != OutputFormat::Jpeg {
+            bail!("PROGRESSIVE_JPEG only works with JPEG output");
+        }
+        let jpeg_encoder = match settings.parse("JPEG_ENCODER")?.unwrap_or_default() {
+            #[cfg(feature = "mozjpeg")]
+            JpegEncoder::Mozjpeg { .. } => {
+                mozjpeg::check_available()?;
+                JpegEncoder::Mozjpeg {
+                    progressive: progressive_jpeg,
+                }
+            }
+            // The built-in encoder only writes baseline JPEGs
+            _ if progressive_jpeg => bail!("PROGRESSIVE_JPEG requires JPEG_ENCODER=mozjpeg"),
+            encoder => encoder,
+        };
+
+        let quality_floor = settings.parse::<u8>("ACCEPTABLE_QUALITY_FLOOR")?;
+        if quality_floor.is_some_and(|floor| !(MIN_JPEG_QUALITY..=100).contains(&floor)) {
+            bail!(
+                "ACCEPTABLE_QUALITY_FLOOR must be between {} and 100",
+                MIN_JPEG_QUALITY
+            );
+        }
+
+        let downscale_step = settings
+            .parse::<f64>("DOWNSCALE_STEP")?
+            .unwrap_or(DEFAULT_DOWNSCALE_STEP);
+        if !(downscale_step > 0.0 && downscale_step < 1.0) {
+            bail!("DOWNSCALE_STEP must be greater than 0 and less than 1");
+        }
+
+        let max_pixels = settings.parse::<u64>("MAX_PIXELS")?;
+        if max_pixels == Some(0) {
+            bail!("MAX_PIXELS must be at least 1");
+        }
+
+        let black_frame_threshold = match settings.var("SKIP_BLACK_FRAMES").as_deref() {
+            Some("true") => Some(
+                settings
+                    .parse("BLACK_FRAME_THRESHOLD")?
+                    .unwrap_or(DEFAULT_BLACK_FRAME_THRESHOLD),
+            ),
+            _ => None,
+        };
+
+        let target_megapixels = settings.parse::<f64>("TARGET_MEGAPIXELS")?;
+        if target_megapixels.is_some_and(|target| !(target.is_finite() && target > 0.0)) {
+            bail!("TARGET_MEGAPIXELS must be a positive number");
+        }
+
+        let post_width = settings.parse::<u32>("POST_WIDTH")?;
+        if post_width == Some(0) {
+            bail!("POST_WIDTH must be at least 1");
+        }
+        if post_width.is_some() && target_megapixels.is_some() {
+            bail!("POST_WIDTH can't be combined with TARGET_MEGAPIXELS");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let max_aspect_dimension = settings.parse::<u32>("MAX_ASPECT_DIMENSION")?;
+        #[cfg(feature = "bluesky")]
+        if max_aspect_dimension == Some(0) {
+            bail!("MAX_ASPECT_DIMENSION must be at least 1");
+        }
+
+        let compression_timeout = settings.parse::<u64>("COMPRESSION_TIMEOUT_SECONDS")?;
+        if compression_timeout == Some(0) {
+            bail!("COMPRESSION_TIMEOUT_SECONDS must be at least 1");
+        }
+
+        let fps = settings.parse::<f64>("FPS")?;
+        if fps.is_some_and(|fps| !(fps.is_finite() && fps > 0.0)) {
+            bail!("FPS must be a positive number");
+        }
+
+        let max_jpeg_size = settings
+            .parse("MAX_JPEG_SIZE")?
+            .unwrap_or(DEFAULT_MAX_JPEG_SIZE);
+        if max_jpeg_size == 0 {
+            bail!("MAX_JPEG_SIZE must be at least 1");
+        }
+        if max_jpeg_size > PLATFORM_BLOB_LIMIT {
+            warn!(
+                "MAX_JPEG_SIZE of {} bytes is over Bluesky's limit, images will be kept under {} bytes instead",
+                max_jpeg_size, PLATFORM_BLOB_LIMIT
+            );
+        }
+
+        let recompress_above = settings.parse("RECOMPRESS_ABOVE")?.unwrap_or(max_jpeg_size);
+
+        let max_consecutive_failures = settings.parse::<u32>("MAX_CONSECUTIVE_FAILURES")?;
+        if max_consecutive_failures == Some(0) {
+            bail!("MAX_CONSECUTIVE_FAILURES must be at least 1");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let atom_feed_max_entries = settings
+            .parse("ATOM_FEED_MAX_ENTRIES")?
+            .unwrap_or(DEFAULT_ATOM_FEED_MAX_ENTRIES);
+        #[cfg(feature = "bluesky")]
+        if atom_feed_max_entries == 0 {
+            bail!("ATOM_FEED_MAX_ENTRIES must be at least 1");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let finale_countdown = settings.parse("FINALE_COUNTDOWN")?;
+        #[cfg(feature = "bluesky")]
+        if finale_countdown == Some(0) {
+            bail!("FINALE_COUNTDOWN must be at least 1");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let frame_tag_padding = match settings.var("TAG_FRAME_NUMBER").as_deref() {
+            Some("true") => Some(
+                settings
+                    .parse("FRAME_TAG_PADDING")?
+                    .unwrap_or(DEFAULT_FRAME_TAG_PADDING),
+            ),
+            _ => None,
+        };
+        #[cfg(feature = "bluesky")]
+        if frame_tag_padding.is_some_and(|padding| padding > MAX_FRAME_TAG_PADDING) {
+            bail!(
+                "FRAME_TAG_PADDING must be at most {}",
+                MAX_FRAME_TAG_PADDING
+            );
+        }
+
+        let timecode_overlay = match settings.var("OVERLAY_TIMECODE").as_deref() {
+            Some("true") => Some(TimecodeOverlay {
+                position: settings.parse("OVERLAY_POSITION")?.unwrap_or_default(),
+                color: settings.parse("OVERLAY_COLOR")?.unwrap_or_default(),
+                font_size: settings.parse("OVERLAY_FONT_SIZE")?,
+            }),
+            _ => None,
+        };
+        if timecode_overlay.is_some() && fps.is_none() {
+            bail!("OVERLAY_TIMECODE requires FPS to be set");
+        }
+        if timecode_overlay.is_some_and(|overlay| overlay.font_size == Some(0)) {
+            bail!("OVERLAY_FONT_SIZE must be at least 1");
+        }
+
+        let crop_aspect = settings.parse("CROP_ASPECT")?;
+        let focal_point = settings.parse("FOCAL_POINT")?;
+        let crop = (crop_aspect.is_some() || focal_point.is_some()).then(|| FrameCrop {
+            aspect: crop_aspect.unwrap_or_default(),
+            focal_point: focal_point.unwrap_or_default(),
+        });
+
+        let tile_mode = settings.parse("TILE_MODE")?;
+        let ken_burns = settings
+            .var("KEN_BURNS")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+        #[cfg(not(feature = "ken-burns"))]
+        if ken_burns {
+            bail!("KEN_BURNS needs the bot built with the ken-burns feature");
+        }
+        #[cfg(feature = "ken-burns")]
+        let ken_burns = match ken_burns {
+            true => Some(KenBurns {
+                zoom: settings
+                    .parse("KEN_BURNS_ZOOM")?
+                    .unwrap_or(DEFAULT_KEN_BURNS_ZOOM),
+                duration: Duration::from_secs(
+                    settings
+                        .parse("KEN_BURNS_DURATION_SECONDS")?
+                        .unwrap_or(DEFAULT_KEN_BURNS_DURATION_SECONDS),
+                ),
+                focal_point: focal_point.unwrap_or_default(),
+            }),
+            false => None,
+        };
+        #[cfg(feature = "ken-burns")]
+        if let Some(ken_burns) = ken_burns {
+            if !(ken_burns.zoom > 1.0 && ken_burns.zoom <= 4.0) {
+                bail!("KEN_BURNS_ZOOM must be greater than 1 and at most 4");
+            }
+            let duration = ken_burns.duration.as_secs();
+            if !(1..=MAX_KEN_BURNS_DURATION_SECONDS).contains(&duration) {
+                bail!(
+                    "KEN_BURNS_DURATION_SECONDS must be between 1 and {}",
+                    MAX_KEN_BURNS_DURATION_SECONDS
+                );
+            }
+            if tile_mode.is_some() {
+                bail!("KEN_BURNS can't be combined with TILE_MODE");
+            }
+            // The video is rendered from the processed frame, which must be decodable
+            if output_format != OutputFormat::Jpeg {
+                bail!("KEN_BURNS only works with JPEG output");
+            }
+        }
+
+        #[cfg(feature = "bluesky")]
+        let link_card = match settings.var("LINK_CARD_URL") {
+            Some(url) => {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    bail!("LINK_CARD_URL must be an http or https URL");
+                }
+                Some(LinkCard {
+                    url,
+                    title: settings.var("LINK_CARD_TITLE"),
+                    description: settings
+                        .var("LINK_CARD_DESCRIPTION")
+                        .unwrap_or_else(|| DEFAULT_LINK_CARD_DESCRIPTION.to_string()),
+                })
+            }
+            None => None,
+        };
+        // A link card has room for a single thumbnail and no video
+        #[cfg(feature = "bluesky")]
+        if link_card.is_some() && tile_mode.is_some() {
+            bail!("LINK_CARD_URL can't be combined with TILE_MODE");
+        }
+        #[cfg(all(feature = "bluesky", feature = "ken-burns"))]
+        if link_card.is_some() && ken_burns.is_some() {
+            bail!("LINK_CARD_URL can't be combined with KEN_BURNS");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let film_start = settings.parse("FILM_START")?;
+        #[cfg(feature = "bluesky")]
+        if film_start.is_some() && fps.is_none() {
+            bail!("FILM_START requires FPS to be set");
+        }
+
+        let frame_name_format = settings.parse("FRAME_NAME_FORMAT")?.unwrap_or_default();
+        let frame_source = settings
+            .parse::<FrameSource>("FRAME_SOURCE")?
+            .unwrap_or_default();
+        // Ordering by timecode needs every file name, which only a listing gives
+        if frame_source != FrameSource::Local && frame_name_format == FrameNameFormat::Timecode {
+            bail!("FRAME_NAME_FORMAT=timecode needs frames loaded from the local directory");
+        }
+
+        let backend = settings.parse("BACKEND")?.unwrap_or_default();
+        #[cfg(feature = "discord")]
+        let discord_webhook_url = settings.var("DISCORD_WEBHOOK_URL");
+        #[cfg(feature = "discord")]
+        if backend == Backend::Discord && discord_webhook_url.is_none() {
+            bail!("BACKEND=discord requires DISCORD_WEBHOOK_URL to be set");
+        }
+
+        #[cfg(feature = "bluesky")]
+        let post_langs = settings
+            .var("POST_LANGS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|lang| !lang.is_empty())
+                    .map(|lang| {
+                        lang.parse::<Language>().map_err(|e| {
+                            anyhow::anyhow!("Invalid language '{}' in POST_LANGS: {}", lang, e)
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        // Only posting to Bluesky needs the account's credentials
+        #[cfg(feature = "bluesky")]
+        let credential = |name: &str| match settings.var(name) {
+            Some(value) => Ok(value),
+            None if backend != Backend::Bluesky => Ok(String::new()),
+            None => bail!("Missing {} setting", name),
+        };
+
         Ok(Self {
-            identifier: env::var("BLUESKY_IDENTIFIER")
-                .context("Missing BLUESKY_IDENTIFIER environment variable")?,
-            app_password: env::var("BLUESKY_APP_PASSWORD")
-                .context("Missing BLUESKY_APP_PASSWORD environment variable")?,
-            movie_name: env::var("MOVIE_NAME")
-                .context("Missing MOVIE_NAME environment variable")?,
-            post_immediately: env::var("POST_IMMEDIATELY")
+            #[cfg(feature = "bluesky")]
+            identifier: credential("BLUESKY_IDENTIFIER")?,
+            #[cfg(feature = "bluesky")]
+            app_password: credential("BLUESKY_APP_PASSWORD")?,
+            #[cfg(feature = "bluesky")]
+            fallback_app_passwords: settings
+                .var("BLUESKY_FALLBACK_APP_PASSWORDS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|password| password.trim().to_string())
+                        .filter(|password| !password.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            backend,
+            #[cfg(feature = "discord")]
+            discord_webhook_url,
+            movie_name: settings
+                .var("MOVIE_NAME")
+                .context("Missing MOVIE_NAME setting")?,
+            post_immediately: settings
+                .var("POST_IMMEDIATELY")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            startup_delay: Duration::from_secs(
+                settings.parse("STARTUP_DELAY_SECONDS")?.unwrap_or(0),
+            ),
+            post_interval,
+            post_interval_unit: post_interval_unit.unwrap_or(IntervalUnit::Seconds),
+            post_cron,
+            shots,
+            schedule_windows,
+            post_days: settings.parse("POST_DAYS")?,
+            scenes: settings
+                .var("SCENES_FILE")
+                .map(|path| SceneList::load(Path::new(&path)))
+                .transpose()?,
+            recover_corrupt_state: settings
+                .var("RECOVER_CORRUPT_STATE")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            use_precompressed: settings
+                .var("USE_PRECOMPRESSED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            dry_advance: settings
+                .var("DRY_ADVANCE")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            prewarm_next_frame: settings
+                .var("PREWARM_NEXT_FRAME")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            analyze_frames: settings
+                .var("ANALYZE_FRAMES")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            #[cfg(feature = "bluesky")]
+            verify_posted: settings
+                .var("VERIFY_POSTED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            #[cfg(feature = "bluesky")]
+            upload_retries: settings
+                .parse("UPLOAD_RETRIES")?
+                .unwrap_or(DEFAULT_UPLOAD_RETRIES),
+            #[cfg(feature = "bluesky")]
+            inter_upload_delay: Duration::from_millis(
+                settings.parse("INTER_UPLOAD_DELAY_MS")?.unwrap_or(0),
+            ),
+            on_out_of_range: settings.parse("ON_OUT_OF_RANGE")?.unwrap_or_default(),
+            frame_name_format,
+            frame_source,
+            fps,
+            timecode_overlay,
+            #[cfg(feature = "bluesky")]
+            film_start,
+            frame_step,
+            max_frame_repeats,
+            verify_checksums: settings
+                .var("VERIFY_CHECKSUMS")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            strip_metadata: settings
+                .var("STRIP_METADATA")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            embed_metadata: settings
+                .var("EMBED_METADATA")
                 .map(|v| v == "true")
                 .unwrap_or(false),
+            ssim_target,
+            output_format,
+            jpeg_encoder,
+            max_jpeg_size,
+            recompress_above,
+            quality_floor,
+            downscale_step,
+            max_pixels,
+            black_frame_threshold,
+            target_megapixels,
+            post_width,
+            #[cfg(feature = "bluesky")]
+            max_aspect_dimension,
+            tile_mode,
+            crop,
+            #[cfg(feature = "ken-burns")]
+            ken_burns,
+            compression_timeout: compression_timeout.map(Duration::from_secs),
+            alt_text_template: settings
+                .var("ALT_TEXT_TEMPLATE")
+                .unwrap_or_else(|| DEFAULT_ALT_TEXT_TEMPLATE.to_string()),
+            #[cfg(feature = "bluesky")]
+            alt_texts: settings
+                .var("ALT_TEXT_FILE")
+                .map(|path| AltTextMap::load(Path::new(&path)))
+                .transpose()?,
+            alt_text_template_secondary: settings.var("ALT_TEXT_TEMPLATE_SECONDARY"),
+            #[cfg(feature = "bluesky")]
+            alt_text_separator: settings
+                .var("ALT_TEXT_SEPARATOR")
+                .unwrap_or_else(|| DEFAULT_ALT_TEXT_SEPARATOR.to_string()),
+            #[cfg(feature = "bluesky")]
+            reply_root: PostRef::from_settings(&settings, "REPLY_ROOT")?,
+            #[cfg(feature = "bluesky")]
+            quote_root: PostRef::from_settings(&settings, "QUOTE_ROOT")?,
+            #[cfg(feature = "bluesky")]
+            link_card,
+            #[cfg(feature = "bluesky")]
+            reply_gate: settings.parse("REPLY_GATE")?,
+            #[cfg(feature = "bluesky")]
+            post_prefix: settings.var("POST_PREFIX").unwrap_or_default(),
+            #[cfg(feature = "bluesky")]
+            finale_countdown,
+            #[cfg(feature = "bluesky")]
+            frame_tag_padding,
+            #[cfg(feature = "bluesky")]
+            post_langs,
+            #[cfg(feature = "bluesky")]
+            audit_log_file: settings.var("AUDIT_LOG_FILE").map(PathBuf::from),
+            atom_feed_file: settings.var("ATOM_FEED_FILE").map(PathBuf::from),
+            #[cfg(feature = "bluesky")]
+            post_index_file: settings.var("POST_INDEX_FILE").map(PathBuf::from),
+            #[cfg(feature = "bluesky")]
+            atom_feed_max_entries,
+            http_addr: settings.parse("HTTP_ADDR")?,
+            min_free_disk_mb: settings.parse("MIN_FREE_DISK_MB")?,
+            archive_dir: settings.var("ARCHIVE_DIR").map(PathBuf::from),
+            archive_max_files: settings.parse("ARCHIVE_MAX_FILES")?,
+            #[cfg(feature = "bluesky")]
+            intro_dir: settings.var("INTRO_DIR").map(PathBuf::from),
+            on_post_success: settings.var("ON_POST_SUCCESS"),
+            on_post_failure: settings.var("ON_POST_FAILURE"),
+            on_cycle_complete: settings.var("ON_CYCLE_COMPLETE"),
+            max_consecutive_failures,
+            exit_on_max_failures: settings
+                .var("EXIT_ON_MAX_FAILURES")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            quality_summary_file: settings.var("QUALITY_SUMMARY_FILE").map(PathBuf::from),
         })
     }
+
+    /// The primary app password followed by any fallbacks.
+    #[cfg(feature = "bluesky")]
+    pub fn app_passwords(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.app_password)
+            .chain(&self.fallback_app_passwords)
+            .map(String::as_str)
+    }
+
+    /// Whether an alt text template uses the `{color}` placeholder, so frames
+    /// need their average color worked out.
+    pub fn wants_average_color(&self) -> bool {
+        std::iter::once(&self.alt_text_template)
+            .chain(&self.alt_text_template_secondary)
+            .any(|template| template.contains("{color}"))
+    }
+
+    /// Largest JPEG to post, in bytes.
+    ///
+    /// This is the configured maximum, capped at what Bluesky accepts so an
+    /// oversized original is never passed through only to fail on upload.
+    pub fn jpeg_size_limit(&self) -> usize {
+        self.max_jpeg_size.min(PLATFORM_BLOB_LIMIT)
+    }
+
+    /// The fixed interval between scheduled posts.
+    pub fn post_interval_duration(&self) -> Duration {
+        let seconds = match self.post_interval_unit {
+            IntervalUnit::Seconds => 1,
+            IntervalUnit::Minutes => 60,
+            IntervalUnit::Hours => 60 * 60,
+        };
+        Duration::from_secs(u64::from(self.post_interval) * seconds)
+    }
+}
+
+/// Settings read from the environment, falling back to the config file.
+struct Settings {
+    /// Values from the config file, keyed by their environment variable name
+    file: HashMap<String, String>,
+}
+
+impl Settings {
+    /// Read the config file named by `CONFIG_FILE`, if it's set.
+    fn load() -> anyhow::Result<Self> {
+        let Ok(path) = env::var("CONFIG_FILE") else {
+            return Ok(Self {
+                file: HashMap::new(),
+            });
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path))?;
+        let table: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path))?;
+
+        let file = table
+            .into_iter()
+            .map(|(key, value)| Ok((key.to_uppercase(), setting_value(&key, value)?)))
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("Invalid config file {}", path))?;
+
+        Ok(Self { file })
+    }
+
+    /// Look up a setting, preferring the environment over the config file.
+    fn var(&self, name: &str) -> Option<String> {
+        debug_assert!(
+            known_settings::is_known(name),
+            "{name} is missing from the list of known settings"
+        );
+        env::var(name).ok().or_else(|| self.file.get(name).cloned())
+    }
+
+    /// Parse an optional setting into the requested type.
+    ///
+    /// Returns `Ok(None)` when the setting is missing, and an error naming it
+    /// when it is set but cannot be parsed.
+    fn parse<T>(&self, name: &str) -> anyhow::Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Into<anyhow::Error>,
+    {
+        self.var(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(Into::into)
+                    .with_context(|| format!("Invalid {name} setting: {value}"))
+            })
+            .transpose()
+    }
+}
+
+/// Convert a config file value to the text its environment variable would hold.
+///
+/// Lists become comma-separated, as used by e.g. `POST_LANGS`, and the
+/// `schedule_windows` table is converted to the windows it lists.
+fn setting_value(key: &str, value: toml::Value) -> anyhow::Result<String> {
+    match value {
+        toml::Value::Table(table) if key.eq_ignore_ascii_case("schedule_windows") => {
+            schedule_windows::table_to_setting(table)
+        }
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|value| setting_value(key, value))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(|values| values.join(",")),
+        _ => bail!(
+            "Unsupported value for {}, expected a string, number, boolean, or list",
+            key
+        ),
+    }
 }
@@ -0,0 +1,133 @@
+//! Falling back between app passwords for the bot's account.
+//!
+//! `BLUESKY_FALLBACK_APP_PASSWORDS` lists further app passwords for the same
+//! account, tried in order when logging in with the primary one is rate
+//! limited or rejected. A password that hits a rate limit isn't tried again
+//! until [`RATE_LIMIT_COOLDOWN`] has passed.
+//!
+//! Bluesky's rate limits exist to protect the service, and deliberately
+//! working around them is against its terms of service. Fallback passwords
+//! keep the bot running when one password is limited or revoked, they are not
+//! a way to post faster than the limits allow. Keep the post interval well
+//! within them.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex,
+        PoisonError,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use bsky_sdk::{
+    api::{
+        com::atproto::server::create_session,
+        xrpc::{
+            self,
+            http::StatusCode,
+        },
+    },
+    BskyAgent,
+};
+use log::*;
+
+use crate::{
+    bluesky,
+    config::Config,
+};
+
+/// How long a rate limited app password is skipped for.
+const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// When each rate limited app password may be tried again, by its position in
+/// [`Config::app_passwords`].
+static COOLDOWNS: Mutex<BTreeMap<usize, Instant>> = Mutex::new(BTreeMap::new());
+
+/// Log in with the first app password that works and save the session.
+///
+/// Passwords still cooling down from a rate limit are skipped, and ones that
+/// get rate limited now start cooling down.
+pub async fn login(config: &Config) -> anyhow::Result<BskyAgent> {
+    let mut last_error = None;
+    for (index, app_password) in config.app_passwords().enumerate() {
+        if let Some(remaining) = cooldown_remaining(index) {
+            debug!(
+                "Skipping {}, rate limited for another {} seconds",
+                label(index),
+                remaining.as_secs()
+            );
+            continue;
+        }
+
+        match bluesky::login(&config.identifier, app_password).await {
+            Ok(agent) => {
+                if index > 0 {
+                    info!("Logged in with {}", label(index));
+                }
+                return Ok(agent);
+            }
+            Err(e) => {
+                if is_rate_limited(&e) {
+                    warn!(
+                        "Logging in with {} was rate limited, not trying it again for {} minutes",
+                        label(index),
+                        RATE_LIMIT_COOLDOWN.as_secs() / 60
+                    );
+                    start_cooldown(index);
+                } else {
+                    warn!("Failed to log in with {}: {:#}", label(index), e);
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(e).context("Failed to log in with any app password"),
+        None => bail!("Every app password is rate limited, try again later"),
+    }
+}
+
+/// Describe a password by its position without revealing it.
+fn label(index: usize) -> String {
+    match index {
+        0 => "the primary app password".to_string(),
+        _ => format!("fallback app password {}", index),
+    }
+}
+
+/// Whether a login failed because of a rate limit.
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<xrpc::Error<create_session::Error>>(),
+        Some(xrpc::Error::XrpcResponse(response))
+            if response.status == StatusCode::TOO_MANY_REQUESTS
+    )
+}
+
+/// Time left before the password at `index` may be tried again, if any.
+fn cooldown_remaining(index: usize) -> Option<Duration> {
+    let mut cooldowns = COOLDOWNS.lock().unwrap_or_else(PoisonError::into_inner);
+    let until = *cooldowns.get(&index)?;
+
+    let remaining = until.checked_duration_since(Instant::now());
+    if remaining.is_none() {
+        cooldowns.remove(&index);
+    }
+    remaining
+}
+
+fn start_cooldown(index: usize) {
+    COOLDOWNS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(index, Instant::now() + RATE_LIMIT_COOLDOWN);
+}
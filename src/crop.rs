@@ -0,0 +1,141 @@
+//! Cropping frames to a fixed aspect ratio around a focal point.
+//!
+//! Clients crop images to fit their feed previews, often to a square around
+//! the center, which can cut the important part of a wide frame out. Setting
+//! `CROP_ASPECT` crops frames to that shape before posting, so the preview
+//! shows the whole posted image, and `FOCAL_POINT` picks which part of the
+//! frame is kept. Bluesky takes the aspect ratio from the posted image, so no
+//! other crop metadata is needed.
+
+use std::str::FromStr;
+
+use anyhow::{
+    bail,
+    Context,
+};
+
+/// Shape to crop frames to, parsed from e.g. `1:1` or `4:5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropAspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for CropAspect {
+    /// Square, the shape most feeds crop previews to.
+    fn default() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+        }
+    }
+}
+
+impl FromStr for CropAspect {
+    type Err = anyhow::Error;
+
+    fn from_str(aspect: &str) -> anyhow::Result<Self> {
+        let (width, height) = aspect
+            .split_once(':')
+            .with_context(|| format!("Aspect ratio '{}' must look like 4:5", aspect))?;
+        let width: u32 = width
+            .parse()
+            .with_context(|| format!("Invalid aspect width '{}'", width))?;
+        let height: u32 = height
+            .parse()
+            .with_context(|| format!("Invalid aspect height '{}'", height))?;
+
+        if width == 0 || height == 0 {
+            bail!("Aspect ratio '{}' must not have a zero side", aspect);
+        }
+
+        Ok(Self { width, height })
+    }
+}
+
+/// Point of the frame to keep in view, as fractions of its width and height
+/// from the top left, parsed from e.g. `0.5,0.3`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FocalPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for FocalPoint {
+    /// The center of the frame.
+    fn default() -> Self {
+        Self { x: 0.5, y: 0.5 }
+    }
+}
+
+impl FocalPoint {
+    /// Top left corner of a `crop_width`x`crop_height` region of a
+    /// `width`x`height` image, centered on the focal point where the image
+    /// edges allow.
+    pub fn origin(&self, width: u32, height: u32, crop_width: u32, crop_height: u32) -> (u32, u32) {
+        let offset = |length: u32, crop_length: u32, focus: f64| {
+            let centered = focus * length as f64 - crop_length as f64 / 2.0;
+            (centered.round().max(0.0) as u32).min(length - crop_length)
+        };
+        (
+            offset(width, crop_width, self.x),
+            offset(height, crop_height, self.y),
+        )
+    }
+}
+
+impl FromStr for FocalPoint {
+    type Err = anyhow::Error;
+
+    fn from_str(point: &str) -> anyhow::Result<Self> {
+        let (x, y) = point
+            .split_once(',')
+            .with_context(|| format!("Focal point '{}' must look like 0.5,0.3", point))?;
+        let coordinate = |value: &str| -> anyhow::Result<f64> {
+            let value: f64 = value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid focal point coordinate '{}'", value))?;
+            if !(0.0..=1.0).contains(&value) {
+                bail!("Focal point coordinates must be between 0 and 1");
+            }
+            Ok(value)
+        };
+
+        Ok(Self {
+            x: coordinate(x)?,
+            y: coordinate(y)?,
+        })
+    }
+}
+
+/// How to crop frames before posting.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameCrop {
+    /// Shape of the cropped frame
+    pub aspect: CropAspect,
+    /// Point the crop is centered on where the frame edges allow
+    pub focal_point: FocalPoint,
+}
+
+impl FrameCrop {
+    /// The largest region of `width`x`height` with the configured aspect
+    /// ratio around the focal point, as `(x, y, width, height)`.
+    pub fn region(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let (aspect_width, aspect_height) = (self.aspect.width as u64, self.aspect.height as u64);
+        let (crop_width, crop_height) =
+            if width as u64 * aspect_height > height as u64 * aspect_width {
+                // Wider than the target, keep the full height
+                let crop_width = (height as u64 * aspect_width / aspect_height).max(1);
+                (crop_width as u32, height)
+            } else {
+                let crop_height = (width as u64 * aspect_height / aspect_width).max(1);
+                (width, crop_height as u32)
+            };
+
+        let (x, y) = self
+            .focal_point
+            .origin(width, height, crop_width, crop_height);
+        (x, y, crop_width, crop_height)
+    }
+}
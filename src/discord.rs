@@ -0,0 +1,228 @@
+//! Posting frames to a Discord channel through a webhook, with `BACKEND=discord`.
+//!
+//! Frames go through the same processing as for Bluesky and are sent as
+//! attachments to `DISCORD_WEBHOOK_URL`, with their alt text as the attachment
+//! descriptions and the usual post text as the message. A webhook needs no
+//! login, so the Bluesky credentials aren't required. Intro cards, replies and
+//! the commands that post to Bluesky don't apply to Discord.
+
+use std::time::Instant;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use log::*;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    bluesky::{
+        self,
+        PostOutcome,
+    },
+    config::Config,
+    frame_processing::{
+        get_frame_images,
+        load_total_frame_count,
+    },
+    hooks,
+    posting,
+    run_stats,
+    stage_timings::StageTimings,
+};
+
+/// The parts of a created message that get logged.
+#[derive(Deserialize, Debug)]
+struct Message {
+    id: String,
+    channel_id: String,
+}
+
+/// Post the next frame to Discord, retrying on failure like the Bluesky poster.
+pub async fn post_frame_task(config: &Config) {
+    bluesky::retry_post(config, || post_frame(config)).await;
+}
+
+/// Process the current frame, send it to the webhook, and advance.
+///
+/// Frames that can never be posted are skipped by advancing the counter rather
+/// than retried.
+async fn post_frame(config: &Config) -> anyhow::Result<PostOutcome> {
+    let webhook_url = config
+        .discord_webhook_url
+        .as_deref()
+        .context("DISCORD_WEBHOOK_URL is not set")?;
+
+    let mut timings = StageTimings::new();
+    let total_frames = load_total_frame_count(config).await?;
+    let mut frame_info = posting::load_frame_info(config, total_frames)?;
+
+    if posting::skip_repeated_frame(&mut frame_info, config)? {
+        return Ok(PostOutcome::Skipped);
+    }
+    let frame = frame_info.current_frame;
+    let processed_images = get_frame_images(frame, config, &mut timings).await;
+    let Some(processed_images) =
+        posting::skip_unpostable_frame(processed_images, &mut frame_info, config)?
+    else {
+        return Ok(PostOutcome::Skipped);
+    };
+
+    let image_count = processed_images.len();
+    let mut attachments = Vec::with_capacity(image_count);
+    let mut filenames = Vec::with_capacity(image_count);
+    for (index, processed_image) in processed_images.iter().enumerate() {
+        let alt_text = bluesky::image_alt_text(
+            config,
+            frame,
+            total_frames,
+            processed_image,
+            index,
+            image_count,
+        )
+        .await;
+        let filename = format!(
+            "frame_{}_{}.{}",
+            frame,
+            index + 1,
+            config.output_format.extension()
+        );
+        attachments.push(json!({
+            "id": index,
+            "filename": filename,
+            "description": alt_text,
+        }));
+        filenames.push(filename);
+    }
+    let payload = json!({
+        "content": bluesky::post_text(
+            config,
+            "",
+            frame_info.posts_left(config.frame_step, config.scenes.as_ref()),
+        ),
+        "attachments": attachments,
+    });
+
+    let (jpeg_images, qualities): (Vec<_>, Vec<_>) = processed_images
+        .into_iter()
+        .map(|processed_image| (processed_image.jpeg_data, processed_image.quality_used))
+        .unzip();
+    let started = Instant::now();
+    let files = filenames.iter().zip(&jpeg_images);
+    let message = execute_webhook(config, webhook_url, &payload, files).await?;
+    timings.record("upload", started);
+    debug!(
+        "Created message {} in channel {}",
+        message.id, message.channel_id
+    );
+
+    // The message is live from here on, so only advancing the counter may fail
+    frame_info.record_posted();
+    posting::advance_frame(&mut frame_info, config)?;
+    hooks::post_succeeded(config, frame, None);
+    qualities.into_iter().for_each(run_stats::record_quality);
+
+    posting::archive_posted_frame(config, frame, &jpeg_images).await;
+
+    timings.log(&format!("frame {}", frame));
+    info!(
+        "Successfully posted frame {}/{} to Discord",
+        frame, total_frames
+    );
+    Ok(PostOutcome::Posted)
+}
+
+/// Send a message with the named files attached, in the order of `payload`'s
+/// attachments.
+async fn execute_webhook<'a>(
+    config: &Config,
+    webhook_url: &str,
+    payload: &serde_json::Value,
+    files: impl Iterator<Item = (&'a String, &'a Vec<u8>)>,
+) -> anyhow::Result<Message> {
+    let boundary = multipart_boundary()?;
+    let mut body = Vec::new();
+    push_part(
+        &mut body,
+        &boundary,
+        "payload_json",
+        None,
+        "application/json",
+        payload.to_string().as_bytes(),
+    );
+    for (index, (filename, data)) in files.enumerate() {
+        push_part(
+            &mut body,
+            &boundary,
+            &format!("files[{}]", index),
+            Some(filename),
+            config.output_format.mime_type(),
+            data,
+        );
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    // Waiting makes Discord reply with the created message instead of nothing
+    let response = reqwest::Client::new()
+        .post(webhook_url)
+        .query(&[("wait", "true")])
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .send()
+        .await
+        .context("Failed to send Discord webhook request")?;
+
+    let status = response.status();
+    let content = response
+        .bytes()
+        .await
+        .context("Failed to read Discord webhook response")?;
+    if !status.is_success() {
+        bail!(
+            "Discord webhook returned {}: {}",
+            status,
+            String::from_utf8_lossy(&content)
+        );
+    }
+
+    serde_json::from_slice(&content).context("Failed to parse Discord webhook response")
+}
+
+/// A random boundary, which can't plausibly appear in the images.
+fn multipart_boundary() -> anyhow::Result<String> {
+    let mut bytes = [0; 16];
+    openssl::rand::rand_bytes(&mut bytes).context("Failed to generate multipart boundary")?;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    Ok(format!("every-frame-{}", hex))
+}
+
+/// Append a `multipart/form-data` part to `body`.
+fn push_part(
+    body: &mut Vec<u8>,
+    boundary: &str,
+    name: &str,
+    filename: Option<&str>,
+    content_type: &str,
+    data: &[u8],
+) {
+    let disposition = match filename {
+        Some(filename) => format!("form-data; name=\"{}\"; filename=\"{}\"", name, filename),
+        None => format!("form-data; name=\"{}\"", name),
+    };
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: {}\r\nContent-Type: {}\r\n\r\n",
+            boundary, disposition, content_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(b"\r\n");
+}
@@ -0,0 +1,101 @@
+//! Free disk space checks, for hosts where the disk fills up.
+//!
+//! With `MIN_FREE_DISK_MB` set, optional writes like archiving a posted frame
+//! are skipped while less than that much space is free, leaving room for the
+//! frame counter. The counter is still saved when space is low, atomically so
+//! a failed write leaves the old one intact, but a low disk is logged loudly
+//! first. Free space can only be measured on Unix, elsewhere nothing is
+//! skipped.
+
+use std::{
+    io,
+    path::Path,
+};
+
+use log::*;
+
+use crate::config::Config;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Whether there is enough free space for an optional write to `path`.
+///
+/// Logs a warning and returns `false` when there isn't. Always `true` without
+/// a minimum configured or when free space can't be measured.
+#[cfg(feature = "bluesky")]
+pub fn has_room(config: &Config, path: &Path, what: &str) -> bool {
+    match low_space(config, path) {
+        Some(available) => {
+            warn!(
+                "Only {} MB free for {}, skipping {}",
+                available / BYTES_PER_MB,
+                path.display(),
+                what
+            );
+            false
+        }
+        None => true,
+    }
+}
+
+/// Log an error when space for the critical state at `path` is running low.
+///
+/// Nothing is skipped, the write is attempted anyway.
+pub fn warn_if_low(config: &Config, path: &Path) {
+    if let Some(available) = low_space(config, path) {
+        error!(
+            "Disk space is low, only {} MB free for {}. Free some space before \
+             saving the frame counter fails",
+            available / BYTES_PER_MB,
+            path.display()
+        );
+    }
+}
+
+/// Free bytes for `path` when they are below the configured minimum.
+fn low_space(config: &Config, path: &Path) -> Option<u64> {
+    let minimum = config.min_free_disk_mb?.saturating_mul(BYTES_PER_MB);
+    match available_bytes(path) {
+        Ok(available) if available < minimum => Some(available),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Failed to check free space for {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`.
+///
+/// Paths that don't exist yet are measured at their nearest existing ancestor.
+#[cfg(unix)]
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    use std::{
+        ffi::CString,
+        os::unix::ffi::OsStrExt,
+    };
+
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let c_path = CString::new(existing.as_os_str().as_bytes())?;
+
+    // SAFETY: statvfs is plain data, and statvfs fills it in before use
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    #[allow(clippy::unnecessary_cast)] // The field types vary between platforms
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available_bytes(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free space can only be checked on Unix",
+    ))
+}
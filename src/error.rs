@@ -2,13 +2,51 @@
 
 use thiserror::Error;
 
+use crate::config::OutputFormat;
+
 /// Errors that can occur during frame processing.
 #[derive(Error, Debug)]
 pub enum FrameError {
-    #[error("Failed to compress frame {frame} to under {max_size}MB at minimum quality")]
-    CompressionFailed { frame: u32, max_size: f64 },
+    #[error("Failed to compress {image} to under {max_size}MB at minimum quality")]
+    CompressionFailed { image: String, max_size: f64 },
+    #[error("Failed to encode {image} to {format} at quality {quality}")]
+    EncodingFailed {
+        image: String,
+        format: OutputFormat,
+        quality: u8,
+        source: image::ImageError,
+    },
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("Image processing error: {0}")]
     Image(#[from] image::ImageError),
+    #[error("Frame {frame} does not match its checksum in the manifest")]
+    ChecksumMismatch { frame: u32 },
+    #[error("Too many pixels in {image}: {width}x{height} is over the limit of {max_pixels}")]
+    TooManyPixels {
+        image: String,
+        width: u32,
+        height: u32,
+        max_pixels: u64,
+    },
+    #[error("Processing {image} did not finish within {seconds} seconds")]
+    ProcessingTimedOut { image: String, seconds: u64 },
+    #[error("Frame {frame} is black, its mean luminance is {mean_luma:.1}")]
+    BlackFrame { frame: u32, mean_luma: f64 },
+}
+
+impl FrameError {
+    /// Whether an error means the frame can never be posted and should be skipped.
+    pub fn is_skippable(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<FrameError>(),
+            Some(
+                FrameError::ChecksumMismatch { .. }
+                    | FrameError::EncodingFailed { .. }
+                    | FrameError::TooManyPixels { .. }
+                    | FrameError::ProcessingTimedOut { .. }
+                    | FrameError::BlackFrame { .. }
+            )
+        )
+    }
 }
@@ -0,0 +1,120 @@
+//! Tracking scheduled posts that keep failing.
+//!
+//! Each scheduled post that fails after all its retries extends a streak of
+//! consecutive failures, kept on disk so restarting the bot doesn't hide the
+//! problem. Any successful post ends the streak.
+//!
+//! Every failure runs the `ON_POST_FAILURE` hook. Once the streak reaches
+//! `MAX_CONSECUTIVE_FAILURES`, failures are logged as critical and the hook
+//! gets `HOOK_SEVERITY=critical`. With `EXIT_ON_MAX_FAILURES` the bot then
+//! exits, so an orchestrator can restart it.
+
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+
+use anyhow::Context;
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    config::{
+        Config,
+        FAILURE_STREAK_FILE,
+        FRAME_DATA_FILE,
+    },
+    frame_info::FrameInfo,
+    hooks,
+};
+
+/// Consecutive failed posts saved between runs.
+#[derive(Deserialize, Serialize, Debug, Default)]
+struct FailureStreak {
+    /// Scheduled posts that failed in a row
+    consecutive_failures: u32,
+}
+
+/// Count a scheduled post that failed after all its retries.
+///
+/// Runs the failure hook, escalating once the streak reaches the configured
+/// maximum, and exits the process if configured to.
+pub fn record_failure(config: &Config, error: &anyhow::Error) {
+    let path = Path::new(FAILURE_STREAK_FILE);
+    let mut streak = load(path);
+    streak.consecutive_failures += 1;
+    if let Err(e) = save(path, &streak) {
+        warn!("{:#}", e);
+    }
+
+    let failures = streak.consecutive_failures;
+    let critical = config
+        .max_consecutive_failures
+        .is_some_and(|max| failures >= max);
+    if critical {
+        error!(
+            "CRITICAL: Scheduled posts have failed {} times in a row, the bot needs attention",
+            failures
+        );
+    } else {
+        warn!("Scheduled posts have failed {} times in a row", failures);
+    }
+
+    // The counter didn't advance, so it still points at the failed frame
+    let frame = FrameInfo::peek_current_frame(FRAME_DATA_FILE).ok();
+    hooks::post_failed(config, frame, error, critical);
+
+    if critical && config.exit_on_max_failures {
+        error!("Exiting after {} consecutive failures", failures);
+        std::process::exit(1);
+    }
+}
+
+/// End the failure streak after a successful post.
+pub fn record_success() {
+    let path = Path::new(FAILURE_STREAK_FILE);
+    let failures = load(path).consecutive_failures;
+    if failures == 0 {
+        return;
+    }
+
+    info!("Posting recovered after {} consecutive failures", failures);
+    if let Err(e) = fs::remove_file(path) {
+        warn!("Failed to remove failure streak {}: {}", path.display(), e);
+    }
+}
+
+/// Load the current streak, treating a missing or unreadable file as none.
+fn load(path: &Path) -> FailureStreak {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return FailureStreak::default(),
+        Err(e) => {
+            warn!("Failed to read failure streak {}: {}", path.display(), e);
+            return FailureStreak::default();
+        }
+    };
+
+    toml::from_str(&content).unwrap_or_else(|e| {
+        warn!("Failed to parse failure streak {}: {}", path.display(), e);
+        FailureStreak::default()
+    })
+}
+
+fn save(path: &Path, streak: &FailureStreak) -> anyhow::Result<()> {
+    let toml_string =
+        toml::to_string_pretty(streak).context("Failed to serialize failure streak")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create parent directories for {}", path.display())
+        })?;
+    }
+
+    fs::write(path, toml_string)
+        .with_context(|| format!("Failed to write failure streak to {}", path.display()))
+}
@@ -0,0 +1,66 @@
+//! A row of thumbnails of consecutive frames, for previewing what's coming up.
+//!
+//! The `filmstrip` command lays out the next few frames from the counter side
+//! by side, each labelled with its frame number, so curators can see at a
+//! glance whether a stretch is worth skipping ahead of.
+
+use image::{
+    imageops::{
+        self,
+        FilterType,
+    },
+    DynamicImage,
+    GenericImageView,
+    Rgb,
+    RgbImage,
+};
+
+use crate::overlay::TimecodeOverlay;
+
+/// Pixels between neighbouring thumbnails.
+const THUMBNAIL_GAP: u32 = 4;
+
+/// Thumbnails collected in order, rendered into a single image at the end.
+pub struct Filmstrip {
+    /// Height every thumbnail is scaled to
+    height: u32,
+    thumbnails: Vec<RgbImage>,
+}
+
+impl Filmstrip {
+    /// Start a filmstrip with thumbnails `height` pixels high.
+    pub fn new(height: u32) -> Self {
+        Self {
+            height,
+            thumbnails: Vec::new(),
+        }
+    }
+
+    /// Add a thumbnail of `image`, labelled as `frame`.
+    ///
+    /// The image is scaled down right away, so only thumbnails are kept.
+    pub fn push(&mut self, frame: u32, image: &DynamicImage) {
+        let (width, height) = image.dimensions();
+        let thumbnail_width =
+            ((width as u64 * self.height as u64 / height.max(1) as u64) as u32).max(1);
+        let mut thumbnail = image
+            .resize_exact(thumbnail_width, self.height, FilterType::Triangle)
+            .to_rgb8();
+        TimecodeOverlay::default().draw(&mut thumbnail, &frame.to_string());
+        self.thumbnails.push(thumbnail);
+    }
+
+    /// Lay the thumbnails out left to right on a black background.
+    pub fn render(&self) -> RgbImage {
+        let gaps = self.thumbnails.len().saturating_sub(1) as u32 * THUMBNAIL_GAP;
+        let width = self.thumbnails.iter().map(RgbImage::width).sum::<u32>() + gaps;
+        let mut strip = RgbImage::from_pixel(width.max(1), self.height, Rgb([0, 0, 0]));
+
+        let mut left = 0;
+        for thumbnail in &self.thumbnails {
+            imageops::replace(&mut strip, thumbnail, left as i64, 0);
+            left += thumbnail.width() + THUMBNAIL_GAP;
+        }
+        strip
+    }
+}
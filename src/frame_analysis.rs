@@ -0,0 +1,95 @@
+//! Luminance statistics of decoded frames, for tuning compression.
+//!
+//! With `ANALYZE_FRAMES` enabled, every frame decoded for recompression gets a
+//! one-line summary logged: its mean luminance, the Shannon entropy of its
+//! luminance histogram, and the histogram itself drawn as a row of bars. High
+//! entropy means the frame's pixels are spread over many brightness levels,
+//! like film grain or busy detail, which takes more bytes to compress and is
+//! usually why a frame ends up at a low quality.
+
+use std::fmt;
+
+use image::DynamicImage;
+
+/// Number of bars the histogram is drawn with.
+const HISTOGRAM_BARS: usize = 16;
+
+/// Characters for histogram bars, from empty to full.
+const BAR_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Luminance statistics of a single image.
+#[derive(Debug, Clone)]
+pub struct FrameAnalysis {
+    /// Number of pixels at each luminance level
+    histogram: [u64; 256],
+    /// Average luminance, from 0 to 255
+    pub mean_luma: f64,
+    /// Shannon entropy of the luminance histogram in bits per pixel, from 0
+    /// for a flat image up to 8
+    pub entropy: f64,
+}
+
+impl FrameAnalysis {
+    /// Compute the luminance statistics of `image`.
+    pub fn of(image: &DynamicImage) -> Self {
+        let luma = image.to_luma8();
+        let mut histogram = [0u64; 256];
+        for pixel in luma.pixels() {
+            histogram[pixel.0[0] as usize] += 1;
+        }
+
+        let total = luma.pixels().len() as f64;
+        let (mut mean_luma, mut entropy) = (0.0, 0.0);
+        if total > 0.0 {
+            for (level, &count) in histogram.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let share = count as f64 / total;
+                mean_luma += level as f64 * share;
+                entropy -= share * share.log2();
+            }
+        }
+
+        Self {
+            histogram,
+            mean_luma,
+            entropy,
+        }
+    }
+
+    /// The histogram as bars of equal-width luminance ranges, scaled so the
+    /// fullest one is a full block.
+    fn bars(&self) -> String {
+        let buckets: Vec<u64> = self
+            .histogram
+            .chunks(self.histogram.len() / HISTOGRAM_BARS)
+            .map(|chunk| chunk.iter().sum())
+            .collect();
+        let fullest = buckets.iter().copied().max().unwrap_or(0).max(1);
+
+        buckets
+            .iter()
+            .map(|&count| {
+                // Empty buckets are blank, so even a few pixels show as a bar
+                if count == 0 {
+                    return ' ';
+                }
+                let level = count * (BAR_LEVELS.len() as u64 - 1) / fullest;
+                BAR_LEVELS[level as usize]
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for FrameAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "entropy {:.2} bits/px, mean luma {:.0}, histogram [{}]",
+            self.entropy,
+            self.mean_luma,
+            self.bars()
+        )
+    }
+}
@@ -6,8 +6,14 @@
 
 use std::{
     fs,
-    io,
-    path::Path,
+    io::{
+        self,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use anyhow::{
@@ -20,6 +26,11 @@ use serde::{
     Serialize,
 };
 
+use crate::{
+    config::OutOfRangePolicy,
+    scenes::SceneList,
+};
+
 /// Tracks current posting progress through a movie's frames.
 ///
 /// Maintains the total number of frames available and which frame
@@ -32,6 +43,31 @@ pub struct FrameInfo {
     pub total_frames: u32,
     /// Next frame number to post (1-based indexing)
     pub current_frame: u32,
+    /// Position in the shuffled scene order, when posting by scene
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene_position: Option<ScenePosition>,
+    /// Frame posted most recently and how many times in a row, for
+    /// `MAX_FRAME_REPEATS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_posted: Option<LastPosted>,
+}
+
+/// A frame that was posted, along with how many times in a row.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastPosted {
+    pub frame: u32,
+    pub times: u32,
+}
+
+/// Progress through a cycle of scenes posted in shuffled order.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScenePosition {
+    /// Indexes of the scenes in the order they're posted this cycle
+    pub order: Vec<usize>,
+    /// Index into `order` of the scene being posted
+    pub scene: usize,
+    /// Frames since the start of the current scene
+    pub offset: u32,
 }
 
 impl FrameInfo {
@@ -45,6 +81,8 @@ impl FrameInfo {
             return Ok(Self {
                 total_frames: 0,
                 current_frame: 0,
+                scene_position: None,
+                last_posted: None,
             });
         }
 
@@ -65,25 +103,83 @@ impl FrameInfo {
         Ok(Self {
             total_frames,
             current_frame,
+            scene_position: None,
+            last_posted: None,
         })
     }
 
+    /// Whether the current frame was already posted `max_repeats` times in a
+    /// row, when a limit is set.
+    pub fn repeat_limit_reached(&self, max_repeats: Option<u32>) -> bool {
+        match (max_repeats, self.last_posted) {
+            (Some(max_repeats), Some(last_posted)) => {
+                last_posted.frame == self.current_frame && last_posted.times >= max_repeats
+            }
+            _ => false,
+        }
+    }
+
+    /// Note that the current frame was posted.
+    ///
+    /// Only kept in memory, it's saved along with the next `increment`.
+    pub fn record_posted(&mut self) {
+        self.last_posted = match self.last_posted {
+            Some(last_posted) if last_posted.frame == self.current_frame => Some(LastPosted {
+                times: last_posted.times.saturating_add(1),
+                ..last_posted
+            }),
+            _ => Some(LastPosted {
+                frame: self.current_frame,
+                times: 1,
+            }),
+        };
+    }
+
     /// Advance to the next frame and save progress to disk.
     ///
-    /// Increments current_frame by 1, wrapping back to 1 when reaching the end.
-    /// This creates an infinite loop through all frames. Automatically saves
-    /// the updated state to the specified file after incrementing.
-    pub fn increment<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+    /// Increments current_frame by `step`, wrapping back to 1 when the next
+    /// frame would pass the end. Restarting at 1 rather than carrying the
+    /// overshoot means every cycle samples the same frames. This creates an
+    /// infinite loop through all frames. Automatically saves the updated
+    /// state to the specified file after incrementing.
+    ///
+    /// With `scenes`, frames advance within the current scene instead, moving
+    /// on to the next scene in the shuffled order when it ends, and a cycle
+    /// ends after the last scene.
+    ///
+    /// Returns whether it wrapped, meaning a new cycle starts with the next post.
+    pub fn increment<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        step: u32,
+        scenes: Option<&SceneList>,
+    ) -> Result<bool> {
+        let wrapped = self.advance(step, scenes)?;
+        self.save_to_file(path)
+            .context("Failed to save frame info after incrementing")?;
+
+        Ok(wrapped)
+    }
+
+    /// Move to the next frame like `increment`, without saving.
+    pub fn advance(&mut self, step: u32, scenes: Option<&SceneList>) -> Result<bool> {
         if self.total_frames == 0 {
             warn!("Cannot increment frame when total_frames is 0");
-            return Ok(());
+            return Ok(false);
         }
 
         let old_frame = self.current_frame;
-        self.current_frame = if self.current_frame >= self.total_frames {
-            1 // Wrap back to first frame
-        } else {
-            self.current_frame + 1
+        let wrapped = match scenes {
+            Some(scenes) => self.advance_scene(step, scenes)?,
+            None => {
+                let (next_frame, wrapped) = match self.current_frame.checked_add(step) {
+                    Some(next) if next <= self.total_frames => (next, false),
+                    _ => (1, true), // Wrap back to first frame
+                };
+                self.current_frame = next_frame;
+                self.scene_position = None;
+                wrapped
+            }
         };
 
         debug!(
@@ -91,16 +187,93 @@ impl FrameInfo {
             old_frame, self.current_frame
         );
 
-        self.save_to_file(path)
-            .context("Failed to save frame info after incrementing")?;
+        Ok(wrapped)
+    }
 
-        Ok(())
+    /// Advance through the shuffled scene order, returning whether it wrapped.
+    ///
+    /// A missing position, or one left over from a different scenes file,
+    /// starts a fresh shuffled order without counting as a wrap.
+    fn advance_scene(&mut self, step: u32, scenes: &SceneList) -> Result<bool> {
+        let position = self.scene_position.take().filter(|position| {
+            position.order.len() == scenes.count() && position.scene < position.order.len()
+        });
+
+        let (position, wrapped) = match position {
+            None => {
+                info!("Starting a new shuffled order of {} scenes", scenes.count());
+                (Self::shuffled_position(scenes)?, false)
+            }
+            Some(mut position) => {
+                let scene = scenes
+                    .get(position.order[position.scene])
+                    .context("Scene order refers to a missing scene")?;
+                position.offset = position.offset.saturating_add(step);
+
+                if scene.start.saturating_add(position.offset) <= scene.end {
+                    (position, false)
+                } else if position.scene + 1 < position.order.len() {
+                    position.scene += 1;
+                    position.offset = 0;
+                    (position, false)
+                } else {
+                    info!("Finished all scenes, shuffling them again");
+                    (Self::shuffled_position(scenes)?, true)
+                }
+            }
+        };
+
+        let scene = scenes
+            .get(position.order[position.scene])
+            .context("Scene order refers to a missing scene")?;
+        self.current_frame = scene.start + position.offset;
+        self.scene_position = Some(position);
+
+        Ok(wrapped)
+    }
+
+    /// The start of a freshly shuffled scene order.
+    fn shuffled_position(scenes: &SceneList) -> Result<ScenePosition> {
+        Ok(ScenePosition {
+            order: scenes.shuffled_order()?,
+            scene: 0,
+            offset: 0,
+        })
+    }
+
+    /// Number of posts still to come after the current frame before the cycle
+    /// ends.
+    ///
+    /// Counts every `step`th frame up to the last, or with `scenes` the rest of
+    /// the current scene and every scene after it in the shuffled order.
+    /// Returns `None` when that isn't known yet, before a shuffled order has
+    /// been started.
+    #[cfg(feature = "bluesky")]
+    pub fn posts_left(&self, step: u32, scenes: Option<&SceneList>) -> Option<u32> {
+        let step = step.max(1);
+        let Some(scenes) = scenes else {
+            return Some(self.total_frames.checked_sub(self.current_frame)? / step);
+        };
+
+        let position = self.scene_position.as_ref().filter(|position| {
+            position.order.len() == scenes.count() && position.scene < position.order.len()
+        })?;
+        let scene = scenes.get(position.order[position.scene])?;
+        let mut posts_left = scene.end.saturating_sub(self.current_frame) / step;
+        for &index in &position.order[position.scene + 1..] {
+            let scene = scenes.get(index)?;
+            posts_left = posts_left.saturating_add((scene.end - scene.start) / step + 1);
+        }
+        Some(posts_left)
     }
 
     /// Save the current state to a TOML file.
     ///
     /// Creates parent directories if they don't exist. The file is written
     /// in pretty-printed TOML format for easy manual editing if needed.
+    /// Writes go to a temporary file that's renamed over the original, so a
+    /// crash mid-write leaves the previous state intact rather than a
+    /// truncated file.
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let path = path.as_ref();
 
@@ -109,12 +282,10 @@ impl FrameInfo {
 
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).with_context(|| {
-                format!("Failed to create parent directories for {}", path.display())
-            })?;
+            create_parent_dir(parent, path)?;
         }
 
-        fs::write(path, toml_string)
+        write_atomically(path, toml_string.as_bytes())
             .with_context(|| format!("Failed to write frame info to {}", path.display()))?;
 
         debug!("Saved frame info to {}", path.display());
@@ -127,23 +298,50 @@ impl FrameInfo {
     /// it loads the saved progress. If not, it creates a new file with the
     /// provided defaults. This allows the bot to resume where it left off
     /// after restarts while handling first-time setup gracefully.
+    ///
+    /// When `recover_corrupt` is set, a file that fails to parse or validate
+    /// is moved aside to `<path>.bak` and replaced with defaults instead of
+    /// returning an error.
+    ///
+    /// A saved frame past the end of the movie, or past `default_total_frames`
+    /// when frames were removed since, is handled according to `on_out_of_range`.
+    /// Clamping or resetting it updates the file so the warning is only logged once.
     pub fn load_or_create<P: AsRef<Path>>(
         path: P,
         default_total_frames: u32,
         default_current_frame: u32,
+        recover_corrupt: bool,
+        on_out_of_range: OutOfRangePolicy,
     ) -> Result<Self> {
         let path = path.as_ref();
 
         match fs::read_to_string(path) {
             Ok(content) => {
                 debug!("Loading existing frame info from {}", path.display());
-                let frame_info: FrameInfo = toml::from_str(&content)
-                    .with_context(|| format!("Failed to parse TOML from {}", path.display()))?;
-
-                // Validate loaded data
-                frame_info.validate().with_context(|| {
-                    format!("Invalid frame info loaded from {}", path.display())
-                })?;
+                let loaded = Self::parse(&content, path).and_then(|frame_info| {
+                    frame_info.fit_to_range(default_total_frames, on_out_of_range, path)
+                });
+                let frame_info = match loaded {
+                    Ok(frame_info) => frame_info,
+                    Err(e) if recover_corrupt => {
+                        warn!("{:#}", e);
+                        let backup_path = Self::backup_corrupt_file(path)?;
+                        warn!(
+                            "Moved corrupt frame info to {}, recreating with defaults",
+                            backup_path.display()
+                        );
+                        return Self::create_default(
+                            path,
+                            default_total_frames,
+                            default_current_frame,
+                        );
+                    }
+                    Err(e) => {
+                        return Err(e.context(
+                            "Set RECOVER_CORRUPT_STATE=true to back up the file and start over",
+                        ));
+                    }
+                };
 
                 info!(
                     "Loaded frame info: frame {}/{} from {}",
@@ -158,26 +356,134 @@ impl FrameInfo {
                     "Frame info file {} not found, creating with defaults",
                     path.display()
                 );
-                let frame_info = Self::new(default_total_frames, default_current_frame)
-                    .context("Failed to create default FrameInfo")?;
+                Self::create_default(path, default_total_frames, default_current_frame)
+            }
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read frame info from {}", path.display()))
+            }
+        }
+    }
 
-                frame_info
-                    .save_to_file(path)
-                    .context("Failed to save initial frame info")?;
+    /// Read the next frame to post without creating or modifying the file.
+    ///
+    /// Unlike `load_or_create`, this never writes to disk, so it's safe to call
+    /// alongside the posting task. Returns frame 1 if there's no file yet.
+    pub fn peek_current_frame<P: AsRef<Path>>(path: P) -> Result<u32> {
+        Ok(Self::peek(path)?.map_or(1, |frame_info| frame_info.current_frame))
+    }
 
-                info!(
-                    "Created new frame info: frame {}/{}",
-                    frame_info.current_frame, frame_info.total_frames
-                );
-                Ok(frame_info)
-            }
+    /// Read the saved frame info without creating or modifying the file, or
+    /// `None` if there's no file yet.
+    pub fn peek<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content, path).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
             Err(e) => {
                 Err(e).with_context(|| format!("Failed to read frame info from {}", path.display()))
             }
         }
     }
 
+    /// Bring a loaded frame back within range when it's past the last frame.
+    ///
+    /// The last frame is the lower of the saved total and `available_frames`,
+    /// unless no frames are available at all. Anything changed is saved back
+    /// to `path`.
+    fn fit_to_range(
+        mut self,
+        available_frames: u32,
+        policy: OutOfRangePolicy,
+        path: &Path,
+    ) -> Result<Self> {
+        let last_frame = match available_frames {
+            0 => self.total_frames,
+            available => self.total_frames.min(available),
+        };
+        if self.current_frame <= last_frame {
+            return Ok(self);
+        }
+
+        let next_frame = match policy {
+            OutOfRangePolicy::Error => {
+                return Err(anyhow::anyhow!(
+                    "Current frame {} in {} exceeds total frames {}, set ON_OUT_OF_RANGE to clamp or reset to continue anyway",
+                    self.current_frame,
+                    path.display(),
+                    last_frame
+                ));
+            }
+            OutOfRangePolicy::Clamp => last_frame,
+            OutOfRangePolicy::Reset => 1,
+        };
+
+        warn!(
+            "Current frame {} is past the last frame {}, continuing from frame {}",
+            self.current_frame, last_frame, next_frame
+        );
+        self.current_frame = next_frame;
+        self.total_frames = last_frame;
+        self.save_to_file(path)
+            .context("Failed to save frame info after bringing it back in range")?;
+
+        Ok(self)
+    }
+
+    /// Parse and validate frame info read from `path`.
+    fn parse(content: &str, path: &Path) -> Result<Self> {
+        let frame_info: FrameInfo = toml::from_str(content).with_context(|| {
+            format!(
+                "Frame info in {} is not valid TOML (the file may be truncated or hand-edited)",
+                path.display()
+            )
+        })?;
+
+        // Validate loaded data
+        frame_info
+            .validate()
+            .with_context(|| format!("Invalid frame info loaded from {}", path.display()))?;
+
+        Ok(frame_info)
+    }
+
+    /// Create frame info with the given defaults and save it to `path`.
+    fn create_default(path: &Path, total_frames: u32, current_frame: u32) -> Result<Self> {
+        let frame_info =
+            Self::new(total_frames, current_frame).context("Failed to create default FrameInfo")?;
+
+        frame_info
+            .save_to_file(path)
+            .context("Failed to save initial frame info")?;
+
+        info!(
+            "Created new frame info: frame {}/{}",
+            frame_info.current_frame, frame_info.total_frames
+        );
+        Ok(frame_info)
+    }
+
+    /// Move a corrupt frame info file to `<path>.bak`, returning the backup path.
+    fn backup_corrupt_file(path: &Path) -> Result<PathBuf> {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(".bak");
+        let backup_path = PathBuf::from(backup_path);
+
+        fs::rename(path, &backup_path).with_context(|| {
+            format!(
+                "Failed to back up corrupt frame info from {} to {}",
+                path.display(),
+                backup_path.display()
+            )
+        })?;
+
+        Ok(backup_path)
+    }
+
     /// Validate the current state.
+    ///
+    /// Whether the current frame is past the end is left to `fit_to_range`,
+    /// since that can be recovered from.
     fn validate(&self) -> Result<()> {
         if self.total_frames == 0 {
             if self.current_frame != 0 {
@@ -194,14 +500,378 @@ impl FrameInfo {
             ));
         }
 
-        if self.current_frame > self.total_frames {
-            return Err(anyhow::anyhow!(
-                "Current frame {} exceeds total frames {}",
-                self.current_frame,
-                self.total_frames
-            ));
+        Ok(())
+    }
+}
+
+/// Exclusive advisory lock on the frame counter, released when dropped.
+///
+/// The counter file itself is replaced on every save, so the lock is taken on
+/// a separate file next to it that's never removed.
+pub struct CounterLock {
+    _file: fs::File,
+}
+
+impl CounterLock {
+    /// Take the lock at `path` if no other process holds it.
+    ///
+    /// Returns `None` when another process already does, instead of waiting
+    /// for it.
+    pub fn try_acquire<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            create_parent_dir(parent, path)?;
         }
 
-        Ok(())
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Create the parent directory of `path`, tolerating races with other writers.
+///
+/// Another instance sharing the directory may create it at the same time, so
+/// `AlreadyExists` is fine as long as a directory (or symlink to one) ends up
+/// there. Permission errors name the directory to make them easy to fix.
+fn create_parent_dir(parent: &Path, path: &Path) -> Result<()> {
+    match fs::create_dir_all(parent) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && parent.is_dir() => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(e).with_context(|| {
+            format!(
+                "Permission denied creating {} for {}, check the directory's owner and mode",
+                parent.display(),
+                path.display()
+            )
+        }),
+        Err(e) => Err(e)
+            .with_context(|| format!("Failed to create parent directories for {}", path.display())),
+    }
+}
+
+/// Write `contents` to `path` via a temporary file in the same directory.
+///
+/// The temporary file is synced to disk before being renamed over `path`, so
+/// readers only ever see the old or the new contents. Its name includes the
+/// process ID so instances sharing the directory don't clobber each other.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = PathBuf::from(temp_path);
+
+    let result = fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        // Don't leave stray temporary files behind, the original error matters more
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch_dir::ScratchDir;
+
+    fn load(path: &Path, total_frames: u32, policy: OutOfRangePolicy) -> Result<FrameInfo> {
+        FrameInfo::load_or_create(path, total_frames, 1, false, policy)
+    }
+
+    #[test]
+    fn new_accepts_frames_within_bounds() {
+        let frame_info = FrameInfo::new(100, 1).unwrap();
+        assert_eq!(frame_info.current_frame, 1);
+        assert_eq!(FrameInfo::new(100, 100).unwrap().current_frame, 100);
+    }
+
+    #[test]
+    fn new_rejects_frames_out_of_bounds() {
+        assert!(FrameInfo::new(100, 0).is_err());
+        assert!(FrameInfo::new(100, 101).is_err());
+    }
+
+    #[test]
+    fn new_with_no_frames_starts_at_zero() {
+        let frame_info = FrameInfo::new(0, 5).unwrap();
+        assert_eq!(frame_info.total_frames, 0);
+        assert_eq!(frame_info.current_frame, 0);
+    }
+
+    #[test]
+    fn advance_wraps_from_last_frame_to_first() {
+        let mut frame_info = FrameInfo::new(3, 2).unwrap();
+        assert!(!frame_info.advance(1, None).unwrap());
+        assert_eq!(frame_info.current_frame, 3);
+        assert!(frame_info.advance(1, None).unwrap());
+        assert_eq!(frame_info.current_frame, 1);
+    }
+
+    #[test]
+    fn advance_by_step_wraps_to_first_frame_past_the_end() {
+        let mut frame_info = FrameInfo::new(10, 6).unwrap();
+        assert!(!frame_info.advance(3, None).unwrap());
+        assert_eq!(frame_info.current_frame, 9);
+        assert!(frame_info.advance(3, None).unwrap());
+        assert_eq!(frame_info.current_frame, 1);
+    }
+
+    #[test]
+    fn advance_with_no_frames_stays_put() {
+        let mut frame_info = FrameInfo::new(0, 0).unwrap();
+        assert!(!frame_info.advance(1, None).unwrap());
+        assert_eq!(frame_info.current_frame, 0);
+    }
+
+    #[test]
+    fn increment_saves_the_next_frame() {
+        let dir = ScratchDir::new("increment");
+        let path = dir.file("frame_data.toml");
+        let mut frame_info = FrameInfo::new(3, 3).unwrap();
+
+        assert!(frame_info.increment(&path, 1, None).unwrap());
+        assert_eq!(FrameInfo::peek_current_frame(&path).unwrap(), 1);
+    }
+
+    #[test]
+    fn increment_by_step_larger_than_total_wraps_every_time() {
+        let dir = ScratchDir::new("step-larger-than-total");
+        let path = dir.file("frame_data.toml");
+        let mut frame_info = FrameInfo::new(5, 1).unwrap();
+
+        assert!(frame_info.increment(&path, 7, None).unwrap());
+        assert_eq!(frame_info.current_frame, 1);
+        assert!(frame_info.increment(&path, 7, None).unwrap());
+        assert_eq!(FrameInfo::peek_current_frame(&path).unwrap(), 1);
+    }
+
+    #[test]
+    fn increment_by_step_not_dividing_total_wraps_to_first_frame() {
+        let dir = ScratchDir::new("step-not-dividing-total");
+        let path = dir.file("frame_data.toml");
+        let mut frame_info = FrameInfo::new(10, 1).unwrap();
+
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            let wrapped = frame_info.increment(&path, 4, None).unwrap();
+            frames.push((frame_info.current_frame, wrapped));
+        }
+        // 13 is past the end, so it goes back to 1 rather than carrying over
+        assert_eq!(frames, [(5, false), (9, false), (1, true), (5, false)]);
+        assert_eq!(FrameInfo::peek_current_frame(&path).unwrap(), 5);
+    }
+
+    #[test]
+    fn increment_by_step_lands_exactly_on_last_frame() {
+        let dir = ScratchDir::new("step-lands-on-total");
+        let path = dir.file("frame_data.toml");
+        let mut frame_info = FrameInfo::new(10, 4).unwrap();
+
+        assert!(!frame_info.increment(&path, 3, None).unwrap());
+        assert!(!frame_info.increment(&path, 3, None).unwrap());
+        assert_eq!(frame_info.current_frame, 10);
+        assert!(frame_info.increment(&path, 3, None).unwrap());
+        assert_eq!(frame_info.current_frame, 1);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = ScratchDir::new("round-trip");
+        let path = dir.file("nested/frame_data.toml");
+        let mut frame_info = FrameInfo::new(50, 7).unwrap();
+        frame_info.record_posted();
+        frame_info.save_to_file(&path).unwrap();
+
+        let loaded = load(&path, 50, OutOfRangePolicy::Error).unwrap();
+        assert_eq!(loaded.total_frames, 50);
+        assert_eq!(loaded.current_frame, 7);
+        assert_eq!(loaded.last_posted, Some(LastPosted { frame: 7, times: 1 }));
+        // The temporary file is renamed over the original, not left behind
+        let leftovers: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, ["frame_data.toml"]);
+    }
+
+    #[test]
+    fn missing_file_is_created_with_defaults() {
+        let dir = ScratchDir::new("missing");
+        let path = dir.file("frame_data.toml");
+
+        let frame_info =
+            FrameInfo::load_or_create(&path, 20, 4, false, OutOfRangePolicy::Error).unwrap();
+        assert_eq!(frame_info.total_frames, 20);
+        assert_eq!(frame_info.current_frame, 4);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn invalid_toml_is_an_error() {
+        let dir = ScratchDir::new("invalid");
+        let path = dir.file("frame_data.toml");
+        fs::write(&path, "total_frames = [not toml").unwrap();
+
+        assert!(load(&path, 20, OutOfRangePolicy::Error).is_err());
+        // Left as it was for the user to look at
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "total_frames = [not toml"
+        );
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up_when_recovering() {
+        let dir = ScratchDir::new("recover");
+        let path = dir.file("frame_data.toml");
+        fs::write(&path, "total_frames = [not toml").unwrap();
+
+        let frame_info =
+            FrameInfo::load_or_create(&path, 20, 1, true, OutOfRangePolicy::Error).unwrap();
+        assert_eq!(frame_info.current_frame, 1);
+        assert_eq!(
+            fs::read_to_string(dir.file("frame_data.toml.bak")).unwrap(),
+            "total_frames = [not toml"
+        );
+        assert_eq!(FrameInfo::peek_current_frame(&path).unwrap(), 1);
+    }
+
+    #[test]
+    fn out_of_range_frame_is_an_error_by_default() {
+        let dir = ScratchDir::new("out-of-range-error");
+        let path = dir.file("frame_data.toml");
+        FrameInfo::new(100, 90)
+            .unwrap()
+            .save_to_file(&path)
+            .unwrap();
+
+        assert!(load(&path, 50, OutOfRangePolicy::Error).is_err());
+    }
+
+    #[test]
+    fn out_of_range_frame_is_clamped_to_the_last_frame() {
+        let dir = ScratchDir::new("out-of-range-clamp");
+        let path = dir.file("frame_data.toml");
+        FrameInfo::new(100, 90)
+            .unwrap()
+            .save_to_file(&path)
+            .unwrap();
+
+        let frame_info = load(&path, 50, OutOfRangePolicy::Clamp).unwrap();
+        assert_eq!(frame_info.current_frame, 50);
+        assert_eq!(frame_info.total_frames, 50);
+        assert_eq!(FrameInfo::peek_current_frame(&path).unwrap(), 50);
+    }
+
+    #[test]
+    fn out_of_range_frame_is_reset_to_the_first_frame() {
+        let dir = ScratchDir::new("out-of-range-reset");
+        let path = dir.file("frame_data.toml");
+        FrameInfo::new(100, 90)
+            .unwrap()
+            .save_to_file(&path)
+            .unwrap();
+
+        let frame_info = load(&path, 50, OutOfRangePolicy::Reset).unwrap();
+        assert_eq!(frame_info.current_frame, 1);
+        assert_eq!(frame_info.total_frames, 50);
+    }
+
+    #[test]
+    fn frame_within_range_is_left_alone() {
+        let dir = ScratchDir::new("in-range");
+        let path = dir.file("frame_data.toml");
+        FrameInfo::new(100, 40)
+            .unwrap()
+            .save_to_file(&path)
+            .unwrap();
+
+        let frame_info = load(&path, 50, OutOfRangePolicy::Error).unwrap();
+        assert_eq!(frame_info.current_frame, 40);
+        assert_eq!(frame_info.total_frames, 100);
+    }
+
+    #[test]
+    fn repeat_limit_counts_posts_of_the_same_frame() {
+        let mut frame_info = FrameInfo::new(10, 3).unwrap();
+        assert!(!frame_info.repeat_limit_reached(Some(2)));
+
+        frame_info.record_posted();
+        assert!(!frame_info.repeat_limit_reached(Some(2)));
+        frame_info.record_posted();
+        assert!(frame_info.repeat_limit_reached(Some(2)));
+        assert!(!frame_info.repeat_limit_reached(None));
+    }
+
+    #[test]
+    fn repeat_count_starts_over_for_another_frame() {
+        let mut frame_info = FrameInfo::new(10, 3).unwrap();
+        frame_info.record_posted();
+        frame_info.record_posted();
+        frame_info.advance(1, None).unwrap();
+
+        assert!(!frame_info.repeat_limit_reached(Some(2)));
+        frame_info.record_posted();
+        assert_eq!(
+            frame_info.last_posted,
+            Some(LastPosted { frame: 4, times: 1 })
+        );
+    }
+
+    #[cfg(feature = "bluesky")]
+    #[test]
+    fn posts_left_counts_every_step_to_the_last_frame() {
+        let frame_info = FrameInfo::new(100, 90).unwrap();
+        assert_eq!(frame_info.posts_left(1, None), Some(10));
+        // 93, 96 and 99 are still to come, 102 is past the end
+        assert_eq!(frame_info.posts_left(3, None), Some(3));
+        assert_eq!(frame_info.posts_left(20, None), Some(0));
+        assert_eq!(
+            FrameInfo::new(100, 100).unwrap().posts_left(1, None),
+            Some(0)
+        );
+    }
+
+    #[cfg(feature = "bluesky")]
+    #[test]
+    fn posts_left_follows_the_shuffled_scene_order() {
+        let dir = ScratchDir::new("posts-left-scenes");
+        dir.write(
+            "scenes.toml",
+            b"[[scene]]\nstart = 1\nend = 10\n\n\
+              [[scene]]\nstart = 20\nend = 24\n\n\
+              [[scene]]\nstart = 50\nend = 59\n",
+        );
+        let scenes = SceneList::load(&dir.file("scenes.toml")).unwrap();
+
+        // Partway through the last scene of the movie, with two scenes to go
+        let mut frame_info = FrameInfo::new(100, 54).unwrap();
+        frame_info.scene_position = Some(ScenePosition {
+            order: vec![2, 0, 1],
+            scene: 0,
+            offset: 4,
+        });
+        assert_eq!(frame_info.posts_left(1, Some(&scenes)), Some(5 + 10 + 5));
+        assert_eq!(frame_info.posts_left(2, Some(&scenes)), Some(2 + 5 + 3));
+
+        // No order has been shuffled yet, so what's left isn't known
+        frame_info.scene_position = None;
+        assert_eq!(frame_info.posts_left(1, Some(&scenes)), None);
     }
 }
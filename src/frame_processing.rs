@@ -2,7 +2,15 @@
 
 use std::{
     io::Cursor,
+    path::{
+        Path,
+        PathBuf,
+    },
     sync::OnceLock,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 use anyhow::{
@@ -10,26 +18,69 @@ use anyhow::{
     Context,
 };
 use image::{
+    imageops::FilterType,
     DynamicImage,
     GenericImageView,
+    ImageReader,
+    Limits,
+    RgbImage,
 };
 use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
+#[cfg(feature = "mozjpeg")]
+use crate::mozjpeg;
+#[cfg(feature = "remote-frames")]
+use crate::remote_frames;
 use crate::{
+    checksum,
     config::{
+        Config,
+        FrameNameFormat,
+        FrameSource,
+        JpegEncoder,
+        OutputFormat,
         FRAMES_DIR,
         JPEG_QUALITY_STEP,
-        MAX_JPEG_SIZE,
         MIN_JPEG_QUALITY,
+        PLATFORM_BLOB_LIMIT,
     },
+    crop::FrameCrop,
     error::FrameError,
+    frame_analysis::FrameAnalysis,
+    jpeg_segments,
+    precompressed,
+    prewarm,
+    ssim,
+    stage_timings::StageTimings,
+    tiles::TileLayout,
+    timecode,
 };
 
 /// Cached total frame count to avoid repeated directory scans.
 static FRAME_COUNT: OnceLock<u32> = OnceLock::new();
 
+/// Times a frame is downscaled and re-encoded after the encoder fails before
+/// giving up on it.
+const MAX_ENCODING_FALLBACKS: u32 = 3;
+
+/// Quality AVIF encoding starts from. Higher qualities rarely look any better
+/// but make files much larger.
+#[cfg(feature = "avif")]
+const AVIF_MAX_QUALITY: u8 = 90;
+
+/// AVIF encoder speed from 1 (slowest, smallest) to 10 (fastest).
+#[cfg(feature = "avif")]
+const AVIF_SPEED: u8 = 7;
+
+/// Frame files named by timecode, sorted into posting order.
+static TIMECODE_FRAMES: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
 /// Image dimensions in pixels.
-#[derive(Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct FrameDimensions {
     pub width: u32,
     pub height: u32,
@@ -41,10 +92,15 @@ pub struct ProcessedFrame {
     pub jpeg_data: Vec<u8>,
     pub dimensions: FrameDimensions,
     pub quality_used: Option<u8>, // None if original was used
+    /// Where this image sits in the frame when split into tiles
+    #[cfg(feature = "bluesky")]
+    pub tile_position: Option<String>,
+    /// Average color as a hex code like `#1a2b3c`, when a template uses it
+    pub average_color: Option<String>,
 }
 
-/// Get total frame count, using cached value if available.
-pub async fn get_total_frame_count() -> anyhow::Result<u32> {
+/// Count the frames in the frames directory, using cached value if available.
+async fn get_total_frame_count() -> anyhow::Result<u32> {
     if let Some(&count) = FRAME_COUNT.get() {
         return Ok(count);
     }
@@ -58,84 +114,655 @@ pub async fn get_total_frame_count() -> anyhow::Result<u32> {
     Ok(count)
 }
 
+/// Count the frames in the configured source and cache the count.
+///
+/// Frames loaded over HTTP are counted from their manifest, otherwise the
+/// frames directory is scanned. Either way the count is cached from then on,
+/// so everything that needs the total goes through here.
+pub async fn load_total_frame_count(config: &Config) -> anyhow::Result<u32> {
+    match &config.frame_source {
+        FrameSource::Local => get_total_frame_count().await,
+        #[cfg(feature = "remote-frames")]
+        FrameSource::Http(base_url) => {
+            if let Some(&count) = FRAME_COUNT.get() {
+                return Ok(count);
+            }
+
+            let count = remote_frames::count_frames(base_url).await?;
+            FRAME_COUNT
+                .set(count)
+                .map_err(|_| anyhow::anyhow!("Failed to cache frame count"))?;
+
+            debug!("Total frames listed in {}: {}", base_url, count);
+            Ok(count)
+        }
+    }
+}
+
 /// Count JPEG files in the frames directory.
 async fn count_frame_files() -> anyhow::Result<u32> {
-    let mut entries = tokio::fs::read_dir(FRAMES_DIR)
+    let paths = scan_frame_files().await?;
+    u32::try_from(paths.len()).context("Too many frame files to count")
+}
+
+/// List the paths of all JPEG files in the frames directory.
+///
+/// Anything else, like `.DS_Store` or a partial `.jpg.part` download, is
+/// skipped and logged at debug level. Empty `.jpg` files are skipped too,
+/// since they're usually still being written and would fail to decode.
+pub async fn scan_frame_files() -> anyhow::Result<Vec<PathBuf>> {
+    scan_frame_files_in(Path::new(FRAMES_DIR)).await
+}
+
+/// List the paths of all JPEG files in `dir`, see `scan_frame_files`.
+async fn scan_frame_files_in(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = tokio::fs::read_dir(dir)
         .await
-        .with_context(|| format!("Failed to read frames directory: {}", FRAMES_DIR))?;
-    let mut count = 0;
+        .with_context(|| format!("Failed to read frames directory: {}", dir.display()))?;
+    let mut paths = Vec::new();
 
     while let Some(entry) = entries.next_entry().await? {
         let path = entry.path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jpg") {
-            count += 1;
+        let is_jpg = path.extension().and_then(|s| s.to_str()) == Some("jpg");
+
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() && is_jpg => {
+                if metadata.len() == 0 {
+                    debug!(
+                        "Skipping empty frame file {}, it may still be copying",
+                        path.display()
+                    );
+                } else {
+                    paths.push(path);
+                }
+            }
+            _ => debug!("Skipping non-frame file {}", path.display()),
         }
     }
 
-    Ok(count)
+    Ok(paths)
 }
 
-/// Load JPEG frame and recompress only if needed for size optimization.
+/// List the frame files named by timecode, sorted into posting order.
 ///
-/// Takes a frame number, loads the corresponding JPEG file. If the file is already
-/// within the size limit, returns it directly. Otherwise, recompresses with quality
-/// optimization to meet the size requirements.
-pub async fn get_frame_as_jpeg(current_frame: u32) -> anyhow::Result<ProcessedFrame> {
-    validate_frame_number(current_frame)?;
+/// Fails if any frame file isn't named by timecode, since it would have no
+/// place in the sequence.
+pub async fn timecode_ordered_frames() -> anyhow::Result<Vec<PathBuf>> {
+    let mut frames = Vec::new();
+    for path in scan_frame_files().await? {
+        let timecode = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(timecode::parse_file_timecode)
+            .with_context(|| {
+                format!(
+                    "Frame file {} isn't named by timecode like 00_01_23_456.jpg",
+                    path.display()
+                )
+            })?;
+        frames.push((timecode, path));
+    }
 
-    let frame_path = format!("{}/{}.jpg", FRAMES_DIR, current_frame);
-    ensure_frame_exists(&frame_path).await?;
+    frames.sort();
+    Ok(frames.into_iter().map(|(_, path)| path).collect())
+}
 
-    let jpeg_data = tokio::fs::read(&frame_path)
-        .await
-        .with_context(|| format!("Failed to read frame file: {}", frame_path))?;
+/// Parse the frame number from a frame file name like `123.jpg`.
+pub fn frame_number(path: &Path) -> Option<u32> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
 
-    let original_size = jpeg_data.len();
-    debug!(
-        "Frame {} original size: {} bytes",
-        current_frame, original_size
-    );
+/// Process a frame into the images to post.
+///
+/// This is the whole frame, or its tiles when a tile layout is configured.
+/// Images already processed ahead of time with `PREWARM_NEXT_FRAME` are used
+/// when there are some. With `EMBED_METADATA`, each image gets a comment
+/// naming the movie and frame. Time spent in each stage is added to `timings`.
+pub async fn get_frame_images(
+    current_frame: u32,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<Vec<ProcessedFrame>> {
+    let mut images = match prewarm::take(current_frame).await {
+        Some(images) => {
+            debug!("Using frame {} processed ahead of time", current_frame);
+            images
+        }
+        None => process_frame_images(current_frame, config, timings).await?,
+    };
 
-    // If already within size limit, return original data directly
-    if original_size <= MAX_JPEG_SIZE {
-        debug!(
-            "Frame {} already within size limit, using original",
+    if config.embed_metadata && config.output_format == OutputFormat::Jpeg {
+        let comment = format!(
+            "{} frame {}/{}",
+            config.movie_name,
+            current_frame,
+            load_total_frame_count(config).await?
+        );
+        for image in &mut images {
+            embed_comment(image, &comment, config);
+        }
+    }
+
+    Ok(images)
+}
+
+/// Process a frame into the whole frame, or its tiles when a tile layout is
+/// configured.
+///
+/// Fails with `FrameError::BlackFrame` for black frames when
+/// `SKIP_BLACK_FRAMES` is set.
+pub async fn process_frame_images(
+    current_frame: u32,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<Vec<ProcessedFrame>> {
+    if let Some(threshold) = config.black_frame_threshold {
+        let started = Instant::now();
+        check_black_frame(current_frame, threshold, config).await?;
+        timings.record("black frame check", started);
+    }
+
+    match config.tile_mode {
+        Some(layout) => get_frame_as_tiles(current_frame, layout, config, timings).await,
+        None => Ok(vec![
+            get_frame_as_jpeg(current_frame, config, timings).await?,
+        ]),
+    }
+}
+
+/// Fail with `FrameError::BlackFrame` when the frame's mean luminance is below
+/// `threshold`.
+///
+/// The source frame is decoded for this on its own, since frames that fit the
+/// size limit are otherwise posted without decoding them.
+async fn check_black_frame(frame: u32, threshold: u8, config: &Config) -> anyhow::Result<()> {
+    let data = read_frame(frame, config).await?;
+    let name = format!("frame {}", frame);
+    let max_pixels = config.max_pixels;
+    let mean_luma = run_blocking(name.clone(), config.compression_timeout, move || {
+        let image = decode_image(&data, &name, max_pixels)?;
+        Ok(FrameAnalysis::of(&image).mean_luma)
+    })
+    .await?;
+
+    if mean_luma < threshold as f64 {
+        return Err(FrameError::BlackFrame { frame, mean_luma }.into());
+    }
+    Ok(())
+}
+
+/// Add `comment` to a processed JPEG, unless that would push it over the size
+/// limit.
+///
+/// The comment is only informational, so failures are logged and the image is
+/// posted without it.
+fn embed_comment(image: &mut ProcessedFrame, comment: &str, config: &Config) {
+    match jpeg_segments::insert_comment(&image.jpeg_data, comment) {
+        Ok(data) if data.len() <= config.jpeg_size_limit() => image.jpeg_data = data,
+        Ok(_) => warn!("Not embedding metadata, it would take the image over the size limit"),
+        Err(e) => warn!("Failed to embed metadata: {:#}", e),
+    }
+}
+
+/// Load JPEG frame and recompress only if needed for size optimization.
+///
+/// With `USE_PRECOMPRESSED`, the frame made by the `precompress` command is
+/// used as-is when there is one. Otherwise see `process_frame`.
+pub async fn get_frame_as_jpeg(
+    current_frame: u32,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<ProcessedFrame> {
+    if config.use_precompressed {
+        let started = Instant::now();
+        if let Some(processed) = precompressed::load(current_frame, config).await? {
+            timings.record("read", started);
+            debug!("Using precompressed frame {}", current_frame);
+            return Ok(processed);
+        }
+        warn!(
+            "Frame {} hasn't been precompressed, processing it now",
             current_frame
         );
+    }
 
-        let result =
-            tokio::task::spawn_blocking(move || get_image_dimensions(jpeg_data, current_frame))
+    process_frame(current_frame, config, timings).await
+}
+
+/// Load a frame file and recompress it only if needed for size optimization.
+///
+/// Takes a frame number, loads the corresponding JPEG file, and verifies it
+/// against the checksum manifest when enabled before handing it to
+/// `process_image` along with its timecode when the overlay is enabled and
+/// the crop when one is configured.
+pub async fn process_frame(
+    current_frame: u32,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<ProcessedFrame> {
+    let started = Instant::now();
+    let jpeg_data = read_frame(current_frame, config).await?;
+    timings.record("read", started);
+
+    process_image(
+        jpeg_data,
+        format!("frame {}", current_frame),
+        overlay_timecode(current_frame, config),
+        config.crop,
+        config,
+        timings,
+    )
+    .await
+}
+
+/// Read and decode a frame, cropped as configured, without compressing it.
+pub async fn decode_frame(frame: u32, config: &Config) -> anyhow::Result<DynamicImage> {
+    let data = read_frame(frame, config).await?;
+    let name = format!("frame {}", frame);
+    let (max_pixels, crop) = (config.max_pixels, config.crop);
+    run_blocking(name.clone(), config.compression_timeout, move || {
+        let image = decode_image(&data, &name, max_pixels)?;
+        Ok(crop_image(image, &name, crop))
+    })
+    .await
+}
+
+/// Check that a frame can be posted, without fully processing it.
+///
+/// Reads and decodes the frame, failing when it can't be decoded or is empty.
+/// Frames that would be recompressed are also encoded once at the lowest
+/// quality, failing when even that is over the size limit, unless a quality
+/// floor is set, since those are downscaled until they fit. Returns the
+/// frame's dimensions.
+pub async fn verify_frame(frame: u32, config: &Config) -> anyhow::Result<(u32, u32)> {
+    let data = read_frame(frame, config).await?;
+    let name = format!("frame {}", frame);
+    let config = config.clone();
+    run_blocking(name.clone(), config.compression_timeout, move || {
+        let image = decode_image(&data, &name, config.max_pixels)?;
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            bail!("{} has no pixels ({}x{})", name, width, height);
+        }
+
+        // Only frames that can't be posted as-is have to fit the limit once encoded
+        let recompressed =
+            frame_original_use(frame, data.clone(), &config)? == OriginalUse::Recompress;
+        if recompressed && config.quality_floor.is_none() {
+            let image = limit_megapixels(image, &name, &config);
+            let image = scale_to_post_width(image, &name, &config);
+            let smallest = encode_image(
+                &image,
+                MIN_JPEG_QUALITY,
+                &name,
+                config.output_format,
+                config.jpeg_encoder,
+            )?;
+            if smallest.len() > config.jpeg_size_limit() {
+                bail!(
+                    "{} is {} bytes even at quality {}, over the {} byte limit",
+                    name,
+                    smallest.len(),
+                    MIN_JPEG_QUALITY,
+                    config.jpeg_size_limit()
+                );
+            }
+        }
+
+        Ok((width, height))
+    })
+    .await
+}
+
+/// Load a frame and split it into tiles, each compressed to fit the size limit.
+///
+/// Tiles are always re-encoded since cropping requires decoding the frame.
+async fn get_frame_as_tiles(
+    current_frame: u32,
+    layout: TileLayout,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<Vec<ProcessedFrame>> {
+    let started = Instant::now();
+    let jpeg_data = read_frame(current_frame, config).await?;
+    timings.record("read", started);
+    let name = format!("frame {}", current_frame);
+    let timecode = overlay_timecode(current_frame, config);
+
+    let config = config.clone();
+    let (tiles, stages) = run_blocking(name.clone(), config.compression_timeout, move || {
+        let mut stages = StageTimings::new();
+        let tiles = process_tiles(
+            jpeg_data,
+            &name,
+            timecode.as_deref(),
+            layout,
+            &config,
+            &mut stages,
+        )?;
+        Ok((tiles, stages))
+    })
+    .await?;
+
+    timings.extend(stages);
+    Ok(tiles)
+}
+
+/// The timecode to burn into a frame, when the overlay is enabled.
+fn overlay_timecode(frame: u32, config: &Config) -> Option<String> {
+    config.timecode_overlay?;
+    Some(timecode::format_timecode(frame, config.fps?))
+}
+
+/// Path of the frame file at a 1-based position in the movie.
+///
+/// Numbered frames are simply `<frame>.jpg`. Frames named by timecode are
+/// found by their position once sorted, which is cached like the frame count.
+async fn frame_path(frame: u32, config: &Config) -> anyhow::Result<PathBuf> {
+    let frames = match config.frame_name_format {
+        FrameNameFormat::Number => return Ok(Path::new(FRAMES_DIR).join(format!("{}.jpg", frame))),
+        FrameNameFormat::Timecode => match TIMECODE_FRAMES.get() {
+            Some(frames) => frames,
+            None => {
+                let frames = timecode_ordered_frames().await?;
+                TIMECODE_FRAMES.get_or_init(|| frames)
+            }
+        },
+    };
+
+    frame
+        .checked_sub(1)
+        .and_then(|index| frames.get(index as usize))
+        .cloned()
+        .with_context(|| {
+            format!(
+                "There is no frame {}, only {} frames are named by timecode",
+                frame,
+                frames.len()
+            )
+        })
+}
+
+/// Read a frame file, verifying it against the checksum manifest when enabled.
+async fn read_frame(current_frame: u32, config: &Config) -> anyhow::Result<Vec<u8>> {
+    validate_frame_number(current_frame)?;
+
+    let (file_name, jpeg_data) = match &config.frame_source {
+        FrameSource::Local => {
+            let frame_path = frame_path(current_frame, config).await?;
+            ensure_frame_exists(&frame_path).await?;
+
+            let jpeg_data = tokio::fs::read(&frame_path)
                 .await
-                .with_context(|| {
-                    format!(
-                        "Task panicked while getting dimensions for frame {}",
-                        current_frame
-                    )
-                })??;
+                .with_context(|| format!("Failed to read frame file: {}", frame_path.display()))?;
+            let file_name = frame_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string();
+            (file_name, jpeg_data)
+        }
+        #[cfg(feature = "remote-frames")]
+        FrameSource::Http(base_url) => {
+            let file_name = format!("{}.jpg", current_frame);
+            let jpeg_data = remote_frames::fetch_frame(base_url, &file_name).await?;
+            (file_name, jpeg_data)
+        }
+    };
 
-        return Ok(result);
+    if config.verify_checksums && !checksum::verify_frame(&file_name, &jpeg_data).await? {
+        return Err(FrameError::ChecksumMismatch {
+            frame: current_frame,
+        }
+        .into());
     }
 
-    // File is too large, needs recompression
+    Ok(jpeg_data)
+}
+
+/// Load an arbitrary image file and prepare it for upload like a frame.
+///
+/// The image doesn't need to be a JPEG, anything the `image` crate can decode
+/// is converted to one.
+#[cfg(feature = "bluesky")]
+pub async fn get_image_as_jpeg(
+    path: &Path,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<ProcessedFrame> {
+    let started = Instant::now();
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read image file: {}", path.display()))?;
+    timings.record("read", started);
+
+    process_image(
+        data,
+        path.display().to_string(),
+        None,
+        None,
+        config,
+        timings,
+    )
+    .await
+}
+
+/// Recompress image data only if needed for size optimization.
+///
+/// If the data is already a JPEG within the size limit and frames are posted
+/// as JPEG, returns it directly. Otherwise, recompresses with quality
+/// optimization to meet the size requirements. Originals within the limit but
+/// over `RECOMPRESS_ABOVE` are recompressed too, keeping whichever of the two
+/// is smaller. Metadata is stripped first when enabled, so both paths post the
+/// same clean output. `name` identifies the image in logs and errors.
+///
+/// A `timecode` to burn in or a `crop` to apply always forces recompression,
+/// as does a `POST_WIDTH` the frame isn't already at.
+async fn process_image(
+    data: Vec<u8>,
+    name: String,
+    timecode: Option<String>,
+    crop: Option<FrameCrop>,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<ProcessedFrame> {
+    let data = strip_metadata_if_enabled(data, &name, config);
+    let original_size = data.len();
+    debug!("Original size of {}: {} bytes", name, original_size);
+
+    let transformed = timecode.is_some() || crop.is_some();
+    let original_use = original_use(&data, &name, transformed, config)?;
+    if original_use == OriginalUse::AsIs {
+        debug!("Using original of {}, already within size limit", name);
+        return use_original(data, name, config, timings).await;
+    }
+
+    // Originals only recompressed to save space are kept if that didn't help
+    let original = (original_use == OriginalUse::RecompressIfSmaller).then(|| data.clone());
+
+    // File is too large or not a JPEG, needs recompression
     debug!(
-        "Frame {} too large ({}), recompressing",
-        current_frame, original_size
+        "Recompressing {} ({} bytes, JPEG: {})",
+        name,
+        original_size,
+        data.starts_with(&[0xFF, 0xD8])
     );
 
-    let result =
-        tokio::task::spawn_blocking(move || process_jpeg_recompression(jpeg_data, current_frame))
-            .await
-            .with_context(|| {
-                format!("Task panicked while recompressing frame {}", current_frame)
-            })??;
+    let recompress_config = config.clone();
+    let recompress_name = name.clone();
+    let (result, stages) = run_blocking(name.clone(), config.compression_timeout, move || {
+        let mut stages = StageTimings::new();
+        let result = process_jpeg_recompression(
+            data,
+            &recompress_name,
+            timecode.as_deref(),
+            crop,
+            &recompress_config,
+            &mut stages,
+        )?;
+        Ok((result, stages))
+    })
+    .await?;
+    timings.extend(stages);
+
+    if let Some(original) = original
+        && result.jpeg_data.len() >= original.len()
+    {
+        debug!(
+            "Recompressing {} didn't make it smaller, using the original",
+            name
+        );
+        return use_original(original, name, config, timings).await;
+    }
 
     debug!(
-        "Frame {} recompressed successfully (quality: {:?})",
-        current_frame, result.quality_used
+        "Recompressed successfully (quality: {:?})",
+        result.quality_used
     );
     Ok(result)
 }
 
+/// How an original image is used when preparing it for posting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OriginalUse {
+    /// Posted as-is, as it's already within every limit
+    AsIs,
+    /// Postable as-is but over `RECOMPRESS_ABOVE`, so recompressed and only
+    /// replaced if that makes it smaller
+    RecompressIfSmaller,
+    /// Recompressed, as it can't be posted the way it is
+    Recompress,
+}
+
+/// Decide how frame `frame`'s original `data` will be used when it's posted.
+///
+/// Makes the same decision as `process_frame`, for reporting on frames
+/// without processing them.
+pub fn frame_original_use(
+    frame: u32,
+    data: Vec<u8>,
+    config: &Config,
+) -> anyhow::Result<OriginalUse> {
+    let name = format!("frame {}", frame);
+    let data = strip_metadata_if_enabled(data, &name, config);
+    let transformed = overlay_timecode(frame, config).is_some() || config.crop.is_some();
+    original_use(&data, &name, transformed, config)
+}
+
+/// Decide how an original image is used, with metadata already stripped.
+///
+/// Only JPEGs posted as JPEG can be used as-is, and only when they're within
+/// the size limit, `TARGET_MEGAPIXELS`, and `POST_WIDTH`, and aren't
+/// `transformed` by a timecode or crop.
+fn original_use(
+    data: &[u8],
+    name: &str,
+    transformed: bool,
+    config: &Config,
+) -> anyhow::Result<OriginalUse> {
+    let is_jpeg = data.starts_with(&[0xFF, 0xD8]);
+    let within_megapixels = match config.target_megapixels {
+        Some(target_megapixels) if is_jpeg => {
+            let (width, height) = read_dimensions(data, name)?;
+            megapixel_limited_size(width, height, target_megapixels).is_none()
+        }
+        _ => true,
+    };
+    let at_post_width = match config.post_width {
+        Some(post_width) if is_jpeg => read_dimensions(data, name)?.0 == post_width,
+        _ => true,
+    };
+
+    let postable = is_jpeg
+        && config.output_format == OutputFormat::Jpeg
+        && data.len() <= config.jpeg_size_limit()
+        && within_megapixels
+        && at_post_width
+        && !transformed;
+    Ok(match postable {
+        false => OriginalUse::Recompress,
+        true if data.len() <= config.recompress_above => OriginalUse::AsIs,
+        true => OriginalUse::RecompressIfSmaller,
+    })
+}
+
+/// Strip metadata from JPEG data when enabled, leaving it as-is if that fails.
+fn strip_metadata_if_enabled(data: Vec<u8>, name: &str, config: &Config) -> Vec<u8> {
+    if !config.strip_metadata || !data.starts_with(&[0xFF, 0xD8]) {
+        return data;
+    }
+
+    match jpeg_segments::strip_metadata(&data) {
+        Ok(stripped) => {
+            trace!(
+                "Stripped {} bytes of metadata from {}",
+                data.len() - stripped.len(),
+                name
+            );
+            stripped
+        }
+        Err(e) => {
+            warn!(
+                "Failed to strip metadata from {}, using it as-is: {:#}",
+                name, e
+            );
+            data
+        }
+    }
+}
+
+/// Post an original JPEG as-is, only decoding it for its dimensions.
+async fn use_original(
+    data: Vec<u8>,
+    name: String,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<ProcessedFrame> {
+    let max_pixels = config.max_pixels;
+    let with_color = config.wants_average_color();
+    let started = Instant::now();
+    let result = run_blocking(name.clone(), config.compression_timeout, move || {
+        get_image_dimensions(data, &name, max_pixels, with_color)
+    })
+    .await?;
+
+    timings.record("decode", started);
+    Ok(result)
+}
+
+/// Run blocking image work on a separate thread, with an optional time limit.
+///
+/// Blocking tasks can't be cancelled, so when the limit is hit the thread keeps
+/// running until the work finishes on its own, but its result is discarded and
+/// the image is reported as timed out so the caller can move on.
+async fn run_blocking<T, F>(name: String, timeout: Option<Duration>, work: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    let task = tokio::task::spawn_blocking(work);
+    let result = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, task).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "Abandoning {} after {} seconds, its thread will finish in the background",
+                    name,
+                    timeout.as_secs()
+                );
+                return Err(FrameError::ProcessingTimedOut {
+                    image: name,
+                    seconds: timeout.as_secs(),
+                }
+                .into());
+            }
+        },
+        None => task.await,
+    };
+
+    result.with_context(|| format!("Task panicked while processing {}", name))?
+}
+
 /// Warn if frame number seems unusual.
 fn validate_frame_number(frame: u32) -> anyhow::Result<()> {
     if frame == 0 {
@@ -145,113 +772,642 @@ fn validate_frame_number(frame: u32) -> anyhow::Result<()> {
 }
 
 /// Check if frame file exists before processing.
-async fn ensure_frame_exists(path: &str) -> anyhow::Result<()> {
+async fn ensure_frame_exists(path: &Path) -> anyhow::Result<()> {
     if !tokio::fs::try_exists(path).await? {
-        bail!("Frame file does not exist: {}", path);
+        bail!("Frame file does not exist: {}", path.display());
     }
     Ok(())
 }
 
 /// Get image dimensions from JPEG data without recompression.
-fn get_image_dimensions(jpeg_data: Vec<u8>, frame_num: u32) -> anyhow::Result<ProcessedFrame> {
-    trace!(
-        "Getting dimensions for frame {} without recompression",
-        frame_num
-    );
+///
+/// Also works out the average color when `with_color` is set, since the image
+/// has been decoded anyway.
+fn get_image_dimensions(
+    jpeg_data: Vec<u8>,
+    name: &str,
+    max_pixels: Option<u64>,
+    with_color: bool,
+) -> anyhow::Result<ProcessedFrame> {
+    trace!("Getting dimensions for {} without recompression", name);
 
-    let image = image::load_from_memory(&jpeg_data)
-        .with_context(|| format!("Failed to decode JPEG for frame {}", frame_num))?;
+    let image = decode_image(&jpeg_data, name, max_pixels)?;
 
     let (width, height) = image.dimensions();
-    debug!("Frame {} dimensions: {}x{}", frame_num, width, height);
+    debug!("Dimensions of {}: {}x{}", name, width, height);
 
     Ok(ProcessedFrame {
         jpeg_data,
         dimensions: FrameDimensions { width, height },
         quality_used: None, // Original image used as-is
+        #[cfg(feature = "bluesky")]
+        tile_position: None,
+        average_color: with_color.then(|| average_color(&image)),
     })
 }
 
-/// Recompress JPEG with quality optimization to meet size requirements.
+/// The average color of an image as a hex code like `#1a2b3c`.
+fn average_color(image: &DynamicImage) -> String {
+    let mut sums = [0u64; 3];
+    for (_, _, pixel) in image.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(channel);
+        }
+    }
+
+    let count = (u64::from(image.width()) * u64::from(image.height())).max(1);
+    let [red, green, blue] = sums.map(|sum| sum / count);
+    format!("#{red:02x}{green:02x}{blue:02x}")
+}
+
+/// Decode image data, refusing images with more pixels than allowed.
+///
+/// The dimensions are read from the header before decoding, so oversized images
+/// are rejected without allocating their pixel buffer. The pixel limit is also
+/// applied as an allocation limit on the decoder itself.
+fn decode_image(data: &[u8], name: &str, max_pixels: Option<u64>) -> anyhow::Result<DynamicImage> {
+    let reader = || {
+        ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .with_context(|| format!("Failed to detect image format of {}", name))
+    };
+
+    let mut limits = Limits::default();
+    if let Some(max_pixels) = max_pixels {
+        let (width, height) = read_dimensions(data, name)?;
+
+        if u64::from(width) * u64::from(height) > max_pixels {
+            return Err(FrameError::TooManyPixels {
+                image: name.to_string(),
+                width,
+                height,
+                max_pixels,
+            }
+            .into());
+        }
+
+        // Allow for up to four bytes per pixel, as in RGBA output
+        limits.max_alloc = Some(max_pixels.saturating_mul(4));
+    }
+
+    let mut reader = reader()?;
+    reader.limits(limits);
+    reader
+        .decode()
+        .with_context(|| format!("Failed to decode {}", name))
+}
+
+/// Read an image's dimensions from its header without decoding it.
+fn read_dimensions(data: &[u8], name: &str) -> anyhow::Result<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .with_context(|| format!("Failed to detect image format of {}", name))?
+        .into_dimensions()
+        .with_context(|| format!("Failed to read dimensions of {}", name))
+}
+
+/// The size an image must be scaled down to for at most `target_megapixels`,
+/// keeping its aspect ratio, or `None` if it's already small enough.
+fn megapixel_limited_size(width: u32, height: u32, target_megapixels: f64) -> Option<(u32, u32)> {
+    let pixels = f64::from(width) * f64::from(height);
+    let target_pixels = target_megapixels * 1_000_000.0;
+    if pixels <= target_pixels {
+        return None;
+    }
+
+    // Rounding both sides down keeps the result at or under the target
+    let scale = (target_pixels / pixels).sqrt();
+    let new_width = ((f64::from(width) * scale) as u32).max(1);
+    let new_height = ((f64::from(height) * scale) as u32).max(1);
+    Some((new_width, new_height))
+}
+
+/// Downscale a decoded image to the configured megapixel target, if it's over.
+fn limit_megapixels(image: DynamicImage, name: &str, config: &Config) -> DynamicImage {
+    let Some(target_megapixels) = config.target_megapixels else {
+        return image;
+    };
+    let (width, height) = image.dimensions();
+    let Some((new_width, new_height)) = megapixel_limited_size(width, height, target_megapixels)
+    else {
+        return image;
+    };
+
+    debug!(
+        "Downscaling {} from {}x{} to {}x{} to fit {} megapixels",
+        name, width, height, new_width, new_height, target_megapixels
+    );
+    image.resize_exact(new_width, new_height, FilterType::Lanczos3)
+}
+
+/// Scale an image up or down to `POST_WIDTH`, keeping its aspect ratio, if set.
+fn scale_to_post_width(image: DynamicImage, name: &str, config: &Config) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    let Some(post_width) = config.post_width.filter(|&post_width| post_width != width) else {
+        return image;
+    };
+    let post_height = ((height as u64 * post_width as u64 + width as u64 / 2) / width as u64)
+        .clamp(1, u32::MAX as u64) as u32;
+
+    debug!(
+        "Scaling {} from {}x{} to {}x{}",
+        name, width, height, post_width, post_height
+    );
+    image.resize_exact(post_width, post_height, FilterType::Lanczos3)
+}
+
+/// Recompress an image in the output format with quality optimization to meet
+/// size requirements.
 fn process_jpeg_recompression(
-    jpeg_data: Vec<u8>,
-    frame_num: u32,
+    data: Vec<u8>,
+    name: &str,
+    timecode: Option<&str>,
+    crop: Option<FrameCrop>,
+    config: &Config,
+    timings: &mut StageTimings,
 ) -> anyhow::Result<ProcessedFrame> {
-    trace!("Decoding JPEG for recompression, frame {}", frame_num);
-    let image = image::load_from_memory(&jpeg_data)
-        .with_context(|| format!("Failed to decode JPEG for frame {}", frame_num))?;
+    trace!("Decoding {} for recompression", name);
+    let started = Instant::now();
+    let image = decode_image(&data, name, config.max_pixels)?;
+    let started = timings.record("decode", started);
+    let image = crop_image(image, name, crop);
+    let image = limit_megapixels(image, name, config);
+    let image = scale_to_post_width(image, name, config);
+    log_analysis(&image, name, config);
 
     let (width, height) = image.dimensions();
-    debug!("Frame {} dimensions: {}x{}", frame_num, width, height);
+    debug!("Dimensions of {}: {}x{}", name, width, height);
 
     // Convert to RGB8 to ensure consistent format for recompression
     trace!("Converting image to RGB8 format");
-    let rgb_image = DynamicImage::ImageRgb8(image.to_rgb8());
-
-    let (optimized_data, quality_used) = compress_to_jpeg(&rgb_image, frame_num)?;
+    let mut rgb_image = image.to_rgb8();
+    burn_in_timecode(&mut rgb_image, timecode, config);
+    let rgb_image = DynamicImage::ImageRgb8(rgb_image);
 
+    let result = compress_to_jpeg(&rgb_image, name, config)?;
+    timings.record("compress", started);
     Ok(ProcessedFrame {
-        jpeg_data: optimized_data,
-        dimensions: FrameDimensions { width, height },
-        quality_used: Some(quality_used),
+        average_color: config
+            .wants_average_color()
+            .then(|| average_color(&rgb_image)),
+        ..result
     })
 }
 
-/// Compress image to JPEG under the size limit.
+/// Split an image into tiles and compress each one to JPEG.
+fn process_tiles(
+    data: Vec<u8>,
+    name: &str,
+    timecode: Option<&str>,
+    layout: TileLayout,
+    config: &Config,
+    timings: &mut StageTimings,
+) -> anyhow::Result<Vec<ProcessedFrame>> {
+    trace!("Decoding {} for splitting into tiles", name);
+    let started = Instant::now();
+    let image = decode_image(&data, name, config.max_pixels)?;
+    let started = timings.record("decode", started);
+    let image = crop_image(image, name, config.crop);
+    let image = limit_megapixels(image, name, config);
+    let image = scale_to_post_width(image, name, config);
+    log_analysis(&image, name, config);
+    let image = match timecode {
+        Some(_) => {
+            let mut rgb_image = image.to_rgb8();
+            burn_in_timecode(&mut rgb_image, timecode, config);
+            DynamicImage::ImageRgb8(rgb_image)
+        }
+        None => image,
+    };
+    let tiles = layout.split(&image);
+    timings.record("split", started);
+
+    tiles
+        .into_iter()
+        .enumerate()
+        .map(|(index, tile)| {
+            let tile_name = format!("{} tile {}", name, index + 1);
+            let started = Instant::now();
+            let rgb_image = DynamicImage::ImageRgb8(tile.image.to_rgb8());
+            let (width, height) = rgb_image.dimensions();
+            debug!("Dimensions of {}: {}x{}", tile_name, width, height);
+
+            let processed = compress_to_jpeg(&rgb_image, &tile_name, config)?;
+            timings.record("compress", started);
+            Ok(ProcessedFrame {
+                #[cfg(feature = "bluesky")]
+                tile_position: Some(tile.position),
+                average_color: config
+                    .wants_average_color()
+                    .then(|| average_color(&rgb_image)),
+                ..processed
+            })
+        })
+        .collect()
+}
+
+/// Log the luminance statistics of a decoded image when `ANALYZE_FRAMES` is set.
+fn log_analysis(image: &DynamicImage, name: &str, config: &Config) {
+    if config.analyze_frames {
+        info!("Analysis of {}: {}", name, FrameAnalysis::of(image));
+    }
+}
+
+/// Crop an image to the configured aspect ratio around its focal point, if any.
+fn crop_image(image: DynamicImage, name: &str, crop: Option<FrameCrop>) -> DynamicImage {
+    let Some(crop) = crop else {
+        return image;
+    };
+
+    let (width, height) = image.dimensions();
+    let (x, y, crop_width, crop_height) = crop.region(width, height);
+    debug!(
+        "Cropping {} from {}x{} to {}x{} at {},{}",
+        name, width, height, crop_width, crop_height, x, y
+    );
+    image.crop_imm(x, y, crop_width, crop_height)
+}
+
+/// Draw the timecode onto an image when the overlay is enabled.
+fn burn_in_timecode(image: &mut RgbImage, timecode: Option<&str>, config: &Config) {
+    if let (Some(timecode), Some(overlay)) = (timecode, &config.timecode_overlay) {
+        trace!("Burning in timecode {}", timecode);
+        overlay.draw(image, timecode);
+    }
+}
+
+/// Compress image in the output format under the size limit.
 ///
-/// Iteratively reduces JPEG quality until the file size is under the limit.
-/// Starts at maximum quality and works down in steps. Fails if even minimum
-/// quality produces a file that's too large.
-fn compress_to_jpeg(image: &DynamicImage, frame_num: u32) -> anyhow::Result<(Vec<u8>, u8)> {
-    let mut quality = 100u8;
-    let mut buffer = Vec::with_capacity(MAX_JPEG_SIZE);
+/// Searches for a quality that fits at full size first. When a quality floor
+/// is configured and the image won't fit without dropping below it, the image
+/// is downscaled by the configured step and the search retried, trading
+/// resolution for quality. Fails if the image can't be made to fit.
+///
+/// If the encoder itself fails, the image is downscaled the same way and
+/// tried again, up to `MAX_ENCODING_FALLBACKS` times, before giving up with
+/// the encoder's error.
+fn compress_to_jpeg(
+    image: &DynamicImage,
+    name: &str,
+    config: &Config,
+) -> anyhow::Result<ProcessedFrame> {
+    let floor = config.quality_floor.unwrap_or(MIN_JPEG_QUALITY);
+    let mut scale = 1.0;
+    let mut downscaled: Option<DynamicImage> = None;
+    let mut encoding_fallbacks = 0;
+
+    loop {
+        let current = downscaled.as_ref().unwrap_or(image);
+        let (width, height) = current.dimensions();
+
+        let encoding_error = match compress_above_floor(current, name, floor, config) {
+            Ok(Some((jpeg_data, quality))) => {
+                return Ok(ProcessedFrame {
+                    jpeg_data,
+                    dimensions: FrameDimensions { width, height },
+                    quality_used: Some(quality),
+                    #[cfg(feature = "bluesky")]
+                    tile_position: None,
+                    average_color: None,
+                });
+            }
+            Ok(None) => None,
+            Err(e)
+                if encoding_fallbacks < MAX_ENCODING_FALLBACKS
+                    && matches!(
+                        e.downcast_ref::<FrameError>(),
+                        Some(FrameError::EncodingFailed { .. })
+                    ) =>
+            {
+                encoding_fallbacks += 1;
+                Some(e)
+            }
+            Err(e) => return Err(e),
+        };
+
+        scale *= config.downscale_step;
+        let new_width = (image.width() as f64 * scale).round() as u32;
+        let new_height = (image.height() as f64 * scale).round() as u32;
+
+        let can_downscale = (config.quality_floor.is_some() || encoding_error.is_some())
+            && new_width > 0
+            && new_height > 0
+            && (new_width, new_height) != (width, height);
+        if !can_downscale {
+            return Err(encoding_error.unwrap_or_else(|| {
+                FrameError::CompressionFailed {
+                    image: name.to_string(),
+                    max_size: config.jpeg_size_limit() as f64 / 1_000_000.0,
+                }
+                .into()
+            }));
+        }
+
+        match &encoding_error {
+            Some(e) => warn!(
+                "{}, downscaling from {}x{} to {}x{} and trying again",
+                e, width, height, new_width, new_height
+            ),
+            None => info!(
+                "Downscaling {} from {}x{} to {}x{} to keep quality at or above {}",
+                name, width, height, new_width, new_height, floor
+            ),
+        }
+        // Always resize from the original so repeated steps don't compound blur
+        downscaled = Some(image.resize_exact(new_width, new_height, FilterType::Lanczos3));
+    }
+}
+
+/// Find the highest quality at or above `floor` that fits under the size limit.
+///
+/// Starts at maximum quality and works down in steps. When an SSIM target is
+/// configured, starts from the lowest quality that still meets it instead.
+/// Returns `None` if even the floor produces a file that's too large.
+fn compress_above_floor(
+    image: &DynamicImage,
+    name: &str,
+    floor: u8,
+    config: &Config,
+) -> anyhow::Result<Option<(Vec<u8>, u8)>> {
+    let mut quality = match config.ssim_target {
+        Some(target) => find_ssim_quality(image, name, target, config.jpeg_encoder)?.max(floor),
+        None => max_quality(config.output_format),
+    };
     let mut attempts = 0;
 
     debug!(
-        "Starting JPEG encoding with quality optimization for frame {}",
-        frame_num
+        "Starting JPEG encoding with quality optimization for {}",
+        name
     );
 
     loop {
         attempts += 1;
-        buffer.clear();
-
         trace!("Attempt {}: Encoding with quality {}", attempts, quality);
 
-        let mut cursor = Cursor::new(&mut buffer);
-        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, quality);
-
-        image.write_with_encoder(encoder).with_context(|| {
-            format!(
-                "Failed to encode frame {} to JPEG at quality {}",
-                frame_num, quality
-            )
-        })?;
-
+        let buffer = encode_image(
+            image,
+            quality,
+            name,
+            config.output_format,
+            config.jpeg_encoder,
+        )?;
         let buffer_size = buffer.len();
         debug!("JPEG encoded at quality {}: {} bytes", quality, buffer_size);
 
-        if buffer_size <= MAX_JPEG_SIZE {
+        if buffer_size <= config.jpeg_size_limit() {
             debug!(
-                "Successfully recompressed frame {} to JPEG: {} bytes at quality {}",
-                frame_num, buffer_size, quality
+                "Successfully recompressed {} to JPEG: {} bytes at quality {}",
+                name, buffer_size, quality
             );
-            return Ok((buffer, quality));
+            return Ok(Some((buffer, quality)));
         }
 
-        if quality <= MIN_JPEG_QUALITY {
-            return Err(FrameError::CompressionFailed {
-                frame: frame_num,
-                max_size: MAX_JPEG_SIZE as f64 / 1_000_000.0,
-            }
-            .into());
+        if quality <= floor {
+            return Ok(None);
         }
 
         let old_quality = quality;
-        quality = quality.saturating_sub(JPEG_QUALITY_STEP);
+        quality = quality.saturating_sub(JPEG_QUALITY_STEP).max(floor);
         debug!(
             "Buffer too large ({} bytes), reducing quality from {} to {}",
             buffer_size, old_quality, quality
         );
     }
 }
+
+/// Find the lowest JPEG quality whose output still meets the SSIM target.
+///
+/// Binary searches between the minimum quality and 100, decoding each candidate
+/// and comparing its luma against the source. Returns 100 if no quality meets
+/// the target. The result may still be over the size limit, in which case
+/// `compress_above_floor` keeps reducing quality from there.
+fn find_ssim_quality(
+    image: &DynamicImage,
+    name: &str,
+    target: f64,
+    encoder: JpegEncoder,
+) -> anyhow::Result<u8> {
+    let reference = image.to_luma8();
+    let (mut low, mut high) = (MIN_JPEG_QUALITY, 100u8);
+
+    while low < high {
+        let quality = low + (high - low) / 2;
+        let encoded = encode_image(image, quality, name, OutputFormat::Jpeg, encoder)?;
+        let decoded = image::load_from_memory(&encoded)
+            .with_context(|| format!("Failed to decode {} encoded at quality {}", name, quality))?
+            .to_luma8();
+
+        let score = ssim::mean_ssim(&reference, &decoded);
+        trace!("Quality {} has SSIM {:.4}", quality, score);
+
+        if score >= target {
+            high = quality;
+        } else {
+            low = quality + 1;
+        }
+    }
+
+    debug!("SSIM target {} met for {} at quality {}", target, name, low);
+    Ok(low)
+}
+
+/// Quality the size search starts from for a format.
+fn max_quality(format: OutputFormat) -> u8 {
+    match format {
+        OutputFormat::Jpeg => 100,
+        #[cfg(feature = "avif")]
+        OutputFormat::Avif => AVIF_MAX_QUALITY,
+    }
+}
+
+/// Encode an image in the given format and quality.
+fn encode_image(
+    image: &DynamicImage,
+    quality: u8,
+    name: &str,
+    format: OutputFormat,
+    encoder: JpegEncoder,
+) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(PLATFORM_BLOB_LIMIT);
+    let mut cursor = Cursor::new(&mut buffer);
+
+    let result = match (format, encoder) {
+        (OutputFormat::Jpeg, JpegEncoder::Image) => image.write_with_encoder(
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, quality),
+        ),
+        #[cfg(feature = "mozjpeg")]
+        (OutputFormat::Jpeg, JpegEncoder::Mozjpeg { progressive }) => {
+            match mozjpeg::encode(image, quality, progressive, name) {
+                Ok(jpeg) => return Ok(jpeg),
+                // Only cjpeg rejecting this image makes the frame skippable
+                Err(mozjpeg::EncodeError::Exited(message)) => {
+                    Err(image::ImageError::IoError(std::io::Error::other(message)))
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        #[cfg(feature = "avif")]
+        (OutputFormat::Avif, _) => {
+            image.write_with_encoder(image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                &mut cursor,
+                AVIF_SPEED,
+                quality,
+            ))
+        }
+    };
+    result.map_err(|source| FrameError::EncodingFailed {
+        image: name.to_string(),
+        format,
+        quality,
+        source,
+    })?;
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch_dir::ScratchDir;
+
+    async fn scanned_names(dir: &ScratchDir) -> Vec<String> {
+        let mut names: Vec<String> = scan_frame_files_in(dir.path())
+            .await
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn scan_finds_jpg_frames() {
+        let dir = ScratchDir::new("scan-frames");
+        dir.write("1.jpg", b"frame");
+        dir.write("2.jpg", b"frame");
+        dir.write("00_01_23_456.jpg", b"frame");
+
+        assert_eq!(
+            scanned_names(&dir).await,
+            ["00_01_23_456.jpg", "1.jpg", "2.jpg"]
+        );
+    }
+
+    #[tokio::test]
+    async fn scan_skips_empty_frames() {
+        let dir = ScratchDir::new("scan-empty");
+        dir.write("1.jpg", b"frame");
+        dir.write("2.jpg", b"");
+
+        assert_eq!(scanned_names(&dir).await, ["1.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn scan_skips_non_frame_files() {
+        let dir = ScratchDir::new("scan-non-frames");
+        dir.write("1.jpg", b"frame");
+        dir.write(".DS_Store", b"junk");
+        dir.write("2.jpg.part", b"partial");
+        dir.write("3.png", b"frame");
+        dir.write("notes.txt", b"notes");
+        std::fs::create_dir(dir.file("4.jpg")).unwrap();
+
+        assert_eq!(scanned_names(&dir).await, ["1.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn scan_of_empty_directory_finds_nothing() {
+        let dir = ScratchDir::new("scan-nothing");
+        assert!(scanned_names(&dir).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn scan_of_missing_directory_fails() {
+        let dir = ScratchDir::new("scan-missing");
+        assert!(scan_frame_files_in(&dir.file("frames")).await.is_err());
+    }
+
+    #[test]
+    fn frame_number_parses_numbered_files() {
+        assert_eq!(frame_number(Path::new("frames/123.jpg")), Some(123));
+        assert_eq!(frame_number(Path::new("frames/007.jpg")), Some(7));
+    }
+
+    #[test]
+    fn frame_number_rejects_other_names() {
+        assert_eq!(frame_number(Path::new("frames/00_01_23_456.jpg")), None);
+        assert_eq!(frame_number(Path::new("frames/frame1.jpg")), None);
+        assert_eq!(frame_number(Path::new("frames/-1.jpg")), None);
+    }
+
+    /// A small JPEG, 8 pixels wide.
+    fn small_jpeg() -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::new_rgb8(8, 4)
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new(&mut data))
+            .unwrap();
+        data
+    }
+
+    fn original_use_with(values: &[(&str, &str)], transformed: bool) -> OriginalUse {
+        let config = Config::from_values(values).unwrap();
+        original_use(&small_jpeg(), "frame", transformed, &config).unwrap()
+    }
+
+    #[test]
+    fn original_within_limits_is_used_as_is() {
+        assert_eq!(original_use_with(&[], false), OriginalUse::AsIs);
+        assert_eq!(
+            original_use_with(&[("POST_WIDTH", "8")], false),
+            OriginalUse::AsIs
+        );
+    }
+
+    #[test]
+    fn original_over_recompress_above_is_recompressed_if_smaller() {
+        assert_eq!(
+            original_use_with(&[("RECOMPRESS_ABOVE", "10")], false),
+            OriginalUse::RecompressIfSmaller
+        );
+    }
+
+    #[test]
+    fn original_that_cant_be_posted_as_is_is_recompressed() {
+        assert_eq!(original_use_with(&[], true), OriginalUse::Recompress);
+        assert_eq!(
+            original_use_with(&[("MAX_JPEG_SIZE", "10")], false),
+            OriginalUse::Recompress
+        );
+        assert_eq!(
+            original_use_with(&[("POST_WIDTH", "16")], false),
+            OriginalUse::Recompress
+        );
+        assert_eq!(
+            original_use_with(&[("TARGET_MEGAPIXELS", "0.00001")], false),
+            OriginalUse::Recompress
+        );
+    }
+
+    #[test]
+    fn non_jpeg_original_is_recompressed() {
+        let mut png = Vec::new();
+        DynamicImage::new_rgb8(8, 4)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let config = Config::from_values(&[]).unwrap();
+        assert_eq!(
+            original_use(&png, "frame", false, &config).unwrap(),
+            OriginalUse::Recompress
+        );
+    }
+
+    #[test]
+    fn frame_with_timecode_overlay_is_recompressed() {
+        let config = Config::from_values(&[("OVERLAY_TIMECODE", "true"), ("FPS", "24")]).unwrap();
+        assert_eq!(
+            frame_original_use(1, small_jpeg(), &config).unwrap(),
+            OriginalUse::Recompress
+        );
+    }
+}
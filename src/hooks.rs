@@ -0,0 +1,88 @@
+//! User-defined shell commands run on posting lifecycle events.
+//!
+//! `ON_POST_SUCCESS`, `ON_POST_FAILURE`, and `ON_CYCLE_COMPLETE` each hold a
+//! command run through `sh -c` when the event happens. Details are passed in
+//! environment variables:
+//!
+//! - `HOOK_EVENT`: `post_success`, `post_failure`, or `cycle_complete`
+//! - `HOOK_FRAME`: the frame number, when there is one
+//! - `HOOK_POST_URI`: the AT URI of the created post, on success
+//! - `HOOK_ERROR`: the error message, on failure
+//! - `HOOK_SEVERITY`: `error`, or `critical` once failures reach `MAX_CONSECUTIVE_FAILURES`, on
+//!   failure
+//!
+//! Commands run in the background so a slow hook never delays posting, and a
+//! failing one is only logged. They run with the same user and privileges as
+//! the bot itself, so only configure commands you trust.
+
+use std::process::Stdio;
+
+use log::*;
+use tokio::process::Command;
+
+use crate::config::Config;
+
+/// Run the success hook for a frame that was just posted.
+pub fn post_succeeded(config: &Config, frame: u32, uri: Option<&str>) {
+    let mut vars = vec![("HOOK_FRAME", frame.to_string())];
+    if let Some(uri) = uri {
+        vars.push(("HOOK_POST_URI", uri.to_string()));
+    }
+
+    spawn(config.on_post_success.as_deref(), "post_success", vars);
+}
+
+/// Run the failure hook for a frame that couldn't be posted.
+pub fn post_failed(config: &Config, frame: Option<u32>, error: &anyhow::Error, critical: bool) {
+    let severity = if critical { "critical" } else { "error" };
+    let mut vars = vec![
+        ("HOOK_ERROR", format!("{:#}", error)),
+        ("HOOK_SEVERITY", severity.to_string()),
+    ];
+    if let Some(frame) = frame {
+        vars.push(("HOOK_FRAME", frame.to_string()));
+    }
+
+    spawn(config.on_post_failure.as_deref(), "post_failure", vars);
+}
+
+/// Run the cycle hook after the last frame of the movie was posted.
+pub fn cycle_completed(config: &Config) {
+    spawn(
+        config.on_cycle_complete.as_deref(),
+        "cycle_complete",
+        Vec::new(),
+    );
+}
+
+/// Start `command` in the background, logging if it fails.
+fn spawn(command: Option<&str>, event: &'static str, vars: Vec<(&'static str, String)>) {
+    let Some(command) = command else {
+        return;
+    };
+
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("HOOK_EVENT", event)
+        .envs(vars)
+        .stdin(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run {} hook: {}", event, e);
+            return;
+        }
+    };
+
+    debug!("Started {} hook", event);
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if status.success() => debug!("The {} hook finished", event),
+            Ok(status) => warn!("The {} hook exited with {}", event, status),
+            Err(e) => warn!("Failed to wait for the {} hook: {}", event, e),
+        }
+    });
+}
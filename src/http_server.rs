@@ -0,0 +1,202 @@
+//! Optional HTTP server for inspecting the bot while it runs.
+//!
+//! Enabled by setting `HTTP_ADDR`. Speaks just enough HTTP/1.1 to answer
+//! simple `GET` requests from a browser or `curl`, closing the connection after
+//! each response.
+//!
+//! `/next` returns the JPEG that will be posted on the next tick, processed
+//! exactly as it would be for posting. The frame counter is only read.
+//!
+//! `/feed.atom` returns the Atom feed of posted frames when `ATOM_FEED_FILE` is
+//! set.
+
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use log::*;
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+};
+
+use crate::{
+    config::{
+        Config,
+        FRAME_DATA_FILE,
+    },
+    frame_info::FrameInfo,
+    frame_processing::get_frame_as_jpeg,
+    reload::{
+        self,
+        SharedConfig,
+    },
+    stage_timings::StageTimings,
+};
+
+/// Maximum size of a request head before the request is rejected.
+const MAX_REQUEST_HEAD_BYTES: usize = 8 * 1024;
+
+/// A response ready to be written to the client.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// A plain text response with the given status line.
+    fn text(status: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+}
+
+/// Listen on `addr` and serve requests until the process exits.
+pub async fn serve(addr: SocketAddr, config: SharedConfig) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind HTTP server to {}", addr))?;
+    info!("HTTP server listening on http://{}", addr);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept HTTP connection: {}", e);
+                continue;
+            }
+        };
+
+        let config = reload::snapshot(&config);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &config).await {
+                debug!("HTTP connection from {} failed: {:#}", peer, e);
+            }
+        });
+    }
+}
+
+/// Read one request from the connection, respond to it, and close it.
+async fn handle_connection(mut stream: TcpStream, config: &Config) -> anyhow::Result<()> {
+    let response = match read_request_line(&mut stream).await? {
+        Some(request_line) => route(&request_line, config).await,
+        None => Response::text("400 Bad Request", "Malformed request\n"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read the request head and return its first line.
+///
+/// Returns `None` if the head is too large or the connection closes before
+/// the head is complete.
+async fn read_request_line(stream: &mut TcpStream) -> anyhow::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD_BYTES {
+            return Ok(None);
+        }
+
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    Ok(head.lines().next().map(str::to_string))
+}
+
+/// Pick the response for a request line like `GET /next HTTP/1.1`.
+async fn route(request_line: &str, config: &Config) -> Response {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Response::text("400 Bad Request", "Malformed request line\n");
+    };
+
+    if method != "GET" {
+        return Response::text("405 Method Not Allowed", "Only GET is supported\n");
+    }
+
+    // Query strings aren't used by any route
+    let path = target.split('?').next().unwrap_or(target);
+    match path {
+        "/next" => next_frame(config).await,
+        "/feed.atom" => atom_feed(config).await,
+        _ => Response::text("404 Not Found", "Not found\n"),
+    }
+}
+
+/// Process and return the frame that will be posted next.
+async fn next_frame(config: &Config) -> Response {
+    let result = async {
+        let frame = FrameInfo::peek_current_frame(FRAME_DATA_FILE)?;
+        debug!("Previewing frame {} over HTTP", frame);
+        let mut timings = StageTimings::new();
+        let processed = get_frame_as_jpeg(frame, config, &mut timings).await?;
+        timings.log(&format!("frame {} preview", frame));
+        anyhow::Ok(processed)
+    }
+    .await;
+
+    match result {
+        Ok(processed_frame) => Response {
+            status: "200 OK",
+            content_type: config.output_format.mime_type(),
+            body: processed_frame.jpeg_data,
+        },
+        Err(e) => {
+            warn!("Failed to preview next frame: {:#}", e);
+            Response::text(
+                "500 Internal Server Error",
+                format!("Failed to preview next frame: {:#}\n", e),
+            )
+        }
+    }
+}
+
+/// Return the Atom feed of posted frames, if one is kept.
+async fn atom_feed(config: &Config) -> Response {
+    let Some(feed_file) = &config.atom_feed_file else {
+        return Response::text("404 Not Found", "No Atom feed is configured\n");
+    };
+
+    match tokio::fs::read(feed_file).await {
+        Ok(body) => Response {
+            status: "200 OK",
+            content_type: "application/atom+xml; charset=utf-8",
+            body,
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Response::text("404 Not Found", "Nothing has been posted to the feed yet\n")
+        }
+        Err(e) => {
+            warn!("Failed to read Atom feed {}: {}", feed_file.display(), e);
+            Response::text(
+                "500 Internal Server Error",
+                format!("Failed to read Atom feed: {}\n", e),
+            )
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Title cards posted once at the start of each cycle through the movie.
+//!
+//! When the frame counter wraps back to frame 1, the images in `INTRO_DIR` are
+//! posted in file name order before the movie starts over. Progress through
+//! the cards is kept in its own file, which only exists while an intro is
+//! underway, so a restart partway through carries on from the next card.
+
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use anyhow::Context;
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Position within an intro that's in progress.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct IntroProgress {
+    /// Index of the next card to post, in file name order
+    pub next_card: usize,
+}
+
+impl IntroProgress {
+    /// Begin a new intro at the first card.
+    pub fn start(path: &Path) -> anyhow::Result<()> {
+        Self { next_card: 0 }.save(path)?;
+        info!("Starting a new cycle, intro cards will be posted first");
+        Ok(())
+    }
+
+    /// Load the intro progress, or `None` if no intro is underway.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read intro progress from {}", path.display())
+                });
+            }
+        };
+
+        toml::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse intro progress from {}", path.display()))
+    }
+
+    /// Move past the card just posted, finishing the intro after the last one.
+    pub fn advance(self, path: &Path, card_count: usize) -> anyhow::Result<()> {
+        let next_card = self.next_card + 1;
+        if next_card >= card_count {
+            info!("Intro finished, resuming the movie");
+            return Self::finish(path);
+        }
+
+        Self { next_card }.save(path)
+    }
+
+    /// End the intro so the next post is a frame.
+    pub fn finish(path: &Path) -> anyhow::Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to remove intro progress {}", path.display())),
+        }
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let toml_string =
+            toml::to_string_pretty(self).context("Failed to serialize intro progress")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create parent directories for {}", path.display())
+            })?;
+        }
+
+        fs::write(path, toml_string)
+            .with_context(|| format!("Failed to write intro progress to {}", path.display()))
+    }
+}
+
+/// List the intro cards in `dir`, sorted by file name.
+///
+/// Hidden files are ignored so stray editor or OS files aren't posted.
+pub fn list_cards(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read intro directory {}", dir.display()))?;
+
+    let mut cards = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+
+        if path.is_file() && !hidden {
+            cards.push(path);
+        }
+    }
+
+    cards.sort();
+    Ok(cards)
+}
@@ -0,0 +1,223 @@
+//! Low-level JPEG marker segment handling.
+//!
+//! Walks the marker segments that precede the compressed image data so metadata
+//! can be removed or added without decoding or re-encoding the image itself.
+
+use anyhow::{
+    bail,
+    Context,
+};
+
+/// Start of image marker.
+const SOI: u8 = 0xD8;
+/// End of image marker.
+const EOI: u8 = 0xD9;
+/// Start of scan marker, after which entropy-coded image data follows.
+const SOS: u8 = 0xDA;
+/// JFIF header, kept since decoders use it to interpret the image.
+const APP0: u8 = 0xE0;
+/// Adobe header, kept since it records the color transform of the image data.
+const APP14: u8 = 0xEE;
+/// Comment marker.
+const COM: u8 = 0xFE;
+
+/// Remove EXIF, XMP, ICC profiles, comments, and other application metadata.
+///
+/// Only the JFIF (APP0) and Adobe (APP14) headers are kept among the
+/// application segments. Dropping the ICC profile means clients render the
+/// image as sRGB, which normalizes color across clients at the cost of
+/// wide-gamut sources looking slightly different.
+///
+/// The EXIF orientation goes with the rest of the EXIF data, and the pixels
+/// aren't rotated to make up for it since that would mean re-encoding. A JPEG
+/// relying on it is posted the way it's stored rather than the way it's shown.
+/// Frames extracted from a movie don't carry an orientation, so this only
+/// matters for images taken by cameras.
+pub fn strip_metadata(jpeg_data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if jpeg_data.len() < 2 || jpeg_data[0] != 0xFF || jpeg_data[1] != SOI {
+        bail!("Data does not start with a JPEG SOI marker");
+    }
+
+    let mut output = Vec::with_capacity(jpeg_data.len());
+    output.extend_from_slice(&jpeg_data[..2]);
+    let mut position = 2;
+
+    loop {
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while jpeg_data.get(position) == Some(&0xFF) && jpeg_data.get(position + 1) == Some(&0xFF) {
+            position += 1;
+        }
+
+        let marker = match jpeg_data.get(position..position + 2) {
+            Some([0xFF, marker]) => *marker,
+            _ => bail!("Expected a JPEG marker at byte {}", position),
+        };
+
+        // Everything from the start of scan onwards is image data, copy it as-is
+        if marker == SOS || marker == EOI {
+            output.extend_from_slice(&jpeg_data[position..]);
+            return Ok(output);
+        }
+
+        // Restart and TEM markers have no length field
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            output.extend_from_slice(&jpeg_data[position..position + 2]);
+            position += 2;
+            continue;
+        }
+
+        let length = jpeg_data
+            .get(position + 2..position + 4)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+            .with_context(|| format!("Truncated JPEG segment at byte {}", position))?;
+        let end = position + 2 + length;
+        if length < 2 || end > jpeg_data.len() {
+            bail!("Invalid JPEG segment length at byte {}", position);
+        }
+
+        if !is_metadata(marker) {
+            output.extend_from_slice(&jpeg_data[position..end]);
+        }
+        position = end;
+    }
+}
+
+/// Add a comment segment holding `comment` to a JPEG.
+///
+/// The comment goes right after the JFIF header when there is one, since
+/// decoders expect that header to come first, or after the SOI marker
+/// otherwise.
+pub fn insert_comment(jpeg_data: &[u8], comment: &str) -> anyhow::Result<Vec<u8>> {
+    if jpeg_data.len() < 2 || jpeg_data[0] != 0xFF || jpeg_data[1] != SOI {
+        bail!("Data does not start with a JPEG SOI marker");
+    }
+
+    // The length field counts itself, and can't exceed 16 bits
+    let length = u16::try_from(comment.len() + 2).context("JPEG comment is too long")?;
+
+    let mut position = 2;
+    if let Some([0xFF, APP0, high, low]) = jpeg_data.get(2..6) {
+        position += 2 + u16::from_be_bytes([*high, *low]) as usize;
+        if position > jpeg_data.len() {
+            bail!("Invalid JPEG segment length at byte 2");
+        }
+    }
+
+    let mut output = Vec::with_capacity(jpeg_data.len() + 4 + comment.len());
+    output.extend_from_slice(&jpeg_data[..position]);
+    output.extend_from_slice(&[0xFF, COM]);
+    output.extend_from_slice(&length.to_be_bytes());
+    output.extend_from_slice(comment.as_bytes());
+    output.extend_from_slice(&jpeg_data[position..]);
+    Ok(output)
+}
+
+/// Whether a marker holds metadata that can be dropped without affecting decoding.
+fn is_metadata(marker: u8) -> bool {
+    let is_application = (0xE0..=0xEF).contains(&marker);
+    marker == COM || (is_application && marker != APP0 && marker != APP14)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A marker segment with a length field followed by `payload`.
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let length = (payload.len() + 2) as u16;
+        [&[0xFF, marker][..], &length.to_be_bytes(), payload].concat()
+    }
+
+    /// A JPEG made of `segments`, followed by a scan and the end of the image.
+    fn jpeg(segments: &[Vec<u8>]) -> Vec<u8> {
+        let scan = [
+            segment(SOS, &[1, 1, 0, 0, 63, 0]),
+            vec![0x12, 0xFF, 0x00, 0x34],
+        ];
+        [
+            vec![0xFF, SOI],
+            segments.concat(),
+            scan.concat(),
+            vec![0xFF, EOI],
+        ]
+        .concat()
+    }
+
+    fn jfif() -> Vec<u8> {
+        segment(APP0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0")
+    }
+
+    fn quantization_table() -> Vec<u8> {
+        segment(0xDB, &[0; 65])
+    }
+
+    #[test]
+    fn metadata_segments_are_dropped() {
+        let adobe = segment(APP14, b"Adobe\0\x64\0\0\0\0\x01");
+        let original = jpeg(&[
+            jfif(),
+            segment(0xE1, b"Exif\0\0MM\0\x2a"),
+            segment(0xE2, b"ICC_PROFILE\0\x01\x01"),
+            segment(COM, b"made with a camera"),
+            adobe.clone(),
+            quantization_table(),
+        ]);
+
+        let stripped = strip_metadata(&original).unwrap();
+        assert_eq!(stripped, jpeg(&[jfif(), adobe, quantization_table()]));
+    }
+
+    #[test]
+    fn jpeg_without_metadata_is_unchanged() {
+        let original = jpeg(&[jfif(), quantization_table()]);
+        assert_eq!(strip_metadata(&original).unwrap(), original);
+    }
+
+    #[test]
+    fn fill_bytes_before_markers_are_skipped() {
+        let original = [
+            vec![0xFF, SOI, 0xFF],
+            segment(COM, b"comment"),
+            vec![0xFF, 0xFF],
+            quantization_table(),
+            vec![0xFF, EOI],
+        ]
+        .concat();
+
+        let stripped = strip_metadata(&original).unwrap();
+        assert_eq!(
+            stripped,
+            [vec![0xFF, SOI], quantization_table(), vec![0xFF, EOI]].concat()
+        );
+    }
+
+    #[test]
+    fn malformed_jpegs_are_rejected() {
+        assert!(strip_metadata(b"\x89PNG").is_err());
+        assert!(strip_metadata(&[0xFF, SOI, 0x00, 0x01]).is_err());
+        // Segment claims more bytes than there are
+        assert!(strip_metadata(&[0xFF, SOI, 0xFF, COM, 0x00, 0x10, b'a']).is_err());
+        // Length too short to cover its own field
+        assert!(strip_metadata(&[0xFF, SOI, 0xFF, COM, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn comment_goes_after_the_jfif_header() {
+        let original = jpeg(&[jfif(), quantization_table()]);
+        let commented = insert_comment(&original, "frame 7/10").unwrap();
+        assert_eq!(
+            commented,
+            jpeg(&[jfif(), segment(COM, b"frame 7/10"), quantization_table()])
+        );
+    }
+
+    #[test]
+    fn comment_goes_after_soi_without_a_jfif_header() {
+        let original = jpeg(&[quantization_table()]);
+        let commented = insert_comment(&original, "frame 7/10").unwrap();
+        assert_eq!(
+            commented,
+            jpeg(&[segment(COM, b"frame 7/10"), quantization_table()])
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Short looping zoom videos made from a single frame.
+//!
+//! With `KEN_BURNS` enabled, each frame is posted to Bluesky as a few seconds
+//! of video that slowly zooms in towards the focal point and back out again,
+//! so the clip loops without a jump. The intermediate frames are cropped and
+//! scaled from the processed still and piped to the `ffmpeg` command-line tool
+//! for encoding to MP4. Only compiled with the `ken-burns` feature, since it
+//! needs ffmpeg installed on the host.
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    f64::consts::TAU,
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::{
+        Read,
+        Write,
+    },
+    path::PathBuf,
+    process::{
+        Command,
+        Stdio,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use image::{
+    imageops::FilterType,
+    DynamicImage,
+    GenericImageView,
+};
+use log::*;
+
+use crate::{
+    crop::FocalPoint,
+    error::FrameError,
+};
+
+/// Frame rate of the generated video.
+const VIDEO_FPS: u32 = 30;
+
+/// How often to check whether ffmpeg has finished.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How to animate frames posted as video.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KenBurns {
+    /// How far to zoom in at the middle of the clip, e.g. 1.2 for 20%
+    pub zoom: f64,
+    /// Length of the clip
+    pub duration: Duration,
+    /// Point to zoom in towards
+    pub focal_point: FocalPoint,
+}
+
+impl KenBurns {
+    /// Render `image_data` as an MP4 zooming towards the focal point.
+    ///
+    /// Returns the video along with its width and height, which are rounded
+    /// down to even numbers as the encoder requires. With a `timeout`, ffmpeg
+    /// is killed if the video isn't finished in time and the frame is reported
+    /// as timed out.
+    pub async fn render(
+        self,
+        image_data: Vec<u8>,
+        name: String,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+        tokio::task::spawn_blocking(move || self.render_blocking(&image_data, &name, timeout))
+            .await
+            .context("Video rendering panicked")?
+    }
+
+    fn render_blocking(
+        self,
+        image_data: &[u8],
+        name: &str,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+        let started = Instant::now();
+        let image = image::load_from_memory(image_data)
+            .with_context(|| format!("Failed to decode {} for video", name))?;
+        let (width, height) = image.dimensions();
+        let (width, height) = (width & !1, height & !1);
+        if width == 0 || height == 0 {
+            bail!("{} is too small to turn into video", name);
+        }
+
+        let frame_count = (self.duration.as_secs_f64() * VIDEO_FPS as f64).round() as u32;
+        debug!(
+            "Rendering {} as {} video frames at {}x{}",
+            name, frame_count, width, height
+        );
+
+        // ffmpeg can't seek in a pipe to write the MP4 index, so it goes via a file
+        let output = OutputFile::create()?;
+        let size = format!("{}x{}", width, height);
+        let fps = VIDEO_FPS.to_string();
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-nostdin", "-y"])
+            .args([
+                "-f", "rawvideo", "-pix_fmt", "rgb24", "-s", &size, "-r", &fps,
+            ])
+            .args(["-i", "pipe:0"])
+            .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+            .args(["-movflags", "+faststart"])
+            .arg(&output.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start ffmpeg")?;
+
+        // Frames are sent from their own thread so a stuck ffmpeg can't block
+        // the time limit from being checked
+        let mut stdin = child.stdin.take().context("Failed to open ffmpeg stdin")?;
+        let writer = thread::spawn(move || {
+            for index in 0..frame_count {
+                let zoom = self.zoom_at(index, frame_count);
+                let frame = zoomed_frame(&image, zoom, self.focal_point, width, height);
+                if let Err(e) = stdin.write_all(frame.to_rgb8().as_raw()) {
+                    // ffmpeg stopping early is reported with its own error below
+                    debug!("Failed to send video frame to ffmpeg: {}", e);
+                    break;
+                }
+            }
+        });
+
+        let status = loop {
+            if let Some(status) = child.try_wait().context("Failed to wait for ffmpeg")? {
+                break status;
+            }
+            if let Some(timeout) = timeout
+                && started.elapsed() >= timeout
+            {
+                warn!(
+                    "Killing ffmpeg after {} seconds rendering {}",
+                    timeout.as_secs(),
+                    name
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(FrameError::ProcessingTimedOut {
+                    image: name.to_string(),
+                    seconds: timeout.as_secs(),
+                }
+                .into());
+            }
+            thread::sleep(EXIT_POLL_INTERVAL);
+        };
+        let _ = writer.join();
+
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            bail!("ffmpeg exited with {}: {}", status, stderr.trim());
+        }
+
+        let video = fs::read(&output.path).context("Failed to read video written by ffmpeg")?;
+        Ok((video, width, height))
+    }
+
+    /// Zoom for frame `index` of `frame_count`, easing from none up to the
+    /// configured zoom halfway through and back, so the clip loops smoothly.
+    fn zoom_at(&self, index: u32, frame_count: u32) -> f64 {
+        let progress = index as f64 / frame_count as f64;
+        1.0 + (self.zoom - 1.0) * (1.0 - (progress * TAU).cos()) / 2.0
+    }
+}
+
+/// Crop `image` to `1 / zoom` of its size around the focal point, kept inside
+/// the frame, and scale it back up to `width`x`height`.
+fn zoomed_frame(
+    image: &DynamicImage,
+    zoom: f64,
+    focal_point: FocalPoint,
+    width: u32,
+    height: u32,
+) -> DynamicImage {
+    let (source_width, source_height) = image.dimensions();
+    let crop_width = ((source_width as f64 / zoom).round() as u32).clamp(1, source_width);
+    let crop_height = ((source_height as f64 / zoom).round() as u32).clamp(1, source_height);
+    let (x, y) = focal_point.origin(source_width, source_height, crop_width, crop_height);
+
+    image
+        .crop_imm(x, y, crop_width, crop_height)
+        .resize_exact(width, height, FilterType::Triangle)
+}
+
+/// Temporary file for ffmpeg to write the video to, removed when dropped.
+///
+/// The name is random and the file is created exclusively, so nothing already
+/// at that path in the shared temporary directory can be written through.
+struct OutputFile {
+    path: PathBuf,
+}
+
+impl OutputFile {
+    fn create() -> anyhow::Result<Self> {
+        let mut bytes = [0; 16];
+        openssl::rand::rand_bytes(&mut bytes).context("Failed to generate video file name")?;
+        let hex = bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let path = std::env::temp_dir().join(format!("every-frame-{}.mp4", hex));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
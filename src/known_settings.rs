@@ -0,0 +1,454 @@
+//! Every setting the bot reads, for the `print-env` command.
+//!
+//! Settings are looked up by name in `Config::load`, and in debug builds
+//! reading a name that isn't listed here panics, so the list can't quietly
+//! fall behind the settings that are actually read.
+
+/// A setting read from the environment or config file.
+#[derive(Debug, Clone, Copy)]
+pub struct KnownSetting {
+    pub name: &'static str,
+    /// Value used when unset, described in words when it isn't a plain value
+    pub default: Option<&'static str>,
+    /// Whether the bot refuses to start without it
+    pub required: bool,
+    /// Cargo feature the bot must be built with for the setting to be read
+    pub feature: Option<&'static str>,
+    pub description: &'static str,
+}
+
+impl KnownSetting {
+    const fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            default: None,
+            required: false,
+            feature: None,
+            description,
+        }
+    }
+
+    const fn default(self, default: &'static str) -> Self {
+        Self {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    const fn required(self) -> Self {
+        Self {
+            required: true,
+            ..self
+        }
+    }
+
+    const fn feature(self, feature: &'static str) -> Self {
+        Self {
+            feature: Some(feature),
+            ..self
+        }
+    }
+}
+
+/// All settings, grouped roughly by what they affect.
+pub const KNOWN_SETTINGS: &[KnownSetting] = &[
+    KnownSetting::new(
+        "CONFIG_FILE",
+        "TOML file to read settings from, under the same names",
+    ),
+    // Account and backend
+    KnownSetting::new("MOVIE_NAME", "Name of the movie, used in alt text").required(),
+    KnownSetting::new("BACKEND", "Where to post frames: bluesky or discord").default("bluesky"),
+    KnownSetting::new(
+        "BLUESKY_IDENTIFIER",
+        "Handle or email of the Bluesky account, needed when posting to Bluesky",
+    )
+    .required()
+    .feature("bluesky"),
+    KnownSetting::new(
+        "BLUESKY_APP_PASSWORD",
+        "App password for the Bluesky account, needed when posting to Bluesky",
+    )
+    .required()
+    .feature("bluesky"),
+    KnownSetting::new(
+        "BLUESKY_FALLBACK_APP_PASSWORDS",
+        "Comma-separated app passwords to fall back on when logging in fails",
+    )
+    .feature("bluesky"),
+    KnownSetting::new(
+        "DISCORD_WEBHOOK_URL",
+        "Webhook to post frames to, needed with BACKEND=discord",
+    )
+    .feature("discord"),
+    // Scheduling
+    KnownSetting::new("POST_IMMEDIATELY", "Post a frame as soon as the bot starts")
+        .default("false"),
+    KnownSetting::new(
+        "STARTUP_DELAY_SECONDS",
+        "Seconds to wait on startup before doing anything",
+    )
+    .default("0"),
+    KnownSetting::new(
+        "POST_INTERVAL",
+        "Time between posts, in POST_INTERVAL_UNITs",
+    )
+    .default("1800"),
+    KnownSetting::new(
+        "POST_INTERVAL_UNIT",
+        "Unit of POST_INTERVAL: seconds, minutes, or hours",
+    )
+    .default("seconds"),
+    KnownSetting::new(
+        "POST_CRON",
+        "Cron schedule to post on instead of a fixed interval",
+    ),
+    KnownSetting::new(
+        "SCHEDULE_WINDOWS",
+        "Times of day with their own interval, like day=08:00-20:00/15m,night=20:00-08:00/1h",
+    ),
+    KnownSetting::new(
+        "POST_DAYS",
+        "Days of the week to post on, like mon,tue,wed,thu,fri",
+    )
+    .default("every day"),
+    KnownSetting::new(
+        "SHOTS_FILE",
+        "Shot list to pace posts by instead of a fixed interval",
+    ),
+    KnownSetting::new(
+        "SHOT_TIME_SCALE",
+        "Real seconds each frame is held per second of shot",
+    )
+    .default("60"),
+    KnownSetting::new(
+        "DRY_ADVANCE",
+        "Only advance the frame counter on each tick, without posting",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "PREWARM_NEXT_FRAME",
+        "Process the next frame in the background after each post",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "ANALYZE_FRAMES",
+        "Log the luminance histogram and entropy of each recompressed frame",
+    )
+    .default("false"),
+    // Frames and progress
+    KnownSetting::new(
+        "FRAME_SOURCE",
+        "Where to load frames from: local, or a base URL",
+    )
+    .default("local"),
+    KnownSetting::new(
+        "FRAME_NAME_FORMAT",
+        "How frame files are named: number or timecode",
+    )
+    .default("number"),
+    KnownSetting::new("FRAME_STEP", "Frames to advance after each post").default("1"),
+    KnownSetting::new(
+        "MAX_FRAME_REPEATS",
+        "Times in a row the same frame may be posted before it's skipped",
+    ),
+    KnownSetting::new(
+        "SCENES_FILE",
+        "Scene list to post the movie in shuffled scene order",
+    ),
+    KnownSetting::new("FPS", "Frame rate of the movie, for timecodes"),
+    KnownSetting::new(
+        "FILM_START",
+        "RFC 3339 time to date posts from by their position in the film",
+    )
+    .feature("bluesky"),
+    KnownSetting::new(
+        "ON_OUT_OF_RANGE",
+        "What to do when the saved frame is past the end: error, clamp, or reset",
+    )
+    .default("error"),
+    KnownSetting::new(
+        "RECOVER_CORRUPT_STATE",
+        "Back up and recreate a frame data file that can't be read",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "VERIFY_CHECKSUMS",
+        "Check frames against the checksum manifest before posting",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "USE_PRECOMPRESSED",
+        "Post frames made by the precompress command when available",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "INTRO_DIR",
+        "Directory of title cards to post before each new cycle",
+    )
+    .feature("bluesky"),
+    // Image processing
+    KnownSetting::new("OUTPUT_FORMAT", "Image format to post: jpeg or avif").default("jpeg"),
+    KnownSetting::new("JPEG_ENCODER", "JPEG encoder to use: image or mozjpeg").default("image"),
+    KnownSetting::new(
+        "PROGRESSIVE_JPEG",
+        "Post progressive rather than baseline JPEGs, with JPEG_ENCODER=mozjpeg",
+    )
+    .default("false")
+    .feature("mozjpeg"),
+    KnownSetting::new(
+        "MAX_JPEG_SIZE",
+        "Largest image to post in bytes, capped at 1000000",
+    )
+    .default("1000000"),
+    KnownSetting::new(
+        "RECOMPRESS_ABOVE",
+        "Size in bytes above which originals are recompressed anyway",
+    )
+    .default("MAX_JPEG_SIZE"),
+    KnownSetting::new(
+        "ACCEPTABLE_QUALITY_FLOOR",
+        "Lowest JPEG quality to use before downscaling instead",
+    ),
+    KnownSetting::new(
+        "DOWNSCALE_STEP",
+        "Scale applied to the frame each time it's downscaled",
+    )
+    .default("0.9"),
+    KnownSetting::new(
+        "SSIM_TARGET",
+        "Minimum SSIM a recompressed frame must keep versus its source",
+    ),
+    KnownSetting::new(
+        "MAX_PIXELS",
+        "Largest decoded pixel count before a frame is rejected",
+    ),
+    KnownSetting::new(
+        "SKIP_BLACK_FRAMES",
+        "Skip frames darker than BLACK_FRAME_THRESHOLD instead of posting them",
+    )
+    .default("false"),
+    KnownSetting::new(
+        "BLACK_FRAME_THRESHOLD",
+        "Mean luminance out of 255 below which a frame counts as black",
+    )
+    .default("10"),
+    KnownSetting::new(
+        "TARGET_MEGAPIXELS",
+        "Megapixels to downscale frames to before compressing",
+    ),
+    KnownSetting::new(
+        "POST_WIDTH",
+        "Width in pixels to scale every frame to, up or down, before compressing",
+    )
+    .default("the frame's own width"),
+    KnownSetting::new(
+        "MAX_ASPECT_DIMENSION",
+        "Largest width or height to declare in a post's aspect ratio",
+    )
+    .feature("bluesky"),
+    KnownSetting::new(
+        "TILE_MODE",
+        "Grid like 2x1 to split each frame into for a multi-image post",
+    ),
+    KnownSetting::new(
+        "CROP_ASPECT",
+        "Aspect ratio like 4:5 to crop frames to, square with only FOCAL_POINT",
+    ),
+    KnownSetting::new(
+        "FOCAL_POINT",
+        "Point like 0.5,0.3 to center crops on, from the top left",
+    )
+    .default("0.5,0.5"),
+    KnownSetting::new(
+        "KEN_BURNS",
+        "Post frames to Bluesky as short looping videos zooming towards FOCAL_POINT",
+    )
+    .default("false")
+    .feature("ken-burns"),
+    KnownSetting::new(
+        "KEN_BURNS_ZOOM",
+        "How far KEN_BURNS videos zoom in, up to 4",
+    )
+    .default("1.2")
+    .feature("ken-burns"),
+    KnownSetting::new(
+        "KEN_BURNS_DURATION_SECONDS",
+        "Length of KEN_BURNS videos, up to 60",
+    )
+    .default("6")
+    .feature("ken-burns"),
+    KnownSetting::new(
+        "COMPRESSION_TIMEOUT_SECONDS",
+        "Time limit for processing a frame",
+    ),
+    KnownSetting::new(
+        "STRIP_METADATA",
+        "Strip EXIF, XMP, ICC profiles and comments from posted JPEGs",
+    )
+    .default("true"),
+    KnownSetting::new(
+        "EMBED_METADATA",
+        "Embed the movie name and frame number in posted JPEGs as a comment",
+    )
+    .default("false"),
+    KnownSetting::new("OVERLAY_TIMECODE", "Burn the timecode into posted frames").default("false"),
+    KnownSetting::new(
+        "OVERLAY_POSITION",
+        "Where to draw the timecode, like top-left or bottom",
+    )
+    .default("bottom-right"),
+    KnownSetting::new("OVERLAY_COLOR", "Hex color of the timecode").default("#ffffff"),
+    KnownSetting::new("OVERLAY_FONT_SIZE", "Height of the timecode in pixels")
+        .default("scaled to the frame"),
+    // Post text and alt text
+    KnownSetting::new(
+        "ALT_TEXT_TEMPLATE",
+        "Template for alt text, with {movie}, {frame}, {total} and {color}",
+    )
+    .default("the built-in wording"),
+    KnownSetting::new(
+        "ALT_TEXT_TEMPLATE_SECONDARY",
+        "Template for a second-language alt text appended to the first",
+    ),
+    KnownSetting::new(
+        "ALT_TEXT_SEPARATOR",
+        "Text between the primary and secondary alt text",
+    )
+    .default("a blank line")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "ALT_TEXT_FILE",
+        "File of hand-written alt text for individual frames",
+    )
+    .feature("bluesky"),
+    KnownSetting::new("POST_PREFIX", "Text to start every post with").feature("bluesky"),
+    KnownSetting::new(
+        "FINALE_COUNTDOWN",
+        "Posts from the end to start counting down the frames left to post",
+    )
+    .feature("bluesky"),
+    KnownSetting::new("TAG_FRAME_NUMBER", "Tag each post with its frame number")
+        .default("false")
+        .feature("bluesky"),
+    KnownSetting::new(
+        "FRAME_TAG_PADDING",
+        "Digits to zero-pad the frame number tag to",
+    )
+    .default("5")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "POST_LANGS",
+        "Comma-separated language tags to declare on posts",
+    )
+    .feature("bluesky"),
+    // Replies and embeds
+    KnownSetting::new(
+        "REPLY_ROOT_URI",
+        "AT URI of a post for the first scheduled post to reply under",
+    )
+    .feature("bluesky"),
+    KnownSetting::new("REPLY_ROOT_CID", "CID of the REPLY_ROOT_URI post").feature("bluesky"),
+    KnownSetting::new(
+        "QUOTE_ROOT_URI",
+        "AT URI of a post for every frame post to quote",
+    )
+    .feature("bluesky"),
+    KnownSetting::new("QUOTE_ROOT_CID", "CID of the QUOTE_ROOT_URI post").feature("bluesky"),
+    KnownSetting::new(
+        "LINK_CARD_URL",
+        "Post frames as a link card to this URL, with the frame as its thumbnail",
+    )
+    .feature("bluesky"),
+    KnownSetting::new("LINK_CARD_TITLE", "Title of the link card")
+        .default("MOVIE_NAME")
+        .feature("bluesky"),
+    KnownSetting::new(
+        "LINK_CARD_DESCRIPTION",
+        "Template for the link card description, with {movie}, {frame}, and {total}",
+    )
+    .default("Frame {frame} of {total}")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "REPLY_GATE",
+        "Who may reply: nobody, or a list of mentioned, following, and followers",
+    )
+    .feature("bluesky"),
+    // Reliability
+    KnownSetting::new(
+        "VERIFY_POSTED",
+        "Confirm each post appears in the feed before advancing",
+    )
+    .default("false")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "UPLOAD_RETRIES",
+        "Times to retry a blob upload that failed in transit",
+    )
+    .default("2")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "INTER_UPLOAD_DELAY_MS",
+        "Milliseconds to wait between uploading each image of a post",
+    )
+    .default("0")
+    .feature("bluesky"),
+    KnownSetting::new(
+        "MAX_CONSECUTIVE_FAILURES",
+        "Failed posts in a row before failures are escalated",
+    ),
+    KnownSetting::new("EXIT_ON_MAX_FAILURES", "Exit once failures are escalated").default("false"),
+    KnownSetting::new(
+        "MIN_FREE_DISK_MB",
+        "Free space in MB below which archiving is skipped",
+    ),
+    // Hooks
+    KnownSetting::new("ON_POST_SUCCESS", "Shell command to run after each post"),
+    KnownSetting::new(
+        "ON_POST_FAILURE",
+        "Shell command to run when a post fails after all retries",
+    ),
+    KnownSetting::new(
+        "ON_CYCLE_COMPLETE",
+        "Shell command to run after the last frame of each cycle",
+    ),
+    // Outputs
+    KnownSetting::new(
+        "ARCHIVE_DIR",
+        "Directory to keep a copy of every posted image in",
+    ),
+    KnownSetting::new(
+        "ARCHIVE_MAX_FILES",
+        "Archived images to keep before pruning the oldest",
+    ),
+    KnownSetting::new(
+        "AUDIT_LOG_FILE",
+        "File to append a JSON line to for every post",
+    )
+    .feature("bluesky"),
+    KnownSetting::new(
+        "ATOM_FEED_FILE",
+        "File to keep an Atom feed of posted frames in",
+    ),
+    KnownSetting::new("ATOM_FEED_MAX_ENTRIES", "Posts to keep in the Atom feed")
+        .default("50")
+        .feature("bluesky"),
+    KnownSetting::new(
+        "POST_INDEX_FILE",
+        "File to keep the URI and CID of each frame's post in",
+    )
+    .feature("bluesky"),
+    KnownSetting::new(
+        "QUALITY_SUMMARY_FILE",
+        "File to also write the quality summary to on shutdown",
+    ),
+    KnownSetting::new(
+        "HTTP_ADDR",
+        "Address to serve the HTTP preview server on, like 127.0.0.1:8080",
+    ),
+];
+
+/// Whether `name` is a listed setting.
+pub fn is_known(name: &str) -> bool {
+    KNOWN_SETTINGS.iter().any(|setting| setting.name == name)
+}
@@ -0,0 +1,92 @@
+//! Local "dump to disk" mode used when built without the `bluesky` feature.
+//!
+//! Runs the same frame pipeline as the Bluesky poster, but writes each processed
+//! JPEG to a directory instead of posting it. Handy for working on frame
+//! processing without compiling or talking to the Bluesky SDK.
+
+use std::{
+    path::Path,
+    time::Instant,
+};
+
+use log::*;
+
+use crate::{
+    archive,
+    config::{
+        Config,
+        DUMP_DIR,
+    },
+    failures,
+    frame_processing::{
+        get_frame_images,
+        load_total_frame_count,
+    },
+    hooks,
+    posting,
+    run_stats,
+    stage_timings::StageTimings,
+};
+
+/// Dump the next frame to disk, logging any failure.
+pub async fn post_frame_task(config: &Config) {
+    match dump_frame(config).await {
+        Ok(()) => failures::record_success(),
+        Err(e) => {
+            error!("Failed to dump frame: {:#}", e);
+            failures::record_failure(config, &e);
+        }
+    }
+}
+
+/// Process the current frame, write it to the dump directory, and advance.
+///
+/// Output goes to `ARCHIVE_DIR` when set, otherwise to the default dump
+/// directory, using the same naming and pruning as the post archive.
+async fn dump_frame(config: &Config) -> anyhow::Result<()> {
+    let mut timings = StageTimings::new();
+    let total_frames = load_total_frame_count(config).await?;
+    let mut frame_info = posting::load_frame_info(config, total_frames)?;
+
+    if posting::skip_repeated_frame(&mut frame_info, config)? {
+        return Ok(());
+    }
+    let frame = frame_info.current_frame;
+    let processed_images = get_frame_images(frame, config, &mut timings).await;
+    let Some(processed_images) =
+        posting::skip_unpostable_frame(processed_images, &mut frame_info, config)?
+    else {
+        return Ok(());
+    };
+
+    let dump_dir = config.archive_dir.as_deref().unwrap_or(Path::new(DUMP_DIR));
+    let (jpeg_images, qualities): (Vec<_>, Vec<_>) = processed_images
+        .into_iter()
+        .map(|processed_image| (processed_image.jpeg_data, processed_image.quality_used))
+        .unzip();
+    let started = Instant::now();
+    archive::archive_frame(
+        dump_dir,
+        frame,
+        &jpeg_images,
+        config.output_format.extension(),
+        config.archive_max_files,
+    )
+    .await?;
+    timings.record("write", started);
+
+    frame_info.record_posted();
+    posting::advance_frame(&mut frame_info, config)?;
+    run_stats::record_post();
+    qualities.into_iter().for_each(run_stats::record_quality);
+    timings.log(&format!("frame {}", frame));
+    hooks::post_succeeded(config, frame, None);
+
+    info!(
+        "Dumped frame {}/{} to {}",
+        frame,
+        total_frames,
+        dump_dir.display()
+    );
+    Ok(())
+}
@@ -3,78 +3,537 @@
 //! This bot reads JXL frames from a directory, converts them to JPEG with
 //! automatic quality adjustment, and posts them to Bluesky on a schedule.
 //! Frame progress is tracked to avoid duplicate posts.
+//!
+//! Building without the default `bluesky` feature drops the Bluesky SDK and
+//! dumps processed frames to disk instead, for working on the frame pipeline.
 
+#[cfg(feature = "bluesky")]
+mod alt_text;
+mod archive;
+#[cfg(feature = "bluesky")]
+mod atom_feed;
+#[cfg(feature = "bluesky")]
+mod audit_log;
+#[cfg(feature = "bluesky")]
+mod blob_cache;
+#[cfg(feature = "bluesky")]
 mod bluesky;
+mod checksum;
+mod commands;
 mod config;
+#[cfg(feature = "bluesky")]
+mod credentials;
+mod crop;
+#[cfg(feature = "discord")]
+mod discord;
+mod disk_space;
 mod error;
+mod failures;
+mod filmstrip;
+mod frame_analysis;
 mod frame_info;
 mod frame_processing;
+mod hooks;
+mod http_server;
+#[cfg(feature = "bluesky")]
+mod intro;
+mod jpeg_segments;
+#[cfg(feature = "ken-burns")]
+mod ken_burns;
+mod known_settings;
+#[cfg(not(feature = "bluesky"))]
+mod local;
+#[cfg(feature = "mozjpeg")]
+mod mozjpeg;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod overlay;
+#[cfg(feature = "bluesky")]
+mod pending_post;
+mod post_days;
+#[cfg(feature = "bluesky")]
+mod post_index;
+mod posting;
+mod precompressed;
+mod prewarm;
+mod reload;
+#[cfg(feature = "remote-frames")]
+mod remote_frames;
+mod run_stats;
+mod scenes;
+mod schedule;
+mod schedule_windows;
+#[cfg(test)]
+mod scratch_dir;
+#[cfg(feature = "bluesky")]
+mod setup;
+mod shots;
+mod ssim;
+mod stage_timings;
+mod template;
+mod tiles;
+mod timecode;
+
+use std::{
+    path::Path,
+    sync::{
+        Arc,
+        RwLock,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
 
-use anyhow::bail;
+use anyhow::{
+    bail,
+    Context,
+};
+use chrono::Datelike;
 use log::*;
+use tokio::sync::{
+    Mutex,
+    Notify,
+};
 use tokio_schedule::{
     every,
     Job,
 };
 
+#[cfg(feature = "bluesky")]
+use crate::bluesky::post_frame_task;
+#[cfg(not(feature = "bluesky"))]
+use crate::local::post_frame_task;
 use crate::{
-    bluesky::post_frame_task,
     config::{
+        Backend,
         Config,
-        POST_INTERVAL_SECONDS,
+        FrameSource,
+        IntervalUnit,
+        FRAME_DATA_FILE,
+        FRAME_DATA_LOCK_FILE,
+    },
+    frame_info::{
+        CounterLock,
+        FrameInfo,
     },
+    reload::SharedConfig,
+    schedule::CronSchedule,
+    schedule_windows::ScheduleWindows,
+    shots::ShotList,
 };
 
+/// Agent authenticated on startup, reused for posting immediately.
+#[cfg(feature = "bluesky")]
+type StartupAgent = bsky_sdk::BskyAgent;
+/// Nothing is authenticated on startup without Bluesky support.
+#[cfg(not(feature = "bluesky"))]
+type StartupAgent = std::convert::Infallible;
+
+/// Held while a scheduled post is in progress, so posts never overlap.
+static POST_LOCK: Mutex<()> = Mutex::const_new(());
+
 /// Entry point - starts the frame posting bot.
 ///
-/// Loads configuration from environment variables, authenticates with Bluesky,
-/// and starts the posting loop. Runs indefinitely until interrupted.
+/// Loads configuration from the environment and config file, authenticates
+/// with Bluesky, and starts the posting loop. Runs indefinitely until
+/// interrupted. If a command name is given on the command line, runs that
+/// command instead.
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
+    run_stats::start();
     init_logging();
     dotenvy::dotenv().ok();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some((command, command_args)) = args.split_first() {
+        return run_command(command, command_args).await;
+    }
+
+    let config = Config::load()?;
+
     // Check that the frames directory exists and has at least one frame.
     let frames_dir = config::FRAMES_DIR;
-    if !std::path::Path::new(frames_dir).exists() {
+    if config.frame_source == FrameSource::Local && !Path::new(frames_dir).exists() {
         bail!("Frames directory '{}' does not exist", frames_dir);
     }
 
-    let frame_count = frame_processing::get_total_frame_count().await?;
+    let frame_count = frame_processing::load_total_frame_count(&config).await?;
     if frame_count == 0 {
-        bail!("No frames found in directory '{}'", frames_dir);
+        bail!("No frames found in {}", config.frame_source);
+    }
+    if let Some(scenes) = &config.scenes {
+        scenes.check_frame_count(frame_count)?;
+    }
+
+    // Give dependencies like a network proxy or mounted storage time to come up
+    if !config.startup_delay.is_zero() {
+        info!(
+            "Waiting {} seconds before starting",
+            config.startup_delay.as_secs()
+        );
+        tokio::time::sleep(config.startup_delay).await;
     }
 
-    let config = Config::from_env()?;
-    bluesky::initialize_agent(&config).await?;
+    let startup_agent = if config.dry_advance {
+        info!("DRY_ADVANCE is set, only the frame counter will advance");
+        None
+    } else {
+        log_in(&config).await?
+    };
 
     info!(
         "Starting frame posting bot for movie: {}",
         config.movie_name
     );
 
-    let movie_name = config.movie_name.clone();
+    let http_addr = config.http_addr;
+    let post_immediately = config.post_immediately;
+    let config: SharedConfig = Arc::new(RwLock::new(config));
+    let reloaded = Arc::new(Notify::new());
+    #[cfg(unix)]
+    reload::spawn_reload_handler(config.clone(), reloaded.clone())?;
 
-    if config.post_immediately {
+    if let Some(addr) = http_addr {
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = http_server::serve(addr, config).await {
+                error!("HTTP server stopped: {:#}", e);
+            }
+        });
+    }
+
+    if post_immediately {
         info!("Posting frames immediately on startup");
-        post_frame_task(&movie_name).await;
-    } else {
-        info!("Will post frames every {} seconds", POST_INTERVAL_SECONDS);
+        post_with_agent(&config, startup_agent).await;
+    }
+
+    // Restart the scheduler whenever the config is reloaded, since the interval
+    // or schedule may have changed
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let current = reload::snapshot(&config);
+        tokio::select! {
+            result = run_scheduler(&current, &config) => return result,
+            () = reloaded.notified() => info!("Restarting scheduler with the reloaded config"),
+            result = &mut shutdown => {
+                result?;
+                shut_down(&reload::snapshot(&config)).await;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Wait for Ctrl+C, or `SIGTERM` on Unix as sent by `docker stop`.
+async fn shutdown_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{
+            signal,
+            SignalKind,
+        };
+
+        let mut terminate =
+            signal(SignalKind::terminate()).context("Failed to listen for SIGTERM")?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.context("Failed to listen for Ctrl+C")?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .context("Failed to listen for Ctrl+C")?;
+
+    Ok(())
+}
+
+/// Let any post in progress finish, then log the quality summary for this run.
+///
+/// The summary is also written to `QUALITY_SUMMARY_FILE` when it's set.
+async fn shut_down(config: &Config) {
+    info!("Shutting down once any post in progress has finished");
+    let _guard = POST_LOCK.lock().await;
+
+    let summary = run_stats::quality_summary();
+    for line in &summary {
+        info!("{}", line);
+    }
+
+    if let Some(path) = &config.quality_summary_file
+        && let Err(e) = std::fs::write(path, summary.join("\n") + "\n")
+    {
+        warn!(
+            "Failed to write quality summary to {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Post frames on the schedule set by the given config until an error occurs.
+///
+/// Each post reads the latest shared config, but the schedule itself stays as
+/// given until the scheduler is restarted.
+async fn run_scheduler(current: &Config, config: &SharedConfig) -> anyhow::Result<()> {
+    if let Some(schedule) = &current.post_cron {
+        return run_cron_schedule(schedule, config).await;
+    }
+
+    if let Some(shots) = &current.shots {
+        return run_shot_paced(shots, current.post_interval_duration(), config).await;
+    }
+
+    if let Some(windows) = &current.schedule_windows {
+        return run_windowed(windows, current.post_interval_duration(), config).await;
+    }
+
+    info!(
+        "Will post frames every {} {}",
+        current.post_interval, current.post_interval_unit
+    );
+    let interval = every(current.post_interval);
+    let config = config.clone();
+    let task = move || {
+        let config = config.clone();
+        async move {
+            scheduled_post(&config).await;
+        }
+    };
+
+    match current.post_interval_unit {
+        IntervalUnit::Seconds => interval.seconds().perform(task).await,
+        IntervalUnit::Minutes => interval.minutes().perform(task).await,
+        IntervalUnit::Hours => interval.hours().perform(task).await,
+    }
+
+    Ok(())
+}
+
+/// Post frames at the times matched by a cron schedule.
+///
+/// Sleeps until the next matching time before each post, recomputing it after
+/// every post so slow posts never cause a scheduled time to fire twice.
+async fn run_cron_schedule(schedule: &CronSchedule, config: &SharedConfig) -> anyhow::Result<()> {
+    loop {
+        let now = chrono::Local::now();
+        let next = schedule
+            .next_after(now)
+            .context("POST_CRON never matches a future time")?;
+
+        info!(
+            "Next post scheduled for {}",
+            next.format("%Y-%m-%d %H:%M %Z")
+        );
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+        scheduled_post(config).await;
     }
+}
 
-    every(POST_INTERVAL_SECONDS)
-        .seconds()
-        .perform(move || {
-            let movie_name = movie_name.clone();
-            async move {
-                post_frame_task(&movie_name).await;
+/// Post frames at the interval of the schedule window the current time is in.
+///
+/// The interval is looked up again whenever a window starts or ends, so a
+/// shorter interval takes effect as soon as its window begins, counting from
+/// the last post. Outside all windows, posts are `interval` apart.
+async fn run_windowed(
+    windows: &ScheduleWindows,
+    interval: Duration,
+    config: &SharedConfig,
+) -> anyhow::Result<()> {
+    let mut last_post = Instant::now();
+    let mut active_window = None;
+    loop {
+        let now = chrono::Local::now().time();
+        let window = windows.active(now);
+        let window_name = window.map(|window| window.name.as_str());
+        let interval = window.map_or(interval, |window| window.interval);
+        // Log the interval whenever it changes to another window's
+        if active_window.as_ref().map(Option::as_deref) != Some(window_name) {
+            match window_name {
+                Some(name) => info!(
+                    "In the {} schedule window, posting every {} seconds",
+                    name,
+                    interval.as_secs()
+                ),
+                None => info!(
+                    "Outside all schedule windows, posting every {} seconds",
+                    interval.as_secs()
+                ),
             }
-        })
-        .await;
+            active_window = Some(window_name.map(str::to_string));
+        }
+
+        let until_due = interval.saturating_sub(last_post.elapsed());
+        if until_due.is_zero() {
+            last_post = Instant::now();
+            scheduled_post(config).await;
+            continue;
+        }
+
+        tokio::time::sleep(until_due.min(windows.until_next_boundary(now))).await;
+    }
+}
 
+/// Post frames paced by the length of the shot each frame belongs to.
+///
+/// Before each post, waits for as long as the upcoming frame's shot says to
+/// hold it, falling back to the regular interval for frames outside any shot.
+async fn run_shot_paced(
+    shots: &ShotList,
+    interval: Duration,
+    config: &SharedConfig,
+) -> anyhow::Result<()> {
+    loop {
+        let frame = FrameInfo::peek_current_frame(FRAME_DATA_FILE)?;
+        let delay = shots.hold_for(frame).unwrap_or(interval);
+
+        info!(
+            "Posting frame {} in {} seconds",
+            frame,
+            delay.as_secs_f64().round()
+        );
+        tokio::time::sleep(delay).await;
+        scheduled_post(config).await;
+    }
+}
+
+/// Post the next frame using the latest config, then log stats for this run.
+async fn scheduled_post(config: &SharedConfig) {
+    post_with_agent(config, None).await;
+}
+
+/// Post the next frame like `scheduled_post`, with `agent` when given instead
+/// of resuming the saved session.
+///
+/// Nothing is posted on days excluded by `POST_DAYS`, or while another
+/// instance holds the frame counter's lock. The post runs in its own task
+/// while holding the post lock, so restarting the scheduler after a reload
+/// can't cut a post off halfway or start a second one alongside it.
+#[cfg_attr(not(feature = "bluesky"), allow(unused_variables))]
+async fn post_with_agent(config: &SharedConfig, agent: Option<StartupAgent>) {
+    let config = config.clone();
+    let post = tokio::spawn(async move {
+        let _guard = POST_LOCK.lock().await;
+        let config = reload::snapshot(&config);
+        let today = chrono::Local::now().weekday();
+        if config
+            .post_days
+            .as_ref()
+            .is_some_and(|days| !days.includes(today))
+        {
+            info!("Not posting on {}, it isn't one of POST_DAYS", today);
+            return;
+        }
+        // Another process sharing the counter would otherwise post the same frame
+        let _counter_lock = match CounterLock::try_acquire(FRAME_DATA_LOCK_FILE) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => {
+                warn!(
+                    "Another instance is posting from {}, skipping this tick",
+                    FRAME_DATA_FILE
+                );
+                return;
+            }
+            Err(e) => {
+                error!("Failed to lock the frame counter: {:#}", e);
+                return;
+            }
+        };
+        disk_space::warn_if_low(&config, Path::new(FRAME_DATA_FILE));
+        if config.dry_advance {
+            if let Err(e) = dry_advance(&config).await {
+                error!("Failed to advance frame counter: {:#}", e);
+            }
+            return;
+        }
+
+        match config.backend {
+            #[cfg(feature = "bluesky")]
+            Backend::Bluesky => match &agent {
+                Some(agent) => bluesky::post_frame_task_with_agent(&config, agent).await,
+                None => post_frame_task(&config).await,
+            },
+            #[cfg(not(feature = "bluesky"))]
+            Backend::Bluesky => post_frame_task(&config).await,
+            #[cfg(feature = "discord")]
+            Backend::Discord => discord::post_frame_task(&config).await,
+        }
+        run_stats::log_summary();
+
+        if config.prewarm_next_frame {
+            prewarm::spawn(config);
+        }
+    });
+
+    if let Err(e) = post.await {
+        error!("Posting task failed: {}", e);
+    }
+}
+
+/// Authenticate with Bluesky when posting there, so bad credentials stop the
+/// bot on startup rather than failing every post.
+#[cfg(feature = "bluesky")]
+async fn log_in(config: &Config) -> anyhow::Result<Option<StartupAgent>> {
+    if config.backend != Backend::Bluesky {
+        return Ok(None);
+    }
+    bluesky::initialize_agent(config).await.map(Some)
+}
+
+/// Without Bluesky support there's nothing to authenticate with.
+#[cfg(not(feature = "bluesky"))]
+async fn log_in(_config: &Config) -> anyhow::Result<Option<StartupAgent>> {
+    Ok(None)
+}
+
+/// Advance the frame counter as a post would, without processing or posting
+/// the frame.
+async fn dry_advance(config: &Config) -> anyhow::Result<()> {
+    let total_frames = frame_processing::load_total_frame_count(config).await?;
+    let mut frame_info = posting::load_frame_info(config, total_frames)?;
+
+    info!(
+        "Dry advance past frame {}/{}",
+        frame_info.current_frame, total_frames
+    );
+    let wrapped =
+        frame_info.increment(FRAME_DATA_FILE, config.frame_step, config.scenes.as_ref())?;
+    if wrapped {
+        info!("Reached the end of the movie, starting a new cycle");
+    }
     Ok(())
 }
 
+/// Run a one-off command by name with its remaining arguments.
+async fn run_command(command: &str, args: &[String]) -> anyhow::Result<()> {
+    match command {
+        "list-frames" => commands::list_frames().await,
+        "generate-manifest" => commands::generate_manifest().await,
+        "reset" => commands::reset(args).await,
+        "precompress" => commands::precompress().await,
+        "verify-frames" => commands::verify_frames().await,
+        "filmstrip" => commands::filmstrip(args).await,
+        "print-env" => {
+            commands::print_env();
+            Ok(())
+        }
+        #[cfg(feature = "bluesky")]
+        "post" => commands::post(args).await,
+        #[cfg(feature = "bluesky")]
+        "announce" => commands::announce(args).await,
+        #[cfg(feature = "bluesky")]
+        "curate" => commands::curate(args).await,
+        #[cfg(feature = "bluesky")]
+        "self-test" => commands::self_test().await,
+        #[cfg(feature = "bluesky")]
+        "setup" => setup::run().await,
+        _ => bail!("Unknown command '{}'", command),
+    }
+}
+
 /// Set up logging with appropriate levels.
 fn init_logging() {
     use env_logger::{
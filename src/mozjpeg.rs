@@ -0,0 +1,104 @@
+//! JPEG encoding with mozjpeg, for smaller files at the same quality.
+//!
+//! With `JPEG_ENCODER=mozjpeg`, frames are encoded by piping them to mozjpeg's
+//! `cjpeg` command-line tool instead of using the built-in encoder. Its
+//! optimized Huffman tables make noticeably smaller files, so frames fit the
+//! size limit at a higher quality, at the cost of slower encoding. With
+//! `PROGRESSIVE_JPEG` enabled it writes progressive scans too, which are
+//! usually smaller still and load in gradually. Only compiled with the
+//! `mozjpeg` feature, since it needs mozjpeg's `cjpeg` installed on the host.
+
+use std::{
+    io::{
+        self,
+        Write,
+    },
+    process::{
+        Command,
+        Stdio,
+    },
+};
+
+use anyhow::Context;
+use image::DynamicImage;
+use log::*;
+use thiserror::Error;
+
+/// Why `cjpeg` couldn't encode an image.
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    /// cjpeg couldn't be run or talked to, which would fail every frame alike
+    #[error("Failed to run cjpeg: {0}")]
+    Run(#[source] io::Error),
+    /// cjpeg ran but exited unsuccessfully on this particular image
+    #[error("{0}")]
+    Exited(String),
+}
+
+/// Check that `cjpeg` can be run, so a missing install is reported at startup
+/// rather than as every frame failing to encode.
+pub fn check_available() -> anyhow::Result<()> {
+    Command::new("cjpeg")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .output()
+        .context("JPEG_ENCODER=mozjpeg needs mozjpeg's cjpeg installed and on the PATH")?;
+    Ok(())
+}
+
+/// Encode `image` as a JPEG at `quality` with `cjpeg`, progressive or baseline.
+///
+/// Only `cjpeg` exiting unsuccessfully is down to the image itself, and
+/// reported as [`EncodeError::Exited`] so callers can skip it like any other
+/// frame that fails to encode.
+pub fn encode(
+    image: &DynamicImage,
+    quality: u8,
+    progressive: bool,
+    name: &str,
+) -> Result<Vec<u8>, EncodeError> {
+    let rgb_image = image.to_rgb8();
+    let mut ppm = format!("P6\n{} {}\n255\n", rgb_image.width(), rgb_image.height()).into_bytes();
+    ppm.extend_from_slice(rgb_image.as_raw());
+
+    let mut command = Command::new("cjpeg");
+    command.args(["-quality", &quality.to_string(), "-optimize"]);
+    // cjpeg defaults to progressive output, so baseline has to be asked for
+    command.arg(if progressive {
+        "-progressive"
+    } else {
+        "-baseline"
+    });
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(EncodeError::Run)?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| EncodeError::Run(io::Error::other("cjpeg has no stdin")))?;
+    // Feed the image from another thread so a full output pipe can't stall cjpeg
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            if let Err(e) = stdin.write_all(&ppm) {
+                // cjpeg stopping early is reported with its own error below
+                debug!("Failed to send {} to cjpeg: {}", name, e);
+            }
+        });
+        child.wait_with_output()
+    })
+    .map_err(EncodeError::Run)?;
+
+    if !output.status.success() {
+        return Err(EncodeError::Exited(format!(
+            "cjpeg exited with {} encoding {}: {}",
+            output.status,
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
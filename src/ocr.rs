@@ -0,0 +1,118 @@
+//! Optional OCR of on-screen text for richer alt text.
+//!
+//! Pipes the posted JPEG through the `tesseract` command-line tool and returns any
+//! recognized text (subtitles, signs) collapsed onto a single line. Only compiled
+//! with the `ocr` feature, since it needs tesseract installed on the host.
+
+use std::process::Stdio;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use log::*;
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::config::OCR_MAX_GRAPHEMES;
+
+/// Append any on-screen text found in the frame to the alt text.
+///
+/// OCR failures are logged and the original alt text is returned unchanged,
+/// since a missing transcription should never block a post.
+pub async fn append_on_screen_text(alt_text: String, jpeg_data: &[u8]) -> String {
+    match extract_text(jpeg_data).await {
+        Ok(Some(text)) => {
+            debug!("OCR found on-screen text: {}", text);
+            format!(
+                "{alt_text}. On-screen text: {}",
+                truncate_graphemes(&text, OCR_MAX_GRAPHEMES)
+            )
+        }
+        Ok(None) => alt_text,
+        Err(e) => {
+            warn!("OCR failed, posting without on-screen text: {:#}", e);
+            alt_text
+        }
+    }
+}
+
+/// Run tesseract over JPEG data and return the recognized text, if any.
+async fn extract_text(jpeg_data: &[u8]) -> anyhow::Result<Option<String>> {
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to start tesseract")?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to open tesseract stdin")?;
+    stdin
+        .write_all(jpeg_data)
+        .await
+        .context("Failed to send frame to tesseract")?;
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("Failed to read tesseract output")?;
+    if !output.status.success() {
+        bail!("tesseract exited with {}", output.status);
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok((!text.is_empty()).then_some(text))
+}
+
+/// Truncate text to at most `max_graphemes` graphemes, marking the cut with an
+/// ellipsis.
+///
+/// Cutting between graphemes rather than characters keeps emoji and combined
+/// characters, common in non-Latin subtitles, from being split in half.
+fn truncate_graphemes(text: &str, max_graphemes: usize) -> String {
+    match text.grapheme_indices(true).nth(max_graphemes) {
+        Some(_) => {
+            let end = text
+                .grapheme_indices(true)
+                .nth(max_graphemes.saturating_sub(1))
+                .map_or(0, |(end, _)| end);
+            format!("{}…", &text[..end])
+        }
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_kept_whole() {
+        assert_eq!(
+            truncate_graphemes("となりのトトロ 🌳", 10),
+            "となりのトトロ 🌳"
+        );
+    }
+
+    #[test]
+    fn long_text_is_cut_between_graphemes_with_an_ellipsis() {
+        // The flag is two characters and the accent is a separate combining mark
+        let text = "となりのトトロ 🇯🇵 cafe\u{301}s";
+        assert_eq!(truncate_graphemes(text, 10), "となりのトトロ 🇯🇵…");
+        // Dropped along with its accent rather than left as a bare e
+        assert_eq!(truncate_graphemes(text, 14), "となりのトトロ 🇯🇵 caf…");
+    }
+}
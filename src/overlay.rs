@@ -0,0 +1,185 @@
+//! Timecode burned into the pixels of posted frames.
+//!
+//! Unlike alt text, the timecode survives reposts and screenshots. Text is
+//! drawn with a small built-in bitmap font covering just the characters a
+//! timecode needs, set on a darkened box so it stays readable over bright
+//! scenes. The font is scaled with the frame unless `OVERLAY_FONT_SIZE` sets a
+//! fixed size.
+
+use std::str::FromStr;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use image::{
+    Rgb,
+    RgbImage,
+};
+
+/// Width of a glyph in font pixels.
+const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in font pixels.
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Glyph bitmaps for `0`-`9` and `:`, one row per byte with the leftmost
+/// pixel in the highest of the low five bits.
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT as usize]); 11] = [
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+];
+
+/// Where in the frame the timecode is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlayPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+impl FromStr for OverlayPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(position: &str) -> anyhow::Result<Self> {
+        match position {
+            "top-left" => Ok(Self::TopLeft),
+            "top" => Ok(Self::Top),
+            "top-right" => Ok(Self::TopRight),
+            "bottom-left" => Ok(Self::BottomLeft),
+            "bottom" => Ok(Self::Bottom),
+            "bottom-right" => Ok(Self::BottomRight),
+            _ => bail!("Expected top-left, top, top-right, bottom-left, bottom, or bottom-right"),
+        }
+    }
+}
+
+/// Text color, parsed from a hex code like `#ffcc00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayColor(pub Rgb<u8>);
+
+impl Default for OverlayColor {
+    fn default() -> Self {
+        Self(Rgb([255, 255, 255]))
+    }
+}
+
+impl FromStr for OverlayColor {
+    type Err = anyhow::Error;
+
+    fn from_str(color: &str) -> anyhow::Result<Self> {
+        let hex = color.strip_prefix('#').unwrap_or(color);
+        if hex.len() != 6 || !hex.is_ascii() {
+            bail!("Expected a hex color like #ffcc00");
+        }
+
+        let channel = |index: usize| {
+            u8::from_str_radix(&hex[index..index + 2], 16)
+                .context("Expected a hex color like #ffcc00")
+        };
+        Ok(Self(Rgb([channel(0)?, channel(2)?, channel(4)?])))
+    }
+}
+
+/// How to draw the timecode onto frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimecodeOverlay {
+    /// Corner to draw the timecode in
+    pub position: OverlayPosition,
+    /// Color of the text
+    pub color: OverlayColor,
+    /// Height of the text in pixels, or `None` to scale it with the frame
+    pub font_size: Option<u32>,
+}
+
+impl TimecodeOverlay {
+    /// Draw `text` at the configured position in `image`.
+    ///
+    /// Characters without a glyph are left blank. Text that doesn't fit in
+    /// the image is clipped.
+    pub fn draw(&self, image: &mut RgbImage, text: &str) {
+        let scale = match self.font_size {
+            // The bitmap font only scales by whole pixels
+            Some(font_size) => (font_size + GLYPH_HEIGHT / 2) / GLYPH_HEIGHT,
+            // Roughly 1/25th of the frame height
+            None => image.height() / (GLYPH_HEIGHT * 25),
+        }
+        .max(1);
+        let advance = (GLYPH_WIDTH + 1) * scale;
+        let text_width = (text.chars().count() as u32 * advance).saturating_sub(scale);
+        let text_height = GLYPH_HEIGHT * scale;
+        let padding = 2 * scale;
+        let margin = 4 * scale;
+
+        let box_width = text_width + 2 * padding;
+        let box_height = text_height + 2 * padding;
+        let left = match self.position {
+            OverlayPosition::TopLeft | OverlayPosition::BottomLeft => margin,
+            OverlayPosition::Top | OverlayPosition::Bottom => {
+                image.width().saturating_sub(box_width) / 2
+            }
+            OverlayPosition::TopRight | OverlayPosition::BottomRight => {
+                image.width().saturating_sub(box_width + margin)
+            }
+        };
+        let top = match self.position {
+            OverlayPosition::TopLeft | OverlayPosition::Top | OverlayPosition::TopRight => margin,
+            _ => image.height().saturating_sub(box_height + margin),
+        };
+
+        darken(image, left, top, box_width, box_height);
+
+        for (index, character) in text.chars().enumerate() {
+            let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == character) else {
+                continue;
+            };
+
+            let glyph_left = left + padding + index as u32 * advance;
+            for (row, bits) in (0..).zip(rows) {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        fill(
+                            image,
+                            glyph_left + column * scale,
+                            top + padding + row * scale,
+                            scale,
+                            self.color.0,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Halve the brightness of a rectangle, clipped to the image.
+fn darken(image: &mut RgbImage, left: u32, top: u32, width: u32, height: u32) {
+    for y in top..(top + height).min(image.height()) {
+        for x in left..(left + width).min(image.width()) {
+            let pixel = image.get_pixel_mut(x, y);
+            pixel.0 = pixel.0.map(|channel| channel / 2);
+        }
+    }
+}
+
+/// Fill a `size` by `size` square with a color, clipped to the image.
+fn fill(image: &mut RgbImage, left: u32, top: u32, size: u32, color: Rgb<u8>) {
+    for y in top..(top + size).min(image.height()) {
+        for x in left..(left + size).min(image.width()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Persistence for a frame that was uploaded but not yet posted.
+//!
+//! Uploading the blob and creating the post record are separate requests. If the
+//! bot is interrupted between the two, the blob reference saved here lets the next
+//! attempt skip straight to creating the record instead of processing and
+//! uploading the frame again. Once the record is created, it's saved here too
+//! until the frame counter has advanced, so a failed verification or a restart
+//! carries on with the same post instead of posting the frame a second time.
+
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+
+use anyhow::Context;
+use bsky_sdk::api::types::{
+    string::Cid,
+    BlobRef,
+};
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    frame_info::write_atomically,
+    frame_processing::FrameDimensions,
+};
+
+/// An uploaded frame waiting for its post record to be created.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PendingPost {
+    /// Frame number the blobs were uploaded for
+    pub frame: u32,
+    /// Uploaded images, more than one when the frame was split into tiles
+    pub images: Vec<UploadedImage>,
+    /// Post record already created for the frame, until the counter advances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<CreatedRecord>,
+}
+
+/// A post record that was created before the frame counter advanced.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreatedRecord {
+    /// AT URI of the post
+    pub uri: String,
+    /// CID of the post record
+    pub cid: Cid,
+}
+
+/// An uploaded image along with what's needed to embed it in a post.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UploadedImage {
+    /// Blob reference returned by the upload
+    pub blob: BlobRef,
+    /// Dimensions of the uploaded image
+    pub dimensions: FrameDimensions,
+    /// Alt text that was generated for the image
+    pub alt_text: String,
+    /// Whether the blob is a video made from the frame rather than an image
+    #[serde(default)]
+    pub video: bool,
+}
+
+impl PendingPost {
+    /// Load the pending post for `frame`, if one was saved.
+    ///
+    /// A pending post left over for a different frame is stale and ignored. An
+    /// unreadable file is logged and ignored too, since the worst case is simply
+    /// uploading the frame again.
+    pub fn load_for_frame<P: AsRef<Path>>(path: P, frame: u32) -> Option<Self> {
+        let path = path.as_ref();
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read pending post from {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        match serde_json::from_str::<Self>(&content) {
+            Ok(pending) if pending.frame == frame => Some(pending),
+            Ok(pending) => {
+                debug!(
+                    "Ignoring pending post for frame {} while on frame {}",
+                    pending.frame, frame
+                );
+                None
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to parse pending post from {}: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Save the pending post to disk.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize pending post")?;
+
+        // Written whole so an interrupted save can't leave a truncated file
+        write_atomically(path, json.as_bytes())
+            .with_context(|| format!("Failed to write pending post to {}", path.display()))?;
+
+        debug!("Saved pending post for frame {}", self.frame);
+        Ok(())
+    }
+
+    /// Remove the pending post file once the frame counter has advanced past its
+    /// post (or it can't be reused).
+    pub fn clear<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to remove pending post {}", path.display()))
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Posting only on certain days of the week.
+//!
+//! `POST_DAYS` lists the days posts are made on, like `mon,tue,wed,thu,fri`.
+//! Scheduled ticks on any other day are skipped without using up a frame, so
+//! whatever schedule is configured simply pauses until the next listed day.
+//! Days are evaluated in the host's local timezone.
+
+use std::str::FromStr;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use chrono::Weekday;
+
+/// Days of the week posts are made on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostDays {
+    days: Vec<Weekday>,
+}
+
+impl FromStr for PostDays {
+    type Err = anyhow::Error;
+
+    fn from_str(days: &str) -> anyhow::Result<Self> {
+        let days = days
+            .split(',')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .ok()
+                    .with_context(|| format!("Invalid day '{}', expected e.g. mon or monday", day))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if days.is_empty() {
+            bail!("No days given");
+        }
+
+        Ok(Self { days })
+    }
+}
+
+impl PostDays {
+    /// Whether posts are made on `day`.
+    pub fn includes(&self, day: Weekday) -> bool {
+        self.days.contains(&day)
+    }
+}
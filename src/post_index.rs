@@ -0,0 +1,80 @@
+//! Index of the post made for each frame, for linking back to them later.
+//!
+//! Enabled by setting `POST_INDEX_FILE`. After each posted frame its post's AT
+//! URI and CID are saved under the frame number, replacing any earlier post of
+//! the same frame, so a thread or gallery of past posts can be built from the
+//! file afterwards without searching the account's feed. Unlike the audit log,
+//! it holds a single entry per frame and is rewritten as a whole each time.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io,
+    path::Path,
+};
+
+use anyhow::Context;
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::frame_info::write_atomically;
+
+/// Posts by frame number.
+type PostIndex = BTreeMap<u32, IndexedPost>;
+
+/// A post of a frame as saved in the index.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct IndexedPost {
+    /// AT URI of the post
+    uri: String,
+    /// CID of the post record
+    cid: String,
+    /// When the post was created, in RFC 3339 format
+    posted_at: String,
+}
+
+/// Save the post just made for `frame` to the index at `path`.
+///
+/// An index that can't be read is left untouched rather than overwritten, so
+/// earlier entries aren't lost to a single bad read.
+pub fn record_post(path: &Path, frame: u32, uri: &str, cid: &str) -> anyhow::Result<()> {
+    let mut index = load(path)?;
+    index.insert(
+        frame,
+        IndexedPost {
+            uri: uri.to_string(),
+            cid: cid.to_string(),
+            posted_at: chrono::Utc::now().to_rfc3339(),
+        },
+    );
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| {
+            format!("Failed to create parent directories for {}", path.display())
+        })?;
+    }
+
+    let json = serde_json::to_string_pretty(&index).context("Failed to serialize post index")?;
+    write_atomically(path, json.as_bytes())
+        .with_context(|| format!("Failed to write post index {}", path.display()))?;
+
+    debug!("Saved frame {} to post index {}", frame, path.display());
+    Ok(())
+}
+
+/// Load the index, empty if it doesn't exist yet.
+fn load(path: &Path) -> anyhow::Result<PostIndex> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(PostIndex::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read post index {}", path.display()));
+        }
+    };
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse post index {}", path.display()))
+}
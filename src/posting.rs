@@ -0,0 +1,128 @@
+//! Steps shared by every way of posting the frame the counter points at.
+//!
+//! Whether a frame goes to Bluesky, Discord, or the dump directory, the frame
+//! counter is loaded and advanced the same way, frames repeated too often or
+//! that can never be posted are skipped, and posted frames are archived.
+
+#[cfg(feature = "bluesky")]
+use std::path::Path;
+
+use log::*;
+
+#[cfg(feature = "bluesky")]
+use crate::{
+    archive,
+    config::{
+        Backend,
+        INTRO_PROGRESS_FILE,
+    },
+    disk_space,
+    intro::IntroProgress,
+};
+use crate::{
+    config::{
+        Config,
+        FRAME_DATA_FILE,
+    },
+    error::FrameError,
+    frame_info::FrameInfo,
+    hooks,
+};
+
+/// Load the frame counter, starting from frame 1 if there isn't one yet.
+pub fn load_frame_info(config: &Config, total_frames: u32) -> anyhow::Result<FrameInfo> {
+    FrameInfo::load_or_create(
+        FRAME_DATA_FILE,
+        total_frames,
+        1,
+        config.recover_corrupt_state,
+        config.on_out_of_range,
+    )
+}
+
+/// Skip the current frame if it was already posted `MAX_FRAME_REPEATS` times
+/// in a row, returning whether it was skipped.
+pub fn skip_repeated_frame(frame_info: &mut FrameInfo, config: &Config) -> anyhow::Result<bool> {
+    if !frame_info.repeat_limit_reached(config.max_frame_repeats) {
+        return Ok(false);
+    }
+
+    warn!(
+        "Frame {} was already posted {} times in a row, skipping it",
+        frame_info.current_frame,
+        frame_info
+            .last_posted
+            .map_or(0, |last_posted| last_posted.times)
+    );
+    advance_frame(frame_info, config)?;
+    Ok(true)
+}
+
+/// Skip the current frame if preparing it failed in a way retrying won't fix.
+///
+/// Returns the prepared value, or `None` when the frame was skipped. Other
+/// errors are passed on to be retried.
+pub fn skip_unpostable_frame<T>(
+    prepared: anyhow::Result<T>,
+    frame_info: &mut FrameInfo,
+    config: &Config,
+) -> anyhow::Result<Option<T>> {
+    match prepared {
+        Ok(prepared) => Ok(Some(prepared)),
+        Err(e) if FrameError::is_skippable(&e) => {
+            error!("Skipping frame {}: {:#}", frame_info.current_frame, e);
+            advance_frame(frame_info, config)?;
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Advance the frame counter, running the cycle hook when a new cycle begins.
+///
+/// When posting to Bluesky, a new cycle also starts the intro cards if an
+/// intro directory is configured. Failing to start the intro is only logged,
+/// since the frame has already been posted by the time the counter advances.
+pub fn advance_frame(frame_info: &mut FrameInfo, config: &Config) -> anyhow::Result<()> {
+    let wrapped =
+        frame_info.increment(FRAME_DATA_FILE, config.frame_step, config.scenes.as_ref())?;
+    if !wrapped {
+        return Ok(());
+    }
+
+    hooks::cycle_completed(config);
+    #[cfg(feature = "bluesky")]
+    if config.backend == Backend::Bluesky
+        && config.intro_dir.is_some()
+        && let Err(e) = IntroProgress::start(Path::new(INTRO_PROGRESS_FILE))
+    {
+        warn!("{:#}", e);
+    }
+    Ok(())
+}
+
+/// Keep a copy of a posted frame's images in `ARCHIVE_DIR`, if configured.
+///
+/// Archiving is best-effort since the frame has already been posted, so
+/// failures are only logged.
+#[cfg(feature = "bluesky")]
+pub async fn archive_posted_frame(config: &Config, frame: u32, images: &[Vec<u8>]) {
+    let Some(archive_dir) = &config.archive_dir else {
+        return;
+    };
+    if !disk_space::has_room(config, archive_dir, "archiving") {
+        return;
+    }
+
+    if let Err(e) = archive::archive_frame(
+        archive_dir,
+        frame,
+        images,
+        config.output_format.extension(),
+        config.archive_max_files,
+    )
+    .await
+    {
+        warn!("Failed to archive frame {}: {:#}", frame, e);
+    }
+}
@@ -0,0 +1,115 @@
+//! Frames processed ahead of time by the `precompress` command.
+//!
+//! Each frame is written to [`PRECOMPRESSED_DIR`] as `<frame>.jpg`, or
+//! `<frame>.avif` for AVIF output, numbered by its position in the movie,
+//! exactly as it would be posted. An index records each frame's dimensions,
+//! the quality it ended up at, and its average color when a template uses it,
+//! so with `USE_PRECOMPRESSED` a frame can be posted without decoding it at
+//! all.
+//!
+//! The files reflect the settings at the time they were made, so `precompress`
+//! needs running again after changing the size limit, overlay, or other
+//! processing settings. Checksums are only verified while precompressing.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use anyhow::Context;
+use log::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    config::{
+        Config,
+        OutputFormat,
+        PRECOMPRESSED_DIR,
+        PRECOMPRESSED_INDEX_FILE,
+    },
+    frame_processing::{
+        FrameDimensions,
+        ProcessedFrame,
+    },
+};
+
+/// What's known about a precompressed frame without decoding it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PrecompressedFrame {
+    pub dimensions: FrameDimensions,
+    /// Quality the frame was recompressed at, or `None` for originals
+    pub quality: Option<u8>,
+    /// Average color, when a template used it while precompressing
+    pub average_color: Option<String>,
+}
+
+/// Precompressed frames keyed by frame number.
+pub type PrecompressedIndex = BTreeMap<u32, PrecompressedFrame>;
+
+/// Path of the precompressed image for `frame` in `format`.
+pub fn frame_path(frame: u32, format: OutputFormat) -> PathBuf {
+    Path::new(PRECOMPRESSED_DIR).join(format!("{}.{}", frame, format.extension()))
+}
+
+/// Load a precompressed frame, or `None` if it hasn't been precompressed or
+/// lacks something the current config needs.
+pub async fn load(frame: u32, config: &Config) -> anyhow::Result<Option<ProcessedFrame>> {
+    let index = load_index().await?;
+    let Some(entry) = index.get(&frame) else {
+        return Ok(None);
+    };
+    if config.wants_average_color() && entry.average_color.is_none() {
+        debug!(
+            "Precompressed frame {} has no average color, processing it instead",
+            frame
+        );
+        return Ok(None);
+    }
+
+    let path = frame_path(frame, config.output_format);
+    let jpeg_data = match tokio::fs::read(&path).await {
+        Ok(jpeg_data) => jpeg_data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    Ok(Some(ProcessedFrame {
+        jpeg_data,
+        dimensions: entry.dimensions.clone(),
+        quality_used: entry.quality,
+        #[cfg(feature = "bluesky")]
+        tile_position: None,
+        average_color: entry.average_color.clone(),
+    }))
+}
+
+/// Load the index, treating a missing one as empty.
+pub async fn load_index() -> anyhow::Result<PrecompressedIndex> {
+    let path = Path::new(PRECOMPRESSED_INDEX_FILE);
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Replace the index with `index`.
+pub async fn save_index(index: &PrecompressedIndex) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(index).context("Failed to serialize index")?;
+    tokio::fs::write(PRECOMPRESSED_INDEX_FILE, json)
+        .await
+        .with_context(|| format!("Failed to write {}", PRECOMPRESSED_INDEX_FILE))
+}
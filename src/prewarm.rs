@@ -0,0 +1,111 @@
+//! Processing the next frame ahead of time, between posts.
+//!
+//! With `PREWARM_NEXT_FRAME` enabled, the frame the counter points at is
+//! decoded and compressed in the background once a scheduled post finishes,
+//! so the next post can skip straight to uploading, or wait for it to finish
+//! instead of starting over. Only one frame is kept, keyed by its number, and
+//! it's only used when the counter still points at it by the time of the next
+//! post. Reloading the config discards it, since it was processed with the
+//! old settings.
+
+use std::sync::{
+    Mutex,
+    PoisonError,
+};
+
+use log::*;
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::{
+        Config,
+        FRAME_DATA_FILE,
+    },
+    frame_info::FrameInfo,
+    frame_processing::{
+        process_frame_images,
+        ProcessedFrame,
+    },
+    stage_timings::StageTimings,
+};
+
+/// The frame being processed ahead of time, if any.
+static PREWARMED: Mutex<Option<Prewarmed>> = Mutex::new(None);
+
+struct Prewarmed {
+    frame: u32,
+    /// Processing of the frame, resolving to `None` if it failed
+    task: JoinHandle<Option<Vec<ProcessedFrame>>>,
+}
+
+/// Start processing the frame the counter points at in the background.
+///
+/// Failures are only logged, the frame is processed again when it's posted.
+pub fn spawn(config: Config) {
+    let frame = match FrameInfo::peek_current_frame(FRAME_DATA_FILE) {
+        Ok(frame) => frame,
+        Err(e) => {
+            debug!("Not processing the next frame ahead of time: {:#}", e);
+            return;
+        }
+    };
+
+    let task = tokio::spawn(async move {
+        let mut timings = StageTimings::new();
+        match process_frame_images(frame, &config, &mut timings).await {
+            Ok(images) => {
+                debug!("Processed frame {} ahead of time", frame);
+                Some(images)
+            }
+            Err(e) => {
+                debug!("Failed to process frame {} ahead of time: {:#}", frame, e);
+                None
+            }
+        }
+    });
+
+    let previous = PREWARMED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Prewarmed { frame, task });
+    if let Some(previous) = previous {
+        previous.task.abort();
+    }
+}
+
+/// Take the images processed ahead of time for `frame`, if any.
+///
+/// Waits for them when they're still being processed. Images for any other
+/// frame are stale and discarded.
+pub async fn take(frame: u32) -> Option<Vec<ProcessedFrame>> {
+    let prewarmed = PREWARMED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()?;
+
+    if prewarmed.frame != frame {
+        debug!(
+            "Discarding frame {} processed ahead of time, posting frame {}",
+            prewarmed.frame, frame
+        );
+        prewarmed.task.abort();
+        return None;
+    }
+
+    if !prewarmed.task.is_finished() {
+        debug!("Waiting for frame {} being processed ahead of time", frame);
+    }
+    prewarmed.task.await.ok().flatten()
+}
+
+/// Discard any frame processed ahead of time, stopping it if it's still being
+/// processed.
+pub fn clear() {
+    let prewarmed = PREWARMED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(prewarmed) = prewarmed {
+        prewarmed.task.abort();
+    }
+}
@@ -0,0 +1,112 @@
+//! Reloading configuration while the bot is running.
+//!
+//! Sending the process `SIGHUP` re-reads `.env`, the environment, and the
+//! config file, then swaps the new configuration in for the next post.
+//! Settings that are only used at startup, like the Bluesky credentials and HTTP server address,
+//! keep their old values with a warning until the next restart. Variables removed from
+//! `.env` stay set in the process environment, so set them to a new value
+//! rather than deleting them.
+
+use std::sync::{
+    Arc,
+    PoisonError,
+    RwLock,
+};
+
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use log::*;
+#[cfg(unix)]
+use tokio::sync::Notify;
+
+use crate::config::Config;
+#[cfg(unix)]
+use crate::prewarm;
+
+/// Configuration shared between the scheduler, posts, and reload handler.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// Take a copy of the current configuration.
+///
+/// Posts work from a copy so a reload halfway through can't mix settings.
+pub fn snapshot(config: &SharedConfig) -> Config {
+    config
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Reload the configuration whenever the process receives `SIGHUP`.
+///
+/// `reloaded` is notified after each successful reload so the scheduler can
+/// pick up a changed interval or schedule.
+#[cfg(unix)]
+pub fn spawn_reload_handler(config: SharedConfig, reloaded: Arc<Notify>) -> anyhow::Result<()> {
+    use tokio::signal::unix::{
+        signal,
+        SignalKind,
+    };
+
+    let mut hangups = signal(SignalKind::hangup()).context("Failed to listen for SIGHUP")?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("Received SIGHUP, reloading config");
+            if reload(&config) {
+                reloaded.notify_one();
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Re-read the configuration and swap it in, returning whether it was applied.
+///
+/// An invalid configuration is logged and the current one kept.
+#[cfg(unix)]
+fn reload(config: &SharedConfig) -> bool {
+    dotenvy::dotenv_override().ok();
+
+    let mut new_config = match Config::load() {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            error!("Failed to reload config, keeping the current one: {:#}", e);
+            return false;
+        }
+    };
+
+    let mut current = config.write().unwrap_or_else(PoisonError::into_inner);
+
+    #[cfg(feature = "bluesky")]
+    if new_config.identifier != current.identifier
+        || new_config.app_password != current.app_password
+        || new_config.fallback_app_passwords != current.fallback_app_passwords
+    {
+        warn!("Bluesky credentials can't be changed without a restart, ignoring them");
+        new_config.identifier = current.identifier.clone();
+        new_config.app_password = current.app_password.clone();
+        new_config.fallback_app_passwords = current.fallback_app_passwords.clone();
+    }
+
+    if new_config.backend != current.backend {
+        warn!("BACKEND can't be changed without a restart, ignoring it");
+        new_config.backend = current.backend;
+    }
+
+    if new_config.frame_source != current.frame_source {
+        warn!("FRAME_SOURCE can't be changed without a restart, ignoring it");
+        new_config.frame_source = current.frame_source.clone();
+    }
+
+    if new_config.http_addr != current.http_addr {
+        warn!("HTTP_ADDR can't be changed without a restart, ignoring it");
+        new_config.http_addr = current.http_addr;
+    }
+
+    *current = new_config;
+    // Anything processed ahead of time used the old settings
+    prewarm::clear();
+    info!("Config reloaded");
+    true
+}
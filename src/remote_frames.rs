@@ -0,0 +1,69 @@
+//! Loading frames over HTTP instead of from the frames directory.
+//!
+//! Setting `FRAME_SOURCE` to a base URL fetches each frame from
+//! `<url>/<frame>.jpg`. Since directory listings usually aren't available, the
+//! frames are counted from a manifest at `<url>/frames.manifest`, in the same
+//! `sha256sum` format as the local checksum manifest, which is also what
+//! frames are verified against with `VERIFY_CHECKSUMS`. S3 and similar object
+//! storage work through a public or CDN endpoint, requests aren't signed.
+//!
+//! Only frames named by number are supported. The total frame count comes
+//! from the manifest everywhere it's needed, including commands like `post`,
+//! `reset`, and `precompress`. Commands that scan the frame files themselves,
+//! like `list-frames` and `generate-manifest`, still work on the local
+//! directory.
+
+use std::time::Duration;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use log::*;
+
+use crate::checksum;
+
+/// Name of the manifest listing the frames under the base URL.
+const MANIFEST_NAME: &str = "frames.manifest";
+
+/// How long to wait for a single download.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Count the frames listed in the manifest under `base_url`.
+pub async fn count_frames(base_url: &str) -> anyhow::Result<u32> {
+    let url = format!("{}/{}", base_url, MANIFEST_NAME);
+    let content = fetch(&url).await?;
+    let content = String::from_utf8(content)
+        .with_context(|| format!("Manifest {} isn't valid UTF-8", url))?;
+
+    let manifest = checksum::cache_manifest(&content, &url)?;
+    u32::try_from(manifest.len()).context("Too many frames in the manifest")
+}
+
+/// Download the frame file `file_name` from under `base_url`.
+pub async fn fetch_frame(base_url: &str, file_name: &str) -> anyhow::Result<Vec<u8>> {
+    let url = format!("{}/{}", base_url, file_name);
+    let data = fetch(&url).await?;
+    debug!("Downloaded {} bytes from {}", data.len(), url);
+    Ok(data)
+}
+
+async fn fetch(url: &str) -> anyhow::Result<Vec<u8>> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to request {}", url))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!("Failed to download {}: {}", url, status);
+    }
+
+    let data = response
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+    Ok(data.to_vec())
+}
@@ -0,0 +1,122 @@
+//! In-memory statistics for the current process.
+//!
+//! Unlike the persisted frame counter these reset on every start, which makes
+//! restart loops easy to spot: a long-running bot has a large uptime, while
+//! one that keeps crashing never gets past a few minutes.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Mutex,
+        OnceLock,
+        PoisonError,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use log::*;
+
+/// When this process started.
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// Number of frames posted since this process started.
+static POSTS_THIS_RUN: AtomicU64 = AtomicU64::new(0);
+
+/// Number of images posted at each JPEG quality, with `None` for originals.
+static QUALITIES: Mutex<BTreeMap<Option<u8>, u64>> = Mutex::new(BTreeMap::new());
+
+/// Width of the longest bar in the quality histogram.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// Record the process start time. Later calls have no effect.
+pub fn start() {
+    STARTED_AT.get_or_init(Instant::now);
+}
+
+/// Count a successful post.
+pub fn record_post() {
+    POSTS_THIS_RUN.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count the JPEG quality a posted image used, `None` if it was the original.
+pub fn record_quality(quality: Option<u8>) {
+    *QUALITIES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(quality)
+        .or_default() += 1;
+}
+
+/// Time since the process started.
+pub fn uptime() -> Duration {
+    STARTED_AT.get_or_init(Instant::now).elapsed()
+}
+
+/// Number of frames posted since the process started.
+pub fn posts_this_run() -> u64 {
+    POSTS_THIS_RUN.load(Ordering::Relaxed)
+}
+
+/// Log the uptime and number of posts made this run.
+pub fn log_summary() {
+    info!(
+        "Up for {}, {} posts this run",
+        format_duration(uptime()),
+        posts_this_run()
+    );
+}
+
+/// Summarize the JPEG qualities posted this run, one line at a time.
+///
+/// Starts with how many images were posted unchanged, followed by a histogram
+/// of the recompression qualities from highest to lowest.
+pub fn quality_summary() -> Vec<String> {
+    let qualities = QUALITIES.lock().unwrap_or_else(PoisonError::into_inner);
+    let total: u64 = qualities.values().sum();
+    let original = qualities.get(&None).copied().unwrap_or_default();
+
+    let mut lines = vec![format!(
+        "{} images posted this run, {} used the original and {} were recompressed",
+        total,
+        original,
+        total - original
+    )];
+
+    let recompressed: Vec<_> = qualities
+        .iter()
+        .rev()
+        .filter_map(|(quality, count)| Some((quality.as_ref()?, *count)))
+        .collect();
+    let most = recompressed
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(1);
+    for (quality, count) in recompressed {
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(most) as usize);
+        lines.push(format!("quality {:>3}: {:>5} {}", quality, count, bar));
+    }
+
+    lines
+}
+
+/// Format a duration as days, hours, and minutes, e.g. `2d 3h 15m`.
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
@@ -0,0 +1,129 @@
+//! Scene boundaries used to shuffle the order of scenes.
+//!
+//! A scenes file groups frames into scenes:
+//!
+//! ```toml
+//! [[scene]]
+//! start = 1
+//! end = 480
+//! ```
+//!
+//! Scenes are posted in a random order that's shuffled again every cycle,
+//! while the frames within each scene are still posted in sequence. Frames
+//! that aren't part of any scene are never posted.
+
+use std::path::Path;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use serde::Deserialize;
+
+/// A run of frames making up a single scene.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Scene {
+    /// First frame of the scene (inclusive)
+    pub start: u32,
+    /// Last frame of the scene (inclusive)
+    pub end: u32,
+}
+
+/// Layout of the scenes file.
+#[derive(Deserialize)]
+struct ScenesFile {
+    #[serde(default)]
+    scene: Vec<Scene>,
+}
+
+/// Scenes loaded from a scenes file, sorted by starting frame.
+#[derive(Debug, Clone)]
+pub struct SceneList {
+    scenes: Vec<Scene>,
+}
+
+impl SceneList {
+    /// Load and validate a scenes file.
+    ///
+    /// There must be at least one scene, each covering a valid 1-based frame
+    /// range, and scenes may not overlap.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenes file {}", path.display()))?;
+        let ScenesFile { scene: mut scenes } = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse scenes file {}", path.display()))?;
+
+        if scenes.is_empty() {
+            bail!("Scenes file {} has no scenes", path.display());
+        }
+        if let Some(scene) = scenes
+            .iter()
+            .find(|scene| scene.start == 0 || scene.start > scene.end)
+        {
+            bail!(
+                "Scene frames {}-{} are not a valid range",
+                scene.start,
+                scene.end
+            );
+        }
+
+        scenes.sort_by_key(|scene| scene.start);
+        if let Some(pair) = scenes.windows(2).find(|pair| pair[1].start <= pair[0].end) {
+            bail!(
+                "Scenes {}-{} and {}-{} overlap",
+                pair[0].start,
+                pair[0].end,
+                pair[1].start,
+                pair[1].end
+            );
+        }
+
+        Ok(Self { scenes })
+    }
+
+    /// Check every scene ends within the movie's `total_frames`.
+    ///
+    /// The file is loaded before the frames are counted, so this runs
+    /// separately once the count is known.
+    pub fn check_frame_count(&self, total_frames: u32) -> anyhow::Result<()> {
+        if let Some(scene) = self.scenes.iter().find(|scene| scene.end > total_frames) {
+            bail!(
+                "Scene frames {}-{} go past the last frame, the movie has {} frames",
+                scene.start,
+                scene.end,
+                total_frames
+            );
+        }
+        Ok(())
+    }
+
+    /// Number of scenes.
+    pub fn count(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// The scene at `index`, in file order.
+    pub fn get(&self, index: usize) -> Option<&Scene> {
+        self.scenes.get(index)
+    }
+
+    /// A random order to post the scenes in, as indexes into the list.
+    pub fn shuffled_order(&self) -> anyhow::Result<Vec<usize>> {
+        let mut order: Vec<usize> = (0..self.scenes.len()).collect();
+
+        // Fisher-Yates, the modulo bias is negligible for any realistic scene count
+        for i in (1..order.len()).rev() {
+            let j = (random_u64()? % (i as u64 + 1)) as usize;
+            order.swap(i, j);
+        }
+
+        Ok(order)
+    }
+}
+
+/// A random number from the system's secure generator.
+fn random_u64() -> anyhow::Result<u64> {
+    let mut bytes = [0u8; 8];
+    openssl::rand::rand_bytes(&mut bytes).context("Failed to generate a random number")?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,306 @@
+//! Cron-style schedules for posting at specific times.
+//!
+//! Supports the standard five fields (minute, hour, day of month, month, day of
+//! week) with `*`, numbers, ranges (`9-17`), lists (`0,30`), and steps (`*/15`,
+//! `9-17/2`). Day of week runs from 0 (Sunday) to 6, with 7 also meaning Sunday.
+//! Times are evaluated in the host's local timezone.
+
+use std::{
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use chrono::{
+    DateTime,
+    Datelike,
+    Duration,
+    Local,
+    NaiveDate,
+    TimeZone,
+    Timelike,
+};
+
+/// How far ahead to look for the next matching time before giving up.
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+/// A parsed cron expression.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day of month was restricted rather than starting with `*`
+    day_of_month_restricted: bool,
+    /// Whether day of week was restricted rather than starting with `*`
+    day_of_week_restricted: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> anyhow::Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            bail!(
+                "Cron expression '{}' must have 5 fields: minute hour day-of-month month day-of-week",
+                expression
+            );
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0..=7).context("Invalid day of week")?;
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0..=59).context("Invalid minute")?,
+            hours: parse_field(hour, 0..=23).context("Invalid hour")?,
+            days_of_month: parse_field(day_of_month, 1..=31).context("Invalid day of month")?,
+            months: parse_field(month, 1..=12).context("Invalid month")?,
+            days_of_week,
+            day_of_month_restricted: is_restricted(day_of_month),
+            day_of_week_restricted: is_restricted(day_of_week),
+        })
+    }
+}
+
+impl CronSchedule {
+    /// Find the first matching minute strictly after `after`.
+    ///
+    /// Returns `None` if nothing matches within the next few years, which only
+    /// happens for impossible dates like `0 0 31 2 *`.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(MAX_SEARCH_DAYS);
+        let mut candidate = start;
+
+        while candidate < limit {
+            if !self.matches_day(candidate.date()) {
+                candidate = candidate.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !has_bit(self.hours, candidate.hour()) {
+                candidate = candidate.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !has_bit(self.minutes, candidate.minute()) {
+                candidate += Duration::minutes(1);
+                continue;
+            }
+
+            // Skip times that don't exist locally, e.g. during a DST jump
+            if let Some(time) = Local.from_local_datetime(&candidate).earliest() {
+                return Some(time);
+            }
+            candidate += Duration::minutes(1);
+        }
+
+        None
+    }
+
+    /// Whether the schedule can fire on the given date.
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !has_bit(self.months, date.month()) {
+            return false;
+        }
+
+        let day_of_month = has_bit(self.days_of_month, date.day());
+        let day_of_week = has_bit(self.days_of_week, date.weekday().num_days_from_sunday());
+
+        // Standard cron: if both day fields are restricted, either may match
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+/// Whether a day field counts as restricted for the day of month or day of
+/// week rule.
+///
+/// Like standard cron, a field starting with `*` isn't, so `*/2` steps
+/// through the days without making the other day field optional.
+fn is_restricted(field: &str) -> bool {
+    !field.starts_with('*')
+}
+
+/// Parse one cron field into a bitset of allowed values.
+fn parse_field(field: &str, bounds: RangeInclusive<u32>) -> anyhow::Result<u64> {
+    let mut bits = 0u64;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("Invalid step '{}'", step))?;
+                if step == 0 {
+                    bail!("Step must be at least 1 in '{}'", item);
+                }
+                (range, step)
+            }
+            None => (item, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (*bounds.start(), *bounds.end()),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start, &bounds)?, parse_value(end, &bounds)?),
+                // A single value with a step runs to the end, like `5/15`
+                None if step > 1 => (parse_value(range, &bounds)?, *bounds.end()),
+                None => {
+                    let value = parse_value(range, &bounds)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start > end {
+            bail!("Range '{}' runs backwards", range);
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// Parse a single numeric cron value and check it's within bounds.
+fn parse_value(value: &str, bounds: &RangeInclusive<u32>) -> anyhow::Result<u32> {
+    let value: u32 = value
+        .parse()
+        .with_context(|| format!("Invalid value '{}'", value))?;
+    if !bounds.contains(&value) {
+        bail!(
+            "Value {} is outside {}-{}",
+            value,
+            bounds.start(),
+            bounds.end()
+        );
+    }
+    Ok(value)
+}
+
+/// Whether `value` is set in a bitset.
+fn has_bit(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .single()
+            .unwrap()
+    }
+
+    #[test]
+    fn ranges_steps_and_lists_set_the_matching_values() {
+        assert_eq!(parse_field("9-11", 0..=23).unwrap(), 0b111 << 9);
+        assert_eq!(parse_field("*/20", 0..=59).unwrap(), 1 | 1 << 20 | 1 << 40);
+        assert_eq!(
+            parse_field("9-17/4", 0..=23).unwrap(),
+            1 << 9 | 1 << 13 | 1 << 17
+        );
+        assert_eq!(parse_field("50/5", 0..=59).unwrap(), 1 << 50 | 1 << 55);
+        assert_eq!(
+            parse_field("0,30,45", 0..=59).unwrap(),
+            1 | 1 << 30 | 1 << 45
+        );
+        assert_eq!(
+            parse_field("1,10-12", 1..=31).unwrap(),
+            1 << 1 | 0b111 << 10
+        );
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        for field in ["60", "5-2", "*/0", "a", "1-", "", "*/x"] {
+            assert!(parse_field(field, 0..=59).is_err(), "{}", field);
+        }
+        assert!("0 9 * *".parse::<CronSchedule>().is_err());
+    }
+
+    #[test]
+    fn seven_is_also_sunday() {
+        let schedule: CronSchedule = "0 0 * * 7".parse().unwrap();
+        // 2023-01-01 was a Sunday
+        assert!(schedule.matches_day(date(2023, 1, 1)));
+        assert!(!schedule.matches_day(date(2023, 1, 2)));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 13th of the month or any Friday
+        let schedule: CronSchedule = "0 0 13 * 5".parse().unwrap();
+        assert!(schedule.matches_day(date(2023, 1, 13)));
+        assert!(schedule.matches_day(date(2023, 1, 6)));
+        assert!(!schedule.matches_day(date(2023, 1, 7)));
+    }
+
+    #[test]
+    fn wildcard_day_field_leaves_the_other_required() {
+        let schedule: CronSchedule = "0 0 * * 5".parse().unwrap();
+        assert!(schedule.matches_day(date(2023, 1, 6)));
+        assert!(!schedule.matches_day(date(2023, 1, 12)));
+    }
+
+    #[test]
+    fn stepped_wildcard_day_field_leaves_the_other_required() {
+        // Odd days of the month that are also Mondays, not either
+        let schedule: CronSchedule = "0 0 */2 * 1".parse().unwrap();
+        assert!(schedule.matches_day(date(2023, 1, 9)));
+        assert!(!schedule.matches_day(date(2023, 1, 2)));
+        assert!(!schedule.matches_day(date(2023, 1, 3)));
+
+        // The 1st of the month only when it's a Sunday, Tuesday, Thursday or Saturday
+        let schedule: CronSchedule = "0 0 1 * */2".parse().unwrap();
+        // 2023-02-01 was a Wednesday and 2023-04-01 a Saturday
+        assert!(!schedule.matches_day(date(2023, 2, 1)));
+        assert!(schedule.matches_day(date(2023, 4, 1)));
+    }
+
+    #[test]
+    fn next_fire_is_strictly_after_the_given_time() {
+        let schedule: CronSchedule = "30 9 * * *".parse().unwrap();
+        assert_eq!(
+            schedule.next_after(local(2023, 1, 10, 8, 0)),
+            Some(local(2023, 1, 10, 9, 30))
+        );
+        assert_eq!(
+            schedule.next_after(local(2023, 1, 10, 9, 30)),
+            Some(local(2023, 1, 11, 9, 30))
+        );
+    }
+
+    #[test]
+    fn next_fire_skips_to_the_next_matching_month() {
+        let schedule: CronSchedule = "0 12 1 3 *".parse().unwrap();
+        assert_eq!(
+            schedule.next_after(local(2023, 1, 10, 8, 0)),
+            Some(local(2023, 3, 1, 12, 0))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_fire() {
+        let schedule: CronSchedule = "0 0 31 2 *".parse().unwrap();
+        assert_eq!(schedule.next_after(local(2023, 1, 10, 8, 0)), None);
+    }
+}
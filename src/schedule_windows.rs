@@ -0,0 +1,188 @@
+//! Posting at different intervals depending on the time of day.
+//!
+//! `SCHEDULE_WINDOWS` lists named windows, each with a daily time range and
+//! the interval to post at during it, like
+//! `day=08:00-20:00/15m,night=20:00-08:00/1h`. Ranges may wrap past midnight,
+//! and the first window containing the current time wins. Outside all windows
+//! the regular `POST_INTERVAL` applies. In the config file the windows can
+//! also be written as a table:
+//!
+//! ```toml
+//! [schedule_windows.day]
+//! start = "08:00"
+//! end = "20:00"
+//! interval = "15m"
+//! ```
+//!
+//! Times are evaluated in the host's local timezone.
+
+use std::{
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use chrono::NaiveTime;
+
+/// Seconds in a day, for measuring time around midnight.
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Named windows of the day with their own posting interval.
+#[derive(Debug, Clone)]
+pub struct ScheduleWindows {
+    windows: Vec<ScheduleWindow>,
+}
+
+/// A daily time range and the interval to post at during it.
+#[derive(Debug, Clone)]
+pub struct ScheduleWindow {
+    pub name: String,
+    /// Start of the window, inclusive
+    pub start: NaiveTime,
+    /// End of the window, exclusive, before `start` when it wraps past midnight
+    pub end: NaiveTime,
+    pub interval: Duration,
+}
+
+impl FromStr for ScheduleWindows {
+    type Err = anyhow::Error;
+
+    fn from_str(windows: &str) -> anyhow::Result<Self> {
+        let windows = windows
+            .split(',')
+            .map(str::trim)
+            .filter(|window| !window.is_empty())
+            .map(|window| {
+                window
+                    .parse()
+                    .with_context(|| format!("Invalid schedule window '{}'", window))
+            })
+            .collect::<anyhow::Result<Vec<ScheduleWindow>>>()?;
+
+        if windows.is_empty() {
+            bail!("No schedule windows given");
+        }
+
+        Ok(Self { windows })
+    }
+}
+
+impl FromStr for ScheduleWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(window: &str) -> anyhow::Result<Self> {
+        let (name, rest) = window
+            .split_once('=')
+            .context("Expected a window like day=08:00-20:00/15m")?;
+        let (range, interval) = rest
+            .split_once('/')
+            .context("Expected a window like day=08:00-20:00/15m")?;
+        let (start, end) = range
+            .split_once('-')
+            .context("Expected a time range like 08:00-20:00")?;
+
+        let time = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .with_context(|| format!("Invalid time '{}', expected HH:MM", time))
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            bail!("Window starts and ends at the same time");
+        }
+
+        Ok(Self {
+            name: name.trim().to_string(),
+            start,
+            end,
+            interval: parse_interval(interval.trim())?,
+        })
+    }
+}
+
+impl ScheduleWindows {
+    /// The first window containing `time`, if any.
+    pub fn active(&self, time: NaiveTime) -> Option<&ScheduleWindow> {
+        self.windows.iter().find(|window| window.contains(time))
+    }
+
+    /// Time from `time` until the next window starts or ends.
+    pub fn until_next_boundary(&self, time: NaiveTime) -> Duration {
+        self.windows
+            .iter()
+            .flat_map(|window| [window.start, window.end])
+            .map(|boundary| {
+                let seconds = (boundary - time).num_seconds().rem_euclid(SECONDS_PER_DAY);
+                // A boundary at exactly `time` is a whole day away
+                if seconds == 0 {
+                    SECONDS_PER_DAY
+                } else {
+                    seconds
+                }
+            })
+            .min()
+            .map(|seconds| Duration::from_secs(seconds as u64))
+            .unwrap_or(Duration::from_secs(SECONDS_PER_DAY as u64))
+    }
+}
+
+impl ScheduleWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Parse an interval like `90s`, `15m`, or `2h`, with bare numbers in seconds.
+fn parse_interval(interval: &str) -> anyhow::Result<Duration> {
+    let (number, unit_seconds) = match interval.char_indices().last() {
+        Some((index, 's')) => (&interval[..index], 1),
+        Some((index, 'm')) => (&interval[..index], 60),
+        Some((index, 'h')) => (&interval[..index], 60 * 60),
+        _ => (interval, 1),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid interval '{}', expected e.g. 15m", interval))?;
+    if number == 0 {
+        bail!("Interval must be longer than zero");
+    }
+
+    let seconds = number
+        .checked_mul(unit_seconds)
+        .with_context(|| format!("Interval '{}' is too long", interval))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Convert a `[schedule_windows.<name>]` config file table to the setting's
+/// text form.
+pub fn table_to_setting(table: toml::Table) -> anyhow::Result<String> {
+    table
+        .into_iter()
+        .map(|(name, window)| {
+            let field = |field: &str| {
+                window
+                    .get(field)
+                    .and_then(|value| match value {
+                        toml::Value::String(value) => Some(value.clone()),
+                        toml::Value::Integer(value) => Some(value.to_string()),
+                        _ => None,
+                    })
+                    .with_context(|| format!("Schedule window '{}' needs a {}", name, field))
+            };
+            Ok(format!(
+                "{}={}-{}/{}",
+                name,
+                field("start")?,
+                field("end")?,
+                field("interval")?
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map(|windows| windows.join(","))
+}
@@ -0,0 +1,43 @@
+//! Temporary directories for tests that read and write files.
+
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// A directory under the system temp directory, removed again on drop.
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    /// Create an empty directory unique to this test process and `name`.
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("every-frame-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Path of `name` inside the directory.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    /// Write `contents` to `name` inside the directory.
+    pub fn write(&self, name: &str, contents: &[u8]) {
+        fs::write(self.file(name), contents).unwrap();
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,280 @@
+//! Interactive first-run setup.
+//!
+//! Prompts for the Bluesky credentials and movie name, checks that they work
+//! by logging in, which also saves the session, then writes them to `.env` so
+//! the bot can be started straight away.
+
+use std::{
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::{
+        self,
+        Write,
+    },
+    path::Path,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::fs::{
+        OpenOptionsExt,
+        PermissionsExt,
+    },
+    sync::OnceLock,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+
+use crate::{
+    bluesky,
+    config::{
+        FRAMES_DIR,
+        SESSION_FILE,
+    },
+    frame_processing::scan_frame_files,
+};
+
+/// File the settings are written to.
+const ENV_FILE: &str = ".env";
+
+/// Walk through setting up the bot, writing `.env` and the session file.
+pub async fn run() -> anyhow::Result<()> {
+    println!("Setting up the frame bot. Create an app password for the bot's account");
+    println!("under Settings > Privacy and security > App passwords on Bluesky.");
+    println!();
+
+    let env_path = Path::new(ENV_FILE);
+    if env_path.exists() && !confirm(&format!("{} already exists, overwrite it?", ENV_FILE))? {
+        bail!("Setup cancelled, {} was left unchanged", ENV_FILE);
+    }
+
+    let identifier = prompt("Bluesky handle or email")?;
+    let app_password = prompt_hidden("App password")?;
+    let movie_name = prompt("Movie name")?;
+
+    if let Some(parent) = Path::new(SESSION_FILE).parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    println!("Logging in as {}...", identifier);
+    bluesky::login(&identifier, &app_password)
+        .await
+        .context("Login failed, check the handle and app password")?;
+    println!("Logged in, saved the session to {}", SESSION_FILE);
+
+    let contents = [
+        ("BLUESKY_IDENTIFIER", identifier.as_str()),
+        ("BLUESKY_APP_PASSWORD", app_password.as_str()),
+        ("MOVIE_NAME", movie_name.as_str()),
+    ]
+    .iter()
+    .map(|(name, value)| format!("{}={}\n", name, quote(value)))
+    .collect::<String>();
+    write_private(env_path, &contents)
+        .with_context(|| format!("Failed to write settings to {}", ENV_FILE))?;
+    println!("Wrote settings to {}", ENV_FILE);
+
+    match scan_frame_files().await {
+        Ok(frames) if !frames.is_empty() => {
+            println!("Found {} frames in {}/", frames.len(), FRAMES_DIR);
+            println!("Setup complete, start the bot to begin posting.");
+        }
+        _ => {
+            println!(
+                "No frames found yet. Put them in {}/ as 1.jpg, 2.jpg, and so on,",
+                FRAMES_DIR
+            );
+            println!("then start the bot to begin posting.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask for a value until a non-empty one is entered.
+fn prompt(label: &str) -> anyhow::Result<String> {
+    loop {
+        print!("{}: ", label);
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            bail!("Setup cancelled");
+        }
+
+        let value = line.trim();
+        if !value.is_empty() {
+            return Ok(value.to_string());
+        }
+    }
+}
+
+/// Ask for a value without echoing it, when reading from a terminal.
+fn prompt_hidden(label: &str) -> anyhow::Result<String> {
+    #[cfg(unix)]
+    let echo = EchoDisabled::new();
+    let value = prompt(label);
+
+    #[cfg(unix)]
+    if echo.is_some() {
+        // The newline typed by the user wasn't echoed either
+        println!();
+    }
+    value
+}
+
+/// Ask a yes or no question, defaulting to no.
+fn confirm(question: &str) -> anyhow::Result<bool> {
+    print!("{} [y/N]: ", question);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
+
+/// Quote a value for `.env` so it's read back exactly as entered.
+///
+/// Single quotes keep `$` from being expanded like it is inside double quotes.
+/// Single quotes and backslashes are written escaped outside the quotes, as in
+/// a shell. Inside them, a single quote would end the value early, and dotenvy
+/// mistakes a backslash before the closing quote for an escape.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in value.chars() {
+        match c {
+            '\'' | '\\' => {
+                quoted.push_str("'\\");
+                quoted.push(c);
+                quoted.push('\'');
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Write `contents` to `path`, readable only by the owner on unix, since it
+/// holds the app password.
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, an existing one keeps its own
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+/// Terminal settings from before echo was turned off, for restoring them if
+/// setup is interrupted.
+#[cfg(unix)]
+static ORIGINAL_TERMIOS: OnceLock<libc::termios> = OnceLock::new();
+
+/// Signals that would end setup while echo is off.
+#[cfg(unix)]
+const INTERRUPT_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Turns off terminal echo until dropped.
+///
+/// Being interrupted by a signal in the meantime turns echo back on before the
+/// process exits, since `Drop` wouldn't run.
+#[cfg(unix)]
+struct EchoDisabled {
+    original: libc::termios,
+    /// Handlers the interrupt signals had before, restored on drop
+    handlers: [libc::sighandler_t; INTERRUPT_SIGNALS.len()],
+}
+
+#[cfg(unix)]
+impl EchoDisabled {
+    /// Turn off echo on stdin, or return `None` if it isn't a terminal.
+    fn new() -> Option<Self> {
+        // SAFETY: termios is plain data, and tcgetattr fills it in before use
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return None;
+        }
+
+        let original = termios;
+        let _ = ORIGINAL_TERMIOS.set(original);
+        // SAFETY: restore_echo_and_exit only calls async-signal-safe functions
+        let handlers = INTERRUPT_SIGNALS.map(|signal| unsafe {
+            libc::signal(
+                signal,
+                restore_echo_and_exit as *const () as libc::sighandler_t,
+            )
+        });
+
+        termios.c_lflag &= !libc::ECHO;
+        // SAFETY: termios came from tcgetattr on the same descriptor
+        let echo_disabled =
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } == 0;
+        let echo = Self { original, handlers };
+        echo_disabled.then_some(echo)
+    }
+}
+
+/// Signal handler turning echo back on, then exiting as the signal would have.
+#[cfg(unix)]
+extern "C" fn restore_echo_and_exit(signal: libc::c_int) {
+    // SAFETY: tcsetattr, signal, and raise are async-signal-safe, and the
+    // settings came from tcgetattr on the same descriptor
+    unsafe {
+        if let Some(original) = ORIGINAL_TERMIOS.get() {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+        }
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoDisabled {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read by tcgetattr in `new` and the
+        // handlers replaced there
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+            for (signal, handler) in INTERRUPT_SIGNALS.into_iter().zip(self.handlers) {
+                libc::signal(signal, handler);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read a value back the way the bot loads `.env`.
+    fn read_back(value: &str) -> String {
+        let line = format!("VALUE={}\n", quote(value));
+        let (_, value) = dotenvy::from_read_iter(line.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        value
+    }
+
+    #[test]
+    fn quoted_values_read_back_unchanged() {
+        for value in [
+            "abcd-efgh-ijkl-mnop",
+            "pa$$word $HOME ${USER}",
+            "Howl's Moving Castle",
+            "\"quoted\" and \\backslashed\\",
+            "千と千尋の神隠し #1",
+        ] {
+            assert_eq!(read_back(value), value);
+        }
+    }
+}
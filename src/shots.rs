@@ -0,0 +1,103 @@
+//! Shot length metadata used to pace posts like real playback.
+//!
+//! A shots file lists frame ranges with the on-screen duration of each shot,
+//! in seconds of movie time:
+//!
+//! ```toml
+//! [[shot]]
+//! start = 1
+//! end = 240
+//! duration = 10.0
+//! ```
+//!
+//! Each frame of a shot is held for `duration` times the configured time scale
+//! before the next post, so long, lingering shots stay up longer than quick cuts.
+
+use std::{
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{
+    bail,
+    Context,
+};
+use serde::Deserialize;
+
+/// A run of frames making up a single shot.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Shot {
+    /// First frame of the shot (inclusive)
+    pub start: u32,
+    /// Last frame of the shot (inclusive)
+    pub end: u32,
+    /// On-screen duration of the shot in seconds
+    pub duration: f64,
+}
+
+/// Layout of the shots file.
+#[derive(Deserialize)]
+struct ShotsFile {
+    #[serde(default)]
+    shot: Vec<Shot>,
+}
+
+/// Shots loaded from a shots file, sorted by starting frame.
+#[derive(Debug, Clone)]
+pub struct ShotList {
+    shots: Vec<Shot>,
+    /// Real seconds to hold a frame per second of shot duration
+    time_scale: f64,
+}
+
+impl ShotList {
+    /// Load and validate a shots file.
+    ///
+    /// Shots must cover valid 1-based frame ranges with a positive duration,
+    /// and may not overlap.
+    pub fn load(path: &Path, time_scale: f64) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read shots file {}", path.display()))?;
+        let ShotsFile { shot: mut shots } = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse shots file {}", path.display()))?;
+
+        for shot in &shots {
+            if shot.start == 0 || shot.start > shot.end {
+                bail!(
+                    "Shot frames {}-{} are not a valid range",
+                    shot.start,
+                    shot.end
+                );
+            }
+            if !(shot.duration.is_finite() && shot.duration > 0.0) {
+                bail!(
+                    "Shot {}-{} must have a positive duration",
+                    shot.start,
+                    shot.end
+                );
+            }
+        }
+
+        shots.sort_by_key(|shot| shot.start);
+        if let Some(pair) = shots.windows(2).find(|pair| pair[1].start <= pair[0].end) {
+            bail!(
+                "Shots {}-{} and {}-{} overlap",
+                pair[0].start,
+                pair[0].end,
+                pair[1].start,
+                pair[1].end
+            );
+        }
+
+        Ok(Self { shots, time_scale })
+    }
+
+    /// How long to hold `frame` for, or `None` if no shot covers it.
+    pub fn hold_for(&self, frame: u32) -> Option<Duration> {
+        let index = self.shots.partition_point(|shot| shot.end < frame);
+        self.shots
+            .get(index)
+            .filter(|shot| shot.start <= frame)
+            .and_then(|shot| Duration::try_from_secs_f64(shot.duration * self.time_scale).ok())
+    }
+}
@@ -0,0 +1,74 @@
+//! Structural similarity (SSIM) between two grayscale images.
+//!
+//! A small windowed SSIM used to judge how close a recompressed frame looks to
+//! its source. Scores range from -1 to 1, where 1 means the images are identical.
+
+use image::GrayImage;
+
+/// Side length of the square windows SSIM is computed over.
+const WINDOW_SIZE: u32 = 8;
+
+/// Stabilizing constants from the original SSIM paper, for 8-bit samples.
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Compute the mean SSIM of two equally sized grayscale images.
+///
+/// The images are split into non-overlapping 8x8 windows (partial windows at
+/// the edges are included) and the per-window scores are averaged.
+pub fn mean_ssim(reference: &GrayImage, candidate: &GrayImage) -> f64 {
+    debug_assert_eq!(reference.dimensions(), candidate.dimensions());
+
+    let (width, height) = reference.dimensions();
+    let mut total = 0.0;
+    let mut windows = 0u32;
+
+    for y in (0..height).step_by(WINDOW_SIZE as usize) {
+        for x in (0..width).step_by(WINDOW_SIZE as usize) {
+            let window_width = WINDOW_SIZE.min(width - x);
+            let window_height = WINDOW_SIZE.min(height - y);
+            total += window_ssim(reference, candidate, x, y, window_width, window_height);
+            windows += 1;
+        }
+    }
+
+    if windows == 0 {
+        return 1.0;
+    }
+    total / windows as f64
+}
+
+/// SSIM of a single window whose top-left corner is at `(x, y)`.
+fn window_ssim(
+    reference: &GrayImage,
+    candidate: &GrayImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> f64 {
+    let count = (width * height) as f64;
+    let (mut sum_a, mut sum_b) = (0.0, 0.0);
+    let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+
+    for dy in 0..height {
+        for dx in 0..width {
+            let a = reference.get_pixel(x + dx, y + dy).0[0] as f64;
+            let b = candidate.get_pixel(x + dx, y + dy).0[0] as f64;
+            sum_a += a;
+            sum_b += b;
+            sum_aa += a * a;
+            sum_bb += b * b;
+            sum_ab += a * b;
+        }
+    }
+
+    let mean_a = sum_a / count;
+    let mean_b = sum_b / count;
+    let variance_a = sum_aa / count - mean_a * mean_a;
+    let variance_b = sum_bb / count - mean_b * mean_b;
+    let covariance = sum_ab / count - mean_a * mean_b;
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2))
+}
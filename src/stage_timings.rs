@@ -0,0 +1,71 @@
+//! Timing of each stage of preparing and posting a frame.
+//!
+//! The breakdown is logged at debug level, which makes it easy to tell whether
+//! a slow tick was spent reading from slow storage, recompressing, or waiting
+//! on the network.
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use log::*;
+
+/// Time spent in each stage, in the order the stages first ran.
+#[derive(Debug, Clone)]
+pub struct StageTimings {
+    started: Instant,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Default for StageTimings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StageTimings {
+    /// Start timing, counting the total from now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Record a stage that began at `since`, returning the current time so it
+    /// can be used as the start of the next stage.
+    ///
+    /// A stage that runs more than once, like compressing each tile, adds up.
+    pub fn record(&mut self, stage: &'static str, since: Instant) -> Instant {
+        let now = Instant::now();
+        self.add(stage, now - since);
+        now
+    }
+
+    /// Add the stages timed elsewhere, e.g. on a blocking thread.
+    pub fn extend(&mut self, other: StageTimings) {
+        for (stage, elapsed) in other.stages {
+            self.add(stage, elapsed);
+        }
+    }
+
+    /// Log the breakdown for `name` along with the total time since starting.
+    pub fn log(&self, name: &str) {
+        let mut breakdown: Vec<String> = self
+            .stages
+            .iter()
+            .map(|(stage, elapsed)| format!("{} {:.1?}", stage, elapsed))
+            .collect();
+        breakdown.push(format!("total {:.1?}", self.started.elapsed()));
+
+        debug!("Timings for {}: {}", name, breakdown.join(", "));
+    }
+
+    fn add(&mut self, stage: &'static str, elapsed: Duration) {
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => *total += elapsed,
+            None => self.stages.push((stage, elapsed)),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Placeholder substitution for user-configurable text.
+//!
+//! Templates use `{name}` placeholders, e.g. `Frame {frame} of {total}`.
+//! Placeholders without a matching value are left as-is.
+
+#[cfg(feature = "bluesky")]
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(feature = "bluesky")]
+use crate::config::MAX_POST_GRAPHEMES;
+
+/// Default alt text template, matching the bot's original wording.
+pub const DEFAULT_ALT_TEXT_TEMPLATE: &str =
+    "A frame from the movie '{movie}', specifically frame {frame} of {total}";
+
+/// Default description template for `LINK_CARD_URL` link cards.
+#[cfg(feature = "bluesky")]
+pub const DEFAULT_LINK_CARD_DESCRIPTION: &str = "Frame {frame} of {total}";
+
+/// Fill `{name}` placeholders in a template with the given values.
+#[cfg(feature = "bluesky")]
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Join post text parts in order, truncated to the post length limit.
+///
+/// Parts are concatenated as-is, so separators belong in the parts themselves.
+/// Truncation happens on grapheme boundaries and drops text from the end, so
+/// earlier parts like the prefix are kept intact.
+#[cfg(feature = "bluesky")]
+pub fn compose_post_text(parts: &[&str]) -> String {
+    let text = parts.concat();
+    match text.grapheme_indices(true).nth(MAX_POST_GRAPHEMES) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+#[cfg(all(test, feature = "bluesky"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_fills_known_placeholders_only() {
+        let text = render(
+            "Frame {frame} of {total} from {movie}",
+            &[("frame", "7".to_string()), ("total", "10".to_string())],
+        );
+        assert_eq!(text, "Frame 7 of 10 from {movie}");
+    }
+
+    #[test]
+    fn short_post_text_is_kept_whole() {
+        let text = compose_post_text(&["千と千尋の神隠し 🐉", " ", "👨‍👩‍👧"]);
+        assert_eq!(text, "千と千尋の神隠し 🐉 👨‍👩‍👧");
+    }
+
+    #[test]
+    fn long_post_text_is_cut_between_graphemes() {
+        let prefix = "千と千尋の神隠し ";
+        // A family emoji is several characters joined into one grapheme
+        let body = "👨‍👩‍👧".repeat(MAX_POST_GRAPHEMES);
+        let text = compose_post_text(&[prefix, &body]);
+
+        assert_eq!(text.graphemes(true).count(), MAX_POST_GRAPHEMES);
+        assert!(text.starts_with(prefix));
+        assert!(text.ends_with("👨‍👩‍👧"));
+        assert!(body.starts_with(&text[prefix.len()..]));
+    }
+
+    #[test]
+    fn post_text_at_the_limit_is_not_cut() {
+        let text = "映".repeat(MAX_POST_GRAPHEMES);
+        assert_eq!(compose_post_text(&[&text]), text);
+    }
+}
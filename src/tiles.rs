@@ -0,0 +1,129 @@
+//! Splitting frames into a grid of tiles posted as one multi-image post.
+//!
+//! Posting a very high resolution frame as tiles keeps more detail than
+//! shrinking it to fit under the upload size limit as a single image.
+
+use std::str::FromStr;
+
+use anyhow::{
+    bail,
+    Context,
+};
+use image::{
+    DynamicImage,
+    GenericImageView,
+};
+
+/// Maximum number of images Bluesky allows in a single post.
+pub const MAX_IMAGES_PER_POST: u32 = 4;
+
+/// How many columns and rows to split a frame into, parsed from e.g. `2x1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileLayout {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+/// One piece of a split frame.
+pub struct Tile {
+    pub image: DynamicImage,
+    /// Human readable position within the frame, e.g. "top left"
+    #[cfg(feature = "bluesky")]
+    pub position: String,
+}
+
+impl FromStr for TileLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(layout: &str) -> anyhow::Result<Self> {
+        let (columns, rows) = layout
+            .split_once('x')
+            .with_context(|| format!("Tile layout '{}' must look like 2x1", layout))?;
+        let columns: u32 = columns
+            .parse()
+            .with_context(|| format!("Invalid column count '{}'", columns))?;
+        let rows: u32 = rows
+            .parse()
+            .with_context(|| format!("Invalid row count '{}'", rows))?;
+
+        let tiles = columns.saturating_mul(rows);
+        if !(2..=MAX_IMAGES_PER_POST).contains(&tiles) {
+            bail!(
+                "Tile layout '{}' makes {} tiles, but posts need 2 to {} images",
+                layout,
+                tiles,
+                MAX_IMAGES_PER_POST
+            );
+        }
+
+        Ok(Self { columns, rows })
+    }
+}
+
+impl TileLayout {
+    /// Total number of tiles in the layout.
+    pub fn count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Split an image into tiles, left to right then top to bottom.
+    ///
+    /// When the image doesn't divide evenly, the last column and row absorb
+    /// the leftover pixels so no part of the frame is lost.
+    pub fn split(&self, image: &DynamicImage) -> Vec<Tile> {
+        let (width, height) = image.dimensions();
+        let (tile_width, tile_height) = (width / self.columns, height / self.rows);
+        let mut tiles = Vec::with_capacity(self.count() as usize);
+
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let x = column * tile_width;
+                let y = row * tile_height;
+                let w = if column + 1 == self.columns {
+                    width - x
+                } else {
+                    tile_width
+                };
+                let h = if row + 1 == self.rows {
+                    height - y
+                } else {
+                    tile_height
+                };
+
+                tiles.push(Tile {
+                    image: image.crop_imm(x, y, w, h),
+                    #[cfg(feature = "bluesky")]
+                    position: self.position(column, row),
+                });
+            }
+        }
+
+        tiles
+    }
+
+    /// Describe where a tile sits, e.g. "left", "bottom", or "top right".
+    #[cfg(feature = "bluesky")]
+    fn position(&self, column: u32, row: u32) -> String {
+        const HORIZONTAL: [&[&str]; 4] = [
+            &[],
+            &["left", "right"],
+            &["left", "center", "right"],
+            &["far left", "center left", "center right", "far right"],
+        ];
+        const VERTICAL: [&[&str]; 4] = [
+            &[],
+            &["top", "bottom"],
+            &["top", "middle", "bottom"],
+            &["top", "upper middle", "lower middle", "bottom"],
+        ];
+
+        let horizontal = HORIZONTAL[self.columns as usize - 1].get(column as usize);
+        let vertical = VERTICAL[self.rows as usize - 1].get(row as usize);
+
+        match (vertical, horizontal) {
+            (Some(vertical), Some(horizontal)) => format!("{vertical} {horizontal}"),
+            (Some(part), None) | (None, Some(part)) => part.to_string(),
+            (None, None) => "whole frame".to_string(),
+        }
+    }
+}
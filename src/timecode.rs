@@ -0,0 +1,163 @@
+//! Conversion between movie timecodes or percentages and frame numbers.
+
+use std::time::Duration;
+
+use anyhow::{
+    bail,
+    Context,
+};
+
+/// Parse a timecode such as `01:23:45`, `23:45`, or `01:23:45.5` into a duration.
+///
+/// Hours and minutes are whole numbers, while the final seconds field may be
+/// fractional. Every field after the first must be below 60.
+pub fn parse_timecode(timecode: &str) -> anyhow::Result<Duration> {
+    let parts: Vec<&str> = timecode.trim().split(':').collect();
+    if parts.len() > 3 {
+        bail!("Invalid timecode '{}', expected HH:MM:SS", timecode);
+    }
+
+    let (seconds_part, leading) = parts.split_last().context("Empty timecode")?;
+    let seconds: f64 = seconds_part
+        .parse()
+        .ok()
+        .filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+        .with_context(|| format!("Invalid seconds in timecode '{}'", timecode))?;
+    if !leading.is_empty() && seconds >= 60.0 {
+        bail!("Seconds must be below 60 in timecode '{}'", timecode);
+    }
+
+    let mut total_seconds = 0.0;
+    for (index, part) in leading.iter().enumerate() {
+        let value: u32 = part
+            .parse()
+            .with_context(|| format!("Invalid field '{}' in timecode '{}'", part, timecode))?;
+        // Minutes are bounded when preceded by hours
+        if index > 0 && value >= 60 {
+            bail!("Minutes must be below 60 in timecode '{}'", timecode);
+        }
+        total_seconds = total_seconds * 60.0 + value as f64;
+    }
+
+    Ok(Duration::from_secs_f64(total_seconds * 60.0 + seconds))
+}
+
+/// Parse a frame file name like `00_01_23_456` into its position in the movie.
+///
+/// The fields are hours, minutes, seconds, and milliseconds.
+pub fn parse_file_timecode(name: &str) -> Option<Duration> {
+    let fields: Vec<u64> = name
+        .split('_')
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()?;
+    let &[hours, minutes, seconds, millis] = fields.as_slice() else {
+        return None;
+    };
+
+    if minutes >= 60 || seconds >= 60 || millis >= 1000 {
+        return None;
+    }
+    Some(Duration::from_millis(
+        ((hours * 60 + minutes) * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+/// Convert a position in the movie to the 1-based number of the frame on screen.
+pub fn frame_at(position: Duration, fps: f64) -> u32 {
+    (position.as_secs_f64() * fps).floor() as u32 + 1
+}
+
+/// Time from the start of the movie until a 1-based frame appears.
+#[cfg(feature = "bluesky")]
+pub fn frame_position(frame: u32, fps: f64) -> Duration {
+    Duration::from_secs_f64(frame.saturating_sub(1) as f64 / fps)
+}
+
+/// Format the time a 1-based frame appears at as `HH:MM:SS:FF`.
+///
+/// `FF` counts frames within the second, starting from 0.
+pub fn format_timecode(frame: u32, fps: f64) -> String {
+    let index = frame.saturating_sub(1) as f64;
+    let seconds = (index / fps).floor();
+    let frames = (index - seconds * fps).floor() as u64;
+    let seconds = seconds as u64;
+
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        frames
+    )
+}
+
+/// Parse a position through the movie such as `50%` into a fraction from 0 to 1.
+///
+/// The `%` sign is optional, but the value must be between 0 and 100.
+pub fn parse_percentage(percentage: &str) -> anyhow::Result<f64> {
+    let value = percentage.trim();
+    let value = value.strip_suffix('%').unwrap_or(value);
+
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value: &f64| (0.0..=100.0).contains(value))
+        .map(|value| value / 100.0)
+        .with_context(|| {
+            format!(
+                "Invalid percentage '{}', expected a value from 0% to 100%",
+                percentage
+            )
+        })
+}
+
+/// Convert a fraction of the way through the movie to a 1-based frame number.
+///
+/// Rounds to the nearest frame, so 0% is the first frame and 100% the last.
+pub fn frame_at_fraction(fraction: f64, total_frames: u32) -> u32 {
+    let frame = (total_frames as f64 * fraction).round() as u32;
+    frame.clamp(1, total_frames.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentages_parse_with_or_without_the_sign() {
+        assert_eq!(parse_percentage("0%").unwrap(), 0.0);
+        assert_eq!(parse_percentage("100%").unwrap(), 1.0);
+        assert_eq!(parse_percentage(" 50 % ").unwrap(), 0.5);
+        assert_eq!(parse_percentage("12.5").unwrap(), 0.125);
+    }
+
+    #[test]
+    fn out_of_range_percentages_are_rejected() {
+        for percentage in ["-1%", "100.1%", "250", "NaN%", "inf"] {
+            assert!(parse_percentage(percentage).is_err(), "{}", percentage);
+        }
+    }
+
+    #[test]
+    fn malformed_percentages_are_rejected() {
+        for percentage in ["", "%", "half", "50%%", "5 0%", "0x10"] {
+            assert!(parse_percentage(percentage).is_err(), "{}", percentage);
+        }
+    }
+
+    #[test]
+    fn ends_of_the_movie_are_the_first_and_last_frames() {
+        assert_eq!(frame_at_fraction(0.0, 1000), 1);
+        assert_eq!(frame_at_fraction(1.0, 1000), 1000);
+        assert_eq!(frame_at_fraction(0.5, 1000), 500);
+        assert_eq!(frame_at_fraction(0.0005, 1000), 1);
+    }
+
+    #[test]
+    fn fractions_past_the_ends_are_clamped() {
+        assert_eq!(frame_at_fraction(-0.5, 1000), 1);
+        assert_eq!(frame_at_fraction(1.5, 1000), 1000);
+        assert_eq!(frame_at_fraction(1.0, 0), 1);
+    }
+}